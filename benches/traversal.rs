@@ -0,0 +1,78 @@
+//! Traversal benchmarks on a large tree
+//!
+//! Run with `cargo bench --bench traversal`. The `children` and
+//! `children_iter` walks visit the same nodes and differ only in whether a
+//! `Vec` of child IDs is allocated per node, which is the cost the tree's
+//! own traversals avoid by iterating the child set directly.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use jangal::Tree;
+
+const NODES: usize = 500_000;
+const BRANCHING: usize = 4;
+const RUNS: u32 = 5;
+
+fn build_tree() -> Tree<usize> {
+    let parents: Vec<Option<usize>> = (0..NODES)
+        .map(|i| {
+            if i == 0 {
+                None
+            } else {
+                Some((i - 1) / BRANCHING)
+            }
+        })
+        .collect();
+    Tree::from_parent_array(&parents).expect("parent array is a valid tree")
+}
+
+fn walk_allocating(tree: &Tree<usize>, start: f64) -> usize {
+    let mut count = 0;
+    let mut stack = vec![start];
+    while let Some(id) = stack.pop() {
+        if let Some(node) = tree.get_node(id) {
+            count += 1;
+            stack.extend(node.children());
+        }
+    }
+    count
+}
+
+fn walk_borrowing(tree: &Tree<usize>, start: f64) -> usize {
+    let mut count = 0;
+    let mut stack = vec![start];
+    while let Some(id) = stack.pop() {
+        if let Some(node) = tree.get_node(id) {
+            count += 1;
+            stack.extend(node.children_iter());
+        }
+    }
+    count
+}
+
+fn bench<R>(name: &str, mut f: impl FnMut() -> R) {
+    black_box(f());
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        black_box(f());
+        best = best.min(started.elapsed());
+    }
+    println!("{:<24} {:>10.2?}", name, best);
+}
+
+fn main() {
+    let tree = build_tree();
+    let root = tree.root_id().expect("tree has a root");
+    println!("{} nodes, best of {} runs", NODES, RUNS);
+
+    bench("walk (children)", || walk_allocating(&tree, root));
+    bench("walk (children_iter)", || walk_borrowing(&tree, root));
+    bench("dfs", || tree.dfs(root).len());
+    bench("bfs", || tree.bfs(root).len());
+    bench("preorder", || tree.preorder(root).len());
+    bench("postorder", || tree.postorder(root).len());
+    bench("num_nodes", || tree.num_nodes(root));
+    bench("height", || tree.height(root));
+}
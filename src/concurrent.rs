@@ -0,0 +1,173 @@
+use crate::Tree;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A [`Tree`] behind a reader-writer lock, for sharing between threads
+///
+/// `Tree<T>` is `Send` and `Sync` whenever `T` is, so a `&Tree<T>` can
+/// already be read from several threads at once; every mutation goes
+/// through `&mut self` and so cannot race. What a plain `Tree` lacks is a
+/// way to mutate it while other threads hold on to it. `SharedTree` wraps
+/// it in an `Arc<RwLock<_>>`: clones are cheap handles to the same tree,
+/// any number of [`SharedTree::read`] guards can be held at once, and a
+/// [`SharedTree::write`] guard waits for them and then has the tree to
+/// itself. This suits read-mostly workloads.
+///
+/// A thread that panics while holding a write guard does not poison the
+/// tree for everyone else: later guards are still handed out, and see
+/// whatever edits were made before the panic.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::{Node, SharedTree, Tree};
+/// use std::thread;
+///
+/// let shared = SharedTree::new(Tree::new());
+/// let root_id = shared.write().add_node(Node::new("root")).unwrap();
+///
+/// let writer = {
+///     let shared = shared.clone();
+///     thread::spawn(move || {
+///         let mut tree = shared.write();
+///         tree.add_child_value(root_id, "child").unwrap();
+///     })
+/// };
+/// writer.join().unwrap();
+///
+/// assert_eq!(shared.read().size(), 2);
+/// assert_eq!(shared.with_read(|tree| tree.height(root_id)), 1);
+/// ```
+#[derive(Debug)]
+pub struct SharedTree<T> {
+    inner: Arc<RwLock<Tree<T>>>,
+}
+
+impl<T> SharedTree<T> {
+    /// Wrap a tree for sharing
+    pub fn new(tree: Tree<T>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(tree)),
+        }
+    }
+
+    /// Lock the tree for reading, waiting for any writer to finish
+    pub fn read(&self) -> RwLockReadGuard<'_, Tree<T>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the tree for writing, waiting for all other guards to drop
+    pub fn write(&self) -> RwLockWriteGuard<'_, Tree<T>> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run `f` with the tree locked for reading and return its result
+    pub fn with_read<R>(&self, f: impl FnOnce(&Tree<T>) -> R) -> R {
+        f(&self.read())
+    }
+
+    /// Run `f` with the tree locked for writing and return its result
+    pub fn with_write<R>(&self, f: impl FnOnce(&mut Tree<T>) -> R) -> R {
+        f(&mut self.write())
+    }
+
+    /// Take the tree back out if this is the last handle to it
+    ///
+    /// Returns `Err(self)` unchanged while other clones are still alive.
+    pub fn try_into_inner(self) -> Result<Tree<T>, Self> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(lock) => Ok(lock.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(inner) => Err(Self { inner }),
+        }
+    }
+}
+
+impl<T> Clone for SharedTree<T> {
+    /// Create another handle to the same tree
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> From<Tree<T>> for SharedTree<T> {
+    fn from(tree: Tree<T>) -> Self {
+        Self::new(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vEB, FrozenTree, Node, BST};
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_types_are_send_and_sync() {
+        assert_send_sync::<Node<String>>();
+        assert_send_sync::<Tree<String>>();
+        assert_send_sync::<FrozenTree<String>>();
+        assert_send_sync::<BST<String>>();
+        assert_send_sync::<vEB>();
+        assert_send_sync::<SharedTree<String>>();
+    }
+
+    #[test]
+    fn test_shared_tree_across_threads() {
+        let shared = SharedTree::from(Tree::new());
+        let root_id = shared.write().add_node(Node::new(0)).unwrap();
+
+        let writers: Vec<_> = (1..=4)
+            .map(|worker| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for i in 0..25 {
+                        shared.with_write(|tree| tree.add_child_value(root_id, worker * 100 + i));
+                    }
+                })
+            })
+            .collect();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..25 {
+                        let tree = shared.read();
+                        assert!(tree.is_tree());
+                        assert_eq!(tree.num_nodes(root_id), tree.size());
+                    }
+                })
+            })
+            .collect();
+        for handle in writers.into_iter().chain(readers) {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(shared.read().size(), 101);
+        let other = shared.clone().try_into_inner().unwrap_err();
+        drop(other);
+        let tree = shared.try_into_inner().unwrap();
+        assert_eq!(tree[root_id].num_children(), 100);
+    }
+
+    #[test]
+    fn test_shared_tree_survives_panicking_writer() {
+        let shared = SharedTree::new(Tree::from_parent_array(&[None, Some(0)]).unwrap());
+
+        let panicking = {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                let mut tree = shared.write();
+                tree.get_node_mut(1.0).unwrap().value = 10;
+                panic!("writer failed");
+            })
+        };
+        assert!(panicking.join().is_err());
+
+        assert_eq!(shared.read()[1.0].value, 10);
+        shared.write().get_node_mut(1.0).unwrap().value = 20;
+        assert_eq!(shared.with_read(|tree| tree[1.0].value), 20);
+    }
+}
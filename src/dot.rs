@@ -0,0 +1,520 @@
+use crate::{FloatId, Node, Number, Tree, TreeError};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A lexical token of the supported DOT subset
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Id(String),
+    Arrow,
+    Undirected,
+    Equals,
+    Comma,
+    Semi,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+}
+
+impl Tree<String> {
+    /// Build a tree from a simple subset of Graphviz DOT
+    ///
+    /// Accepts a single `digraph` (optionally `strict` and named) whose
+    /// statements are nodes (`a;`) and edges (`a -> b;`, or chains such as
+    /// `a -> b -> c;`). Each distinct node name becomes a node holding that
+    /// name as its value, with IDs `0, 1, 2, ...` in order of first
+    /// appearance, and every edge becomes a parent→child link. The node
+    /// without a parent is the root. Names may be bare identifiers,
+    /// numerals or double-quoted strings. Comments, attribute lists
+    /// (`[label="x"]`), `node`/`edge`/`graph` defaults and `key = value`
+    /// graph attributes are accepted and ignored. An empty digraph produces
+    /// an empty tree. [`Tree::to_dot`] writes trees in this subset.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TreeError::Parse`] for input outside the subset, including
+    /// undirected graphs and subgraphs, [`TreeError::MultipleParents`] if a
+    /// node has edges from two different nodes, [`TreeError::MultipleRoots`]
+    /// or [`TreeError::MissingRoot`] if there is not exactly one root, and
+    /// [`TreeError::Cycle`] if the edges form a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, TreeError};
+    ///
+    /// let tree = Tree::from_dot(
+    ///     r#"digraph files {
+    ///         // directories point at their entries
+    ///         root -> src -> "main.rs";
+    ///         root -> README [shape=box];
+    ///     }"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(tree.size(), 4);
+    /// assert_eq!(tree.root().unwrap().value, "root");
+    /// assert_eq!(tree.values_preorder_cloned(0.0).len(), 4);
+    /// assert_eq!(tree[2.0].value, "main.rs");
+    /// assert_eq!(tree[2.0].parent(), Some(1.0));
+    ///
+    /// assert_eq!(
+    ///     Tree::from_dot("digraph { a -> c; b -> d; }").unwrap_err(),
+    ///     TreeError::MultipleRoots
+    /// );
+    /// ```
+    pub fn from_dot(s: &str) -> Result<Tree<String>, TreeError> {
+        let tokens = tokenize(s)?;
+        let Parser { names, edges, .. } = Parser::new(tokens).parse()?;
+
+        let mut parents: Vec<Option<usize>> = vec![None; names.len()];
+        for (parent, child) in edges {
+            if parent == child {
+                return Err(TreeError::Cycle);
+            }
+            match parents[child] {
+                None => parents[child] = Some(parent),
+                Some(existing) if existing == parent => {}
+                Some(_) => return Err(TreeError::MultipleParents),
+            }
+        }
+        // Reuse the parent-array checks for roots and cycles
+        Tree::from_parent_array(&parents)?;
+
+        let mut tree = Tree::new();
+        for (index, name) in names.into_iter().enumerate() {
+            tree.add_node_no_root(Node::with_id(name, index as Number));
+        }
+        if let Some(root) = parents.iter().position(Option::is_none) {
+            tree.set_root(root as Number);
+        }
+        for (index, parent) in parents.into_iter().enumerate() {
+            if let Some(parent) = parent {
+                if let Some(node) = tree.get_node_mut(parent as Number) {
+                    node.add_child(index as Number);
+                }
+                if let Some(node) = tree.get_node_mut(index as Number) {
+                    node.set_parent(parent as Number);
+                }
+            }
+        }
+        Ok(tree)
+    }
+}
+
+impl<T: fmt::Display> Tree<T> {
+    /// Write the tree as a Graphviz `digraph`
+    ///
+    /// The counterpart of [`Tree::from_dot`]. Each node is named by its
+    /// value's `Display` output in double quotes, and each node's `parent`
+    /// link becomes an edge. Nodes are listed in [`Tree::dfs_sorted`] order
+    /// from the root, then any nodes the root does not reach in ascending
+    /// ID order, so `from_dot` numbers them in that same order.
+    ///
+    /// `from_dot` identifies nodes by name, so the round trip keeps the
+    /// tree's shape only if every value displays differently; nodes whose
+    /// values display the same are merged. Double quotes in values are
+    /// escaped, but a backslash right before a closing quote is not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    /// let dot = tree.to_dot();
+    /// assert_eq!(
+    ///     dot,
+    ///     "digraph {\n    \"0\";\n    \"0\" -> \"1\";\n    \"1\" -> \"3\";\n    \"0\" -> \"2\";\n}\n"
+    /// );
+    ///
+    /// let parsed = Tree::from_dot(&dot).unwrap();
+    /// assert_eq!(parsed.to_dot(), dot);
+    /// assert_eq!(parsed[2.0].value, "3");
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut order: Vec<&Node<T>> = match self.root_id {
+            Some(root) => self.dfs_sorted(root.value()),
+            None => Vec::new(),
+        };
+        let reached: HashSet<FloatId> = order.iter().map(|node| FloatId::from(node.id)).collect();
+        let mut rest: Vec<(&FloatId, &Node<T>)> = self
+            .nodes
+            .iter()
+            .filter(|(id, _)| !reached.contains(id))
+            .collect();
+        rest.sort_unstable_by_key(|(&id, _)| id);
+        order.extend(rest.into_iter().map(|(_, node)| node));
+
+        let mut dot = String::from("digraph {\n");
+        for node in order {
+            let name = quote(&node.value);
+            let parent = node.parent.and_then(|parent_id| self.nodes.get(&parent_id));
+            let line = match parent {
+                Some(parent) => format!("    {} -> {};\n", quote(&parent.value), name),
+                None => format!("    {};\n", name),
+            };
+            dot.push_str(&line);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A value as a double-quoted DOT ID
+fn quote(value: &impl fmt::Display) -> String {
+    format!("\"{}\"", value.to_string().replace('"', "\\\""))
+}
+
+/// Split DOT input into tokens, each paired with its 1-based line
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, TreeError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let token_line = line;
+        let at_line_start = line_start;
+        line_start = false;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                line_start = true;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                line_start = at_line_start;
+                continue;
+            }
+            // Preprocessor-style output lines, as Graphviz ignores them
+            '#' if at_line_start => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => return Err(TreeError::Parse { line: token_line }),
+                    }
+                }
+                continue;
+            }
+            '-' if chars.next_if_eq(&'>').is_some() => Token::Arrow,
+            '-' if chars.next_if_eq(&'-').is_some() => Token::Undirected,
+            '"' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.next_if_eq(&'"').is_some() => name.push('"'),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            name.push(c);
+                        }
+                        None => return Err(TreeError::Parse { line: token_line }),
+                    }
+                }
+                Token::Id(name)
+            }
+            c if is_id_char(c) || c == '-' => {
+                let mut name = String::from(c);
+                while let Some(c) = chars.next_if(|&c| is_id_char(c)) {
+                    name.push(c);
+                }
+                if name == "-" {
+                    return Err(TreeError::Parse { line: token_line });
+                }
+                Token::Id(name)
+            }
+            '=' => Token::Equals,
+            ',' => Token::Comma,
+            ';' => Token::Semi,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            _ => return Err(TreeError::Parse { line: token_line }),
+        };
+        tokens.push((token, token_line));
+    }
+
+    Ok(tokens)
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Recursive-descent parser over the tokens of a single digraph
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<(Token, usize)>) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            names: Vec::new(),
+            index: HashMap::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    /// Parse the whole graph, filling in `names` and `(parent, child)`
+    /// index pairs in `edges`
+    fn parse(mut self) -> Result<Self, TreeError> {
+        if self.peek_keyword("strict") {
+            self.pos += 1;
+        }
+        if !self.peek_keyword("digraph") {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        if let Some(Token::Id(_)) = self.peek() {
+            self.pos += 1;
+        }
+        self.expect(&Token::LBrace)?;
+
+        loop {
+            match self.next() {
+                Some(Token::RBrace) => break,
+                Some(Token::Semi) => {}
+                Some(Token::Id(name)) => self.statement(name)?,
+                _ => return Err(self.error()),
+            }
+        }
+
+        if self.peek().is_some() {
+            return Err(self.error());
+        }
+        Ok(self)
+    }
+
+    /// Parse the rest of a statement that starts with the ID `first`
+    fn statement(&mut self, first: String) -> Result<(), TreeError> {
+        let is_keyword = |k: &str| first.eq_ignore_ascii_case(k);
+        if is_keyword("subgraph") {
+            return Err(self.error());
+        }
+        if ["node", "edge", "graph"].into_iter().any(is_keyword) {
+            return self.attributes();
+        }
+        if self.peek() == Some(&Token::Equals) {
+            self.pos += 1;
+            return match self.next() {
+                Some(Token::Id(_)) => Ok(()),
+                _ => Err(self.error()),
+            };
+        }
+
+        let mut previous = self.intern(first);
+        while self.peek() == Some(&Token::Arrow) {
+            self.pos += 1;
+            match self.next() {
+                Some(Token::Id(name)) => {
+                    let next = self.intern(name);
+                    self.edges.push((previous, next));
+                    previous = next;
+                }
+                _ => return Err(self.error()),
+            }
+        }
+        if self.peek() == Some(&Token::LBracket) {
+            self.attributes()?;
+        }
+        Ok(())
+    }
+
+    /// Skip an attribute list such as `[label="x", shape=box]`
+    fn attributes(&mut self) -> Result<(), TreeError> {
+        self.expect(&Token::LBracket)?;
+        loop {
+            match self.next() {
+                Some(Token::RBracket) => return Ok(()),
+                Some(Token::Id(_) | Token::Equals | Token::Comma | Token::Semi) => {}
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn intern(&mut self, name: String) -> usize {
+        if let Some(&index) = self.index.get(&name) {
+            return index;
+        }
+        let index = self.names.len();
+        self.index.insert(name.clone(), index);
+        self.names.push(name);
+        index
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(name)) if name.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), TreeError> {
+        match self.next() {
+            Some(token) if token == *expected => Ok(()),
+            _ => Err(self.error()),
+        }
+    }
+
+    /// A parse error at the most recently consumed token
+    fn error(&self) -> TreeError {
+        let index = self
+            .pos
+            .saturating_sub(1)
+            .min(self.tokens.len().saturating_sub(1));
+        let line = self.tokens.get(index).map_or(1, |&(_, line)| line);
+        TreeError::Parse { line }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_dot_builds_tree() {
+        let dot = r#"
+# generated by a script
+strict digraph "org chart" {
+    graph [rankdir=LR];
+    node [shape=box];
+    rankdir = TB;
+    /* leadership */
+    ceo -> cto -> "lead \"infra\"";
+    ceo -> cfo [weight=2];
+    cto -> lead_app;
+    cfo; ceo -> cto
+}
+"#;
+        let tree = Tree::from_dot(dot).unwrap();
+        assert!(tree.is_tree());
+        assert_eq!(tree.size(), 5);
+        assert_eq!(tree.root_id(), Some(0.0));
+
+        let values = |id: Number| tree[id].value.as_str();
+        assert_eq!(values(0.0), "ceo");
+        assert_eq!(values(1.0), "cto");
+        assert_eq!(values(2.0), "lead \"infra\"");
+        assert_eq!(values(3.0), "cfo");
+        assert_eq!(values(4.0), "lead_app");
+        assert_eq!(tree[4.0].parent(), Some(1.0));
+        assert_eq!(
+            tree.edge_list(),
+            vec![(0.0, 1.0), (0.0, 3.0), (1.0, 2.0), (1.0, 4.0)]
+        );
+
+        assert!(Tree::from_dot("digraph {}").unwrap().is_empty());
+        assert_eq!(Tree::from_dot("digraph { -1 -> 2.5 }").unwrap().size(), 2);
+    }
+
+    #[test]
+    fn test_from_dot_rejects_non_trees() {
+        assert_eq!(
+            Tree::from_dot("digraph { a -> b; c; }").unwrap_err(),
+            TreeError::MultipleRoots
+        );
+        assert_eq!(
+            Tree::from_dot("digraph { a -> b -> a; }").unwrap_err(),
+            TreeError::MissingRoot
+        );
+        assert_eq!(
+            Tree::from_dot("digraph { r -> a; a -> b -> c -> b; }").unwrap_err(),
+            TreeError::MultipleParents
+        );
+        assert_eq!(
+            Tree::from_dot("digraph { r; a -> b -> c -> a; }").unwrap_err(),
+            TreeError::Cycle
+        );
+        assert_eq!(
+            Tree::from_dot("digraph { a -> a; }").unwrap_err(),
+            TreeError::Cycle
+        );
+        assert_eq!(
+            Tree::from_dot("digraph { a -> b; a -> b; }")
+                .unwrap()
+                .size(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_from_dot_syntax_errors() {
+        let parse_error = |s: &str| match Tree::from_dot(s) {
+            Err(TreeError::Parse { line }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+
+        assert_eq!(parse_error("graph { a -- b; }"), 1);
+        assert_eq!(parse_error("digraph {\n a -> b;\n a -- c;\n}"), 3);
+        assert_eq!(parse_error("digraph {\n a ->\n}"), 3);
+        assert_eq!(parse_error("digraph { subgraph s { a } }"), 1);
+        assert_eq!(parse_error("digraph { a -> b;"), 1);
+        assert_eq!(parse_error("digraph { a } b"), 1);
+        assert_eq!(parse_error("digraph {\n\n \"open }"), 3);
+        assert_eq!(parse_error("digraph { a /* open }"), 1);
+        assert_eq!(parse_error("digraph { a [label=x }"), 1);
+        assert_eq!(parse_error(""), 1);
+    }
+
+    #[test]
+    fn test_to_dot_round_trip() {
+        //        root
+        //       /    \
+        //     src   "a \"b\""
+        //      |
+        //   main.rs
+        let mut tree = Tree::new();
+        let root = tree.add_node(Node::new("root".to_string())).unwrap();
+        let src = tree.add_child_value(root, "src".to_string()).unwrap();
+        tree.add_child_value(root, "a \"b\"".to_string()).unwrap();
+        tree.add_child_value(src, "main.rs".to_string()).unwrap();
+
+        let dot = tree.to_dot();
+        let parsed = Tree::from_dot(&dot).unwrap();
+        assert!(parsed.is_tree());
+        assert_eq!(parsed, tree);
+        assert_eq!(parsed.to_dot(), dot);
+        assert_eq!(parsed[0.0].value, "root");
+
+        // Nodes outside the root's subtree are kept
+        tree.add_node(Node::new("orphan".to_string()));
+        assert!(tree.to_dot().contains("    \"orphan\";\n"));
+
+        let empty: Tree<i32> = Tree::new();
+        assert_eq!(empty.to_dot(), "digraph {\n}\n");
+        assert!(Tree::from_dot(&empty.to_dot()).unwrap().is_empty());
+    }
+}
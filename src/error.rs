@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors returned by fallible tree operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeError {
+    /// The tree has no nodes
+    Empty,
+    /// More than one node has no parent
+    MultipleRoots,
+    /// No node is without a parent, so there is no root
+    MissingRoot,
+    /// A node refers to a parent that does not exist
+    InvalidParent { index: usize, parent: usize },
+    /// The parent links form a cycle
+    Cycle,
+    /// A node is listed as a child by more than one node
+    MultipleParents,
+    /// Parent and child links disagree or refer to missing nodes
+    InconsistentLinks,
+    /// An edge would connect a node to itself
+    SelfLoop,
+    /// A recursive traversal went deeper than the configured limit
+    TooDeep,
+    /// Text input could not be parsed; `line` is 1-based
+    Parse { line: usize },
+    /// An ID map does not map the tree's node IDs one-to-one
+    InvalidIdMap,
+    /// Values are not in strictly increasing binary search tree order
+    NotSearchOrder,
+    /// A node with this ID does not exist
+    MissingNode,
+    /// A node already has the maximum number of children allowed
+    TooManyChildren { max: usize },
+    /// The tree's root is not its one parentless node
+    RootMismatch,
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::Empty => write!(f, "tree is empty"),
+            TreeError::MultipleRoots => write!(f, "tree has more than one root"),
+            TreeError::MissingRoot => write!(f, "tree has no root"),
+            TreeError::InvalidParent { index, parent } => {
+                write!(f, "node {} has out-of-range parent {}", index, parent)
+            }
+            TreeError::Cycle => write!(f, "parent links form a cycle"),
+            TreeError::MultipleParents => write!(f, "node has more than one parent"),
+            TreeError::InconsistentLinks => {
+                write!(
+                    f,
+                    "parent and child links disagree or refer to missing nodes"
+                )
+            }
+            TreeError::SelfLoop => write!(f, "edge would connect a node to itself"),
+            TreeError::TooDeep => write!(f, "tree is deeper than the recursion limit"),
+            TreeError::Parse { line } => write!(f, "could not parse input at line {}", line),
+            TreeError::InvalidIdMap => {
+                write!(f, "ID map is not a bijection over the tree's node IDs")
+            }
+            TreeError::NotSearchOrder => {
+                write!(f, "values are not in binary search tree order")
+            }
+            TreeError::MissingNode => write!(f, "node does not exist"),
+            TreeError::TooManyChildren { max } => {
+                write!(f, "node already has the maximum of {} children", max)
+            }
+            TreeError::RootMismatch => write!(f, "root is not the node without a parent"),
+        }
+    }
+}
+
+impl Error for TreeError {}
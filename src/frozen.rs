@@ -0,0 +1,430 @@
+use crate::{FloatId, Node, Number, Tree, TreeLike};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Index;
+
+/// An immutable, read-optimized snapshot of a [`Tree`]
+///
+/// Built with [`Tree::freeze`]. Nodes are stored in a `Vec` in preorder
+/// (children in ascending ID order, as in [`Tree::dfs_sorted`]), so every
+/// subtree occupies a contiguous range of slots. The depth, height and
+/// subtree size of every node are computed once when freezing, which makes
+/// [`FrozenTree::depth`], [`FrozenTree::height`] and
+/// [`FrozenTree::num_nodes`] O(1) and [`FrozenTree::preorder`] a slice walk.
+///
+/// The snapshot has no mutation methods. Nodes keep their IDs, values and
+/// links, so the usual [`Node`] accessors still work on them.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::Tree;
+///
+/// //     0
+/// //    / \
+/// //   1   2
+/// //   |
+/// //   3
+/// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+/// let frozen = tree.freeze();
+///
+/// assert_eq!(frozen.size(), 4);
+/// assert_eq!(frozen.depth(3.0), 2);
+/// assert_eq!(frozen.num_nodes(1.0), 2);
+/// assert_eq!(frozen.height(0.0), 2);
+///
+/// let ids: Vec<f64> = frozen.preorder(0.0).iter().map(|n| n.id).collect();
+/// assert_eq!(ids, vec![0.0, 1.0, 3.0, 2.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrozenTree<T> {
+    slots: Vec<Slot<T>>,
+    index: HashMap<FloatId, usize>,
+    root: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    node: Node<T>,
+    children: Vec<usize>,
+    depth: usize,
+    height: usize,
+    subtree_size: usize,
+}
+
+impl<T> Tree<T> {
+    /// Freeze the tree into an immutable [`FrozenTree`]
+    ///
+    /// The subtree under the root is laid out first, followed by the trees
+    /// of any other parentless nodes in ascending ID order, each of which
+    /// has depth 0. Nodes that are not reachable from a parentless node (for
+    /// example, nodes on a parent cycle) are dropped. Metadata is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_level_order(vec![Some("a"), Some("b"), Some("c")]);
+    /// let root_id = tree.root_id();
+    ///
+    /// let frozen = tree.freeze();
+    /// assert_eq!(frozen.root_id(), root_id);
+    /// assert_eq!(frozen.root().unwrap().value, "a");
+    /// ```
+    pub fn freeze(self) -> FrozenTree<T> {
+        FrozenTree::from(self)
+    }
+}
+
+impl<T> From<Tree<T>> for FrozenTree<T> {
+    fn from(mut tree: Tree<T>) -> Self {
+        let mut starts: Vec<FloatId> = tree.root_id.into_iter().collect();
+        starts.extend(
+            tree.roots()
+                .into_iter()
+                .map(FloatId::from)
+                .filter(|&id| Some(id) != tree.root_id),
+        );
+
+        // Preorder with an explicit stack; each entry remembers the slot of
+        // the node that pushed it so children can be linked as they land
+        let mut order: Vec<(FloatId, Option<usize>, usize)> = Vec::new();
+        let mut visited = HashSet::new();
+        for start in starts {
+            let mut stack = vec![(start, None, 0)];
+            while let Some((current_id, parent_slot, depth)) = stack.pop() {
+                if !visited.insert(current_id) {
+                    continue;
+                }
+                let Some(node) = tree.nodes.get(&current_id) else {
+                    continue;
+                };
+                let slot = order.len();
+                order.push((current_id, parent_slot, depth));
+                let mut children: Vec<FloatId> = node.children.iter().copied().collect();
+                children.sort_unstable_by(|a, b| b.cmp(a));
+                stack.extend(children.into_iter().map(|id| (id, Some(slot), depth + 1)));
+            }
+        }
+
+        let mut slots: Vec<Slot<T>> = Vec::with_capacity(order.len());
+        let mut index = HashMap::with_capacity(order.len());
+        for (slot, &(id, parent_slot, depth)) in order.iter().enumerate() {
+            let node = tree.nodes.remove(&id).expect("laid out nodes exist");
+            index.insert(id, slot);
+            slots.push(Slot {
+                node,
+                children: Vec::new(),
+                depth,
+                height: 0,
+                subtree_size: 1,
+            });
+            if let Some(parent_slot) = parent_slot {
+                slots[parent_slot].children.push(slot);
+            }
+        }
+
+        // Children always come after their parent, so a reverse sweep sees
+        // every child before its parent
+        for slot in (0..slots.len()).rev() {
+            let (height, subtree_size) =
+                slots[slot]
+                    .children
+                    .iter()
+                    .fold((0, 1), |(height, size), &child| {
+                        let child = &slots[child];
+                        (height.max(child.height + 1), size + child.subtree_size)
+                    });
+            slots[slot].height = height;
+            slots[slot].subtree_size = subtree_size;
+        }
+
+        let root = tree.root_id.and_then(|id| index.get(&id).copied());
+        Self { slots, index, root }
+    }
+}
+
+impl<T> FrozenTree<T> {
+    /// Get the number of nodes
+    pub fn size(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Check if the tree has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Get the root node ID
+    pub fn root_id(&self) -> Option<Number> {
+        self.root.map(|slot| self.slots[slot].node.id)
+    }
+
+    /// Get the root node
+    pub fn root(&self) -> Option<&Node<T>> {
+        self.root.map(|slot| &self.slots[slot].node)
+    }
+
+    /// Get a node by ID
+    pub fn get_node(&self, id: Number) -> Option<&Node<T>> {
+        self.slot(id).map(|slot| &slot.node)
+    }
+
+    /// Iterate over every node in layout (preorder) order
+    pub fn iter(&self) -> impl Iterator<Item = &Node<T>> + '_ {
+        self.slots.iter().map(|slot| &slot.node)
+    }
+
+    /// Get the depth of a node, or 0 if it does not exist
+    pub fn depth(&self, node_id: Number) -> usize {
+        self.slot(node_id).map_or(0, |slot| slot.depth)
+    }
+
+    /// Get the height of the subtree rooted at a node, or 0 if it does not
+    /// exist
+    pub fn height(&self, node_id: Number) -> usize {
+        self.slot(node_id).map_or(0, |slot| slot.height)
+    }
+
+    /// Get the number of nodes in the subtree rooted at a node, or 0 if it
+    /// does not exist
+    pub fn num_nodes(&self, node_id: Number) -> usize {
+        self.slot(node_id).map_or(0, |slot| slot.subtree_size)
+    }
+
+    /// Get the number of leaves in the subtree rooted at a node
+    pub fn num_leaves(&self, node_id: Number) -> usize {
+        self.subtree(node_id)
+            .iter()
+            .filter(|slot| slot.children.is_empty())
+            .count()
+    }
+
+    /// Get the leaves of the subtree rooted at a node, in preorder
+    pub fn get_leaves(&self, node_id: Number) -> Vec<&Node<T>> {
+        self.subtree(node_id)
+            .iter()
+            .filter(|slot| slot.children.is_empty())
+            .map(|slot| &slot.node)
+            .collect()
+    }
+
+    /// Perform a preorder traversal of the subtree rooted at a node
+    ///
+    /// Children are visited in ascending ID order. This reads the
+    /// subtree's contiguous slots directly.
+    pub fn preorder(&self, node_id: Number) -> Vec<&Node<T>> {
+        self.subtree(node_id)
+            .iter()
+            .map(|slot| &slot.node)
+            .collect()
+    }
+
+    /// Perform a depth-first traversal of the subtree rooted at a node
+    ///
+    /// The same as [`FrozenTree::preorder`].
+    pub fn dfs(&self, node_id: Number) -> Vec<&Node<T>> {
+        self.preorder(node_id)
+    }
+
+    /// Perform a breadth-first traversal of the subtree rooted at a node
+    ///
+    /// Children are visited in ascending ID order.
+    pub fn bfs(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        let mut queue: VecDeque<usize> = self.slot_index(node_id).into_iter().collect();
+        while let Some(current) = queue.pop_front() {
+            let slot = &self.slots[current];
+            result.push(&slot.node);
+            queue.extend(&slot.children);
+        }
+        result
+    }
+
+    /// Perform a postorder traversal of the subtree rooted at a node
+    ///
+    /// Children are visited in ascending ID order.
+    pub fn postorder(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        let mut stack: Vec<(usize, bool)> = self
+            .slot_index(node_id)
+            .map(|slot| (slot, false))
+            .into_iter()
+            .collect();
+        while let Some((current, children_done)) = stack.pop() {
+            let slot = &self.slots[current];
+            if children_done {
+                result.push(&slot.node);
+            } else {
+                stack.push((current, true));
+                stack.extend(slot.children.iter().rev().map(|&child| (child, false)));
+            }
+        }
+        result
+    }
+
+    fn slot_index(&self, id: Number) -> Option<usize> {
+        self.index.get(&FloatId::from(id)).copied()
+    }
+
+    fn slot(&self, id: Number) -> Option<&Slot<T>> {
+        self.slot_index(id).map(|slot| &self.slots[slot])
+    }
+
+    /// The contiguous slots of the subtree rooted at `id`
+    fn subtree(&self, id: Number) -> &[Slot<T>] {
+        match self.slot_index(id) {
+            Some(start) => &self.slots[start..start + self.slots[start].subtree_size],
+            None => &[],
+        }
+    }
+}
+
+impl<T> TreeLike<T> for FrozenTree<T> {
+    fn size(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    fn search_by_value(&self, value: &T) -> Option<Number>
+    where
+        T: PartialEq,
+    {
+        self.iter()
+            .find(|node| node.value == *value)
+            .map(|node| node.id)
+    }
+
+    fn num_nodes(&self, node_id: Number) -> usize {
+        FrozenTree::num_nodes(self, node_id)
+    }
+
+    fn is_balanced(&self, node_id: Number) -> bool {
+        self.subtree(node_id).iter().all(|slot| {
+            let heights = slot.children.iter().map(|&child| self.slots[child].height);
+            match (heights.clone().min(), heights.max()) {
+                (Some(min), Some(max)) => max - min <= 1,
+                _ => true,
+            }
+        })
+    }
+}
+
+impl<T> Index<Number> for FrozenTree<T> {
+    type Output = Node<T>;
+
+    /// Get a node by ID
+    ///
+    /// # Panics
+    ///
+    /// Panics if no node with the given ID exists. Use
+    /// [`FrozenTree::get_node`] for a non-panicking lookup.
+    fn index(&self, id: Number) -> &Self::Output {
+        match self.get_node(id) {
+            Some(node) => node,
+            None => panic!("no node with id {} in tree", id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(nodes: Vec<&Node<usize>>) -> Vec<Number> {
+        nodes.iter().map(|node| node.id).collect()
+    }
+
+    #[test]
+    fn test_freeze_matches_tree_queries() {
+        //        0
+        //      / | \
+        //     1  2  3
+        //    / \     \
+        //   4   5     6
+        //             |
+        //             7
+        let parents = [
+            None,
+            Some(0),
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(3),
+            Some(6),
+        ];
+        let tree = Tree::from_parent_array(&parents).unwrap();
+        let expected: Vec<_> = (0..parents.len())
+            .map(|i| {
+                let id = i as Number;
+                (
+                    tree.depth(id),
+                    tree.height(id),
+                    tree.num_nodes(id),
+                    tree.num_leaves(id),
+                    tree.is_balanced(id),
+                )
+            })
+            .collect();
+        let preorder = ids(tree.dfs_sorted(0.0));
+        let bfs = ids(tree.bfs_sorted(0.0));
+
+        let frozen = tree.freeze();
+        assert_eq!(frozen.size(), parents.len());
+        assert_eq!(frozen.root_id(), Some(0.0));
+        for (i, expected) in expected.into_iter().enumerate() {
+            let id = i as Number;
+            let actual = (
+                frozen.depth(id),
+                frozen.height(id),
+                frozen.num_nodes(id),
+                frozen.num_leaves(id),
+                frozen.is_balanced(id),
+            );
+            assert_eq!(actual, expected, "node {}", id);
+        }
+
+        assert_eq!(ids(frozen.preorder(0.0)), preorder);
+        assert_eq!(ids(frozen.dfs(0.0)), preorder);
+        assert_eq!(ids(frozen.bfs(0.0)), bfs);
+        assert_eq!(
+            ids(frozen.postorder(0.0)),
+            vec![4.0, 5.0, 1.0, 2.0, 7.0, 6.0, 3.0, 0.0]
+        );
+        assert_eq!(ids(frozen.preorder(3.0)), vec![3.0, 6.0, 7.0]);
+        assert_eq!(ids(frozen.get_leaves(0.0)), vec![4.0, 5.0, 2.0, 7.0]);
+        assert_eq!(frozen[6.0].parent(), Some(3.0));
+        assert_eq!(frozen.search_by_value(&5), Some(5.0));
+    }
+
+    #[test]
+    fn test_freeze_forest_and_missing_nodes() {
+        let mut tree = Tree::new();
+        let a = tree.add_node(Node::with_id(0, 1.0)).unwrap();
+        let b = tree.add_node(Node::with_id(1, 2.0)).unwrap();
+        let c = tree.add_node(Node::with_id(2, 3.0)).unwrap();
+        tree.get_node_mut(b).unwrap().add_child(c);
+        tree.get_node_mut(c).unwrap().set_parent(b);
+        tree.set_root(b);
+
+        let frozen = tree.freeze();
+        assert_eq!(frozen.root_id(), Some(b));
+        assert_eq!(ids(frozen.iter().collect()), vec![b, c, a]);
+        assert_eq!(frozen.depth(a), 0);
+        assert_eq!(frozen.depth(c), 1);
+
+        assert_eq!(frozen.get_node(99.0), None);
+        assert_eq!(frozen.num_nodes(99.0), 0);
+        assert!(frozen.preorder(99.0).is_empty());
+        assert!(frozen.bfs(99.0).is_empty());
+        assert!(frozen.postorder(99.0).is_empty());
+
+        let empty: FrozenTree<usize> = Tree::new().freeze();
+        assert!(empty.is_empty());
+        assert_eq!(empty.root(), None);
+    }
+}
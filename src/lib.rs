@@ -38,9 +38,13 @@
 //! assert!(!child1.is_root());
 //! ```
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
-use std::hash::{Hash, Hasher};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Index;
+use std::sync::atomic::{self, AtomicU64};
 
 /// Core trait for any tree-like data structure
 pub trait TreeLike<T> {
@@ -98,8 +102,32 @@ pub trait NodeBasedTree<T>: TreeLike<T> {
     fn postorder(&self, node_id: Number) -> Vec<&Node<T>>;
 }
 
+/// Callbacks invoked by [`Tree::accept`] while walking a subtree
+///
+/// `on_enter` is called for a node before any of its descendants (preorder)
+/// and `on_leave` after all of them (postorder), so the calls are always
+/// bracket-matched. Both default to doing nothing.
+pub trait Visitor<T> {
+    /// Called when the walk first reaches `node`, at `depth` below the start
+    fn on_enter(&mut self, _node: &Node<T>, _depth: usize) {}
+
+    /// Called once every descendant of `node` has been visited
+    fn on_leave(&mut self, _node: &Node<T>, _depth: usize) {}
+}
+
+pub mod concurrent;
+mod dot;
+pub mod error;
+pub mod frozen;
+#[cfg(feature = "rand")]
+mod sample;
 pub mod tree;
-pub use tree::{vEB, BST};
+pub mod zipper;
+pub use concurrent::SharedTree;
+pub use error::TreeError;
+pub use frozen::FrozenTree;
+pub use tree::{vEB, KeyedBST, MultiBST, BST};
+pub use zipper::TreeZipper;
 
 #[derive(Debug, Clone, Copy)]
 pub struct FloatId(f64);
@@ -116,8 +144,16 @@ impl FloatId {
 
 impl Hash for FloatId {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        // Use the bit representation for hashing
-        self.0.to_bits().hash(state);
+        // Hash the bit representation, normalized so that values `eq`
+        // considers equal (`-0.0` and `0.0`, or any two NaNs) hash alike
+        let value = if self.0.is_nan() {
+            f64::NAN
+        } else if self.0 == 0.0 {
+            0.0
+        } else {
+            self.0
+        };
+        value.to_bits().hash(state);
     }
 }
 
@@ -133,6 +169,65 @@ impl PartialEq for FloatId {
 
 impl Eq for FloatId {}
 
+impl PartialOrd for FloatId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FloatId {
+    /// Total order consistent with `Eq`: NaN compares equal to NaN and
+    /// greater than every other value, and `-0.0` equals `0.0`.
+    fn cmp(&self, other: &Self) -> Ordering {
+        total_cmp_f64(self.0, other.0)
+    }
+}
+
+/// Compare two floats with a total order
+///
+/// NaN compares equal to NaN and greater than every other value, and `-0.0`
+/// equals `0.0`. This is the same order `FloatId` uses, and it lets float
+/// payloads implement `Ord` without the `partial_cmp(..).unwrap()` pattern.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::{total_cmp_f64, BST};
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(total_cmp_f64(1.0, 2.0), Ordering::Less);
+/// assert_eq!(total_cmp_f64(f64::NAN, f64::INFINITY), Ordering::Greater);
+///
+/// // Storing floats in a BST
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Float(f64);
+///
+/// impl Eq for Float {}
+/// impl PartialOrd for Float {
+///     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+///         Some(self.cmp(other))
+///     }
+/// }
+/// impl Ord for Float {
+///     fn cmp(&self, other: &Self) -> Ordering {
+///         total_cmp_f64(self.0, other.0)
+///     }
+/// }
+///
+/// let mut bst = BST::new();
+/// bst.insert(Float(2.5));
+/// bst.insert(Float(-1.0));
+/// assert_eq!(bst.min(), Some(&Float(-1.0)));
+/// ```
+pub fn total_cmp_f64(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
 impl From<f64> for FloatId {
     fn from(value: f64) -> Self {
         Self(value)
@@ -154,7 +249,7 @@ pub type Number = f64;
 /// - Binary trees (using left/right relationships)
 /// - Graphs (using edges)
 /// - BSTs (using both parent/children and left/right)
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 #[allow(dead_code)]
 pub struct Node<T> {
     pub value: T,
@@ -174,6 +269,16 @@ pub struct Node<T> {
     right: Option<FloatId>,
 }
 
+/// The next ID [`Node::new`] will hand out
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Explicit IDs at or above this are not reserved
+///
+/// Kept well below 2^53, where `f64` stops representing every whole number,
+/// so that reserving a huge ID can never push the generator into a range
+/// where two generated IDs round to the same value.
+const MAX_RESERVED_ID: u64 = 1 << 52;
+
 impl<T> Node<T> {
     /// Create a new node with the given value
     ///
@@ -212,6 +317,14 @@ impl<T> Node<T> {
     /// This allows you to control the ID assignment, which can be useful
     /// when reconstructing data structures from serialized data.
     ///
+    /// Any `f64` is a valid ID, compared as [`FloatId`] does (so `-0.0` and
+    /// `0.0` are the same ID). IDs generated by [`Node::new`] are always
+    /// positive whole numbers, so negative or fractional explicit IDs can
+    /// never collide with them. A positive whole-number explicit ID is
+    /// reserved instead: the generator skips past it, so later calls to
+    /// [`Node::new`] never hand it out again. IDs of 2^52 and above are not
+    /// reserved, since the generator never gets that far.
+    ///
     /// # Examples
     ///
     /// ```
@@ -220,8 +333,16 @@ impl<T> Node<T> {
     /// let node = Node::with_id("custom", 999.0);
     /// assert_eq!(node.id, 999.0);
     /// assert_eq!(node.value, "custom");
+    ///
+    /// // Outside the generated ID space
+    /// assert_ne!(Node::new("generated").id, Node::with_id("explicit", -1.5).id);
+    ///
+    /// // Reserved, so never generated
+    /// let explicit = Node::with_id("explicit", 5000.0);
+    /// assert!(Node::new("generated").id > explicit.id);
     /// ```
     pub fn with_id(value: T, id: Number) -> Self {
+        Self::reserve_id(id);
         Self {
             value,
             id,
@@ -235,11 +356,81 @@ impl<T> Node<T> {
         }
     }
 
+    /// Create a new graph vertex with the given value
+    ///
+    /// The vertex is an unconnected node with a fresh ID, like
+    /// [`Node::new`]. Chain [`Node::with_outgoing`], [`Node::with_incoming`]
+    /// and [`Node::with_undirected`] to fill in its edge lists. As with
+    /// [`Node::add_edge`], only this vertex's side of each edge is recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Node;
+    ///
+    /// let vertex = Node::graph_vertex("hub")
+    ///     .with_outgoing([1.0, 2.0])
+    ///     .with_incoming([3.0])
+    ///     .with_undirected([4.0]);
+    ///
+    /// assert_eq!(vertex.outgoing().len(), 2);
+    /// assert_eq!(vertex.incoming(), vec![3.0]);
+    /// assert_eq!(vertex.edges(), vec![4.0]);
+    /// assert!(vertex.children().is_empty());
+    /// ```
+    pub fn graph_vertex(value: T) -> Self {
+        Self::new(value)
+    }
+
+    /// Add directed edges from this vertex to each of `ids`
+    pub fn with_outgoing(mut self, ids: impl IntoIterator<Item = Number>) -> Self {
+        self.outgoing.extend(ids.into_iter().map(FloatId::from));
+        self
+    }
+
+    /// Add directed edges into this vertex from each of `ids`
+    pub fn with_incoming(mut self, ids: impl IntoIterator<Item = Number>) -> Self {
+        self.incoming.extend(ids.into_iter().map(FloatId::from));
+        self
+    }
+
+    /// Add undirected edges between this vertex and each of `ids`
+    pub fn with_undirected(mut self, ids: impl IntoIterator<Item = Number>) -> Self {
+        self.edges.extend(ids.into_iter().map(FloatId::from));
+        self
+    }
+
+    /// Get the IDs of nodes this node has a directed edge to
+    pub fn outgoing(&self) -> Vec<Number> {
+        self.outgoing.iter().map(|id| id.value()).collect()
+    }
+
+    /// Get the IDs of nodes with a directed edge into this node
+    pub fn incoming(&self) -> Vec<Number> {
+        self.incoming.iter().map(|id| id.value()).collect()
+    }
+
+    /// Get the IDs of nodes this node shares an undirected edge with
+    pub fn edges(&self) -> Vec<Number> {
+        self.edges.iter().map(|id| id.value()).collect()
+    }
+
     /// Generate a unique ID for the node
     fn generate_id() -> Number {
-        use std::sync::atomic::{AtomicU64, Ordering};
-        static COUNTER: AtomicU64 = AtomicU64::new(1);
-        COUNTER.fetch_add(1, Ordering::Relaxed) as Number
+        let id = NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed);
+        assert!(id < 1 << 53, "node ID space exhausted");
+        id as Number
+    }
+
+    /// Make sure [`Node::generate_id`] never hands out `id`
+    ///
+    /// Only positive whole numbers can be generated, so any other ID needs no
+    /// reservation. IDs at or above [`MAX_RESERVED_ID`] are left alone too:
+    /// the generator would need 2^52 calls to reach them.
+    fn reserve_id(id: Number) {
+        if (1.0..MAX_RESERVED_ID as Number).contains(&id) && id.fract() == 0.0 {
+            NEXT_ID.fetch_max(id as u64 + 1, atomic::Ordering::Relaxed);
+        }
     }
 
     /// Add an edge to another node
@@ -255,6 +446,11 @@ impl<T> Node<T> {
     /// * `directed` - Whether the edge is directed (default: false)
     /// * `bidirectional` - Whether to create a bidirectional connection (default: false)
     ///
+    /// Edges are stored as sets, so adding the same edge twice keeps a single
+    /// edge (multi-edges are collapsed). Self-loops (`other_id == self.id`)
+    /// are accepted and can be detected with [`Node::has_self_loop`]; use
+    /// [`Node::add_edge_checked`] to reject them instead.
+    ///
     /// # Examples
     ///
     /// ```
@@ -292,6 +488,59 @@ impl<T> Node<T> {
         }
     }
 
+    /// Add an edge to another node, rejecting self-loops
+    ///
+    /// Behaves like [`Node::add_edge`], but returns
+    /// [`TreeError::SelfLoop`] without modifying the node if `other_id` is
+    /// this node's own ID.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, TreeError};
+    ///
+    /// let mut node1 = Node::new("A");
+    /// let node2 = Node::new("B");
+    ///
+    /// assert!(node1.add_edge_checked(node2.id, None, None, None).is_ok());
+    /// assert_eq!(
+    ///     node1.add_edge_checked(node1.id, None, None, None),
+    ///     Err(TreeError::SelfLoop)
+    /// );
+    /// assert!(!node1.has_self_loop());
+    /// ```
+    pub fn add_edge_checked(
+        &mut self,
+        other_id: Number,
+        weight: Option<Number>,
+        directed: Option<bool>,
+        bidirectional: Option<bool>,
+    ) -> Result<(), TreeError> {
+        if FloatId::from(other_id) == FloatId::from(self.id) {
+            return Err(TreeError::SelfLoop);
+        }
+        self.add_edge(other_id, weight, directed, bidirectional);
+        Ok(())
+    }
+
+    /// Check if this node has an edge to itself
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Node;
+    ///
+    /// let mut node = Node::new("A");
+    /// assert!(!node.has_self_loop());
+    ///
+    /// node.add_edge(node.id, None, None, None);
+    /// assert!(node.has_self_loop());
+    /// ```
+    pub fn has_self_loop(&self) -> bool {
+        let id = FloatId::from(self.id);
+        self.edges.contains(&id) || self.outgoing.contains(&id) || self.incoming.contains(&id)
+    }
+
     /// Add a child node
     ///
     /// Adds a node as a child of this node. This is used for tree structures
@@ -423,7 +672,28 @@ impl<T> Node<T> {
     /// assert!(children.contains(&child2.id));
     /// ```
     pub fn children(&self) -> Vec<Number> {
-        self.children.iter().map(|id| id.value()).collect()
+        self.children_iter().collect()
+    }
+
+    /// Iterate over children IDs without allocating
+    ///
+    /// Yields the same IDs as [`Node::children`], in the same order, but
+    /// borrows the node's child set instead of collecting into a `Vec`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Node;
+    ///
+    /// let mut parent = Node::new("parent");
+    /// let child = Node::new("child");
+    /// parent.add_child(child.id);
+    ///
+    /// assert_eq!(parent.children_iter().count(), 1);
+    /// assert!(parent.children_iter().any(|id| id == child.id));
+    /// ```
+    pub fn children_iter(&self) -> impl Iterator<Item = Number> + '_ {
+        self.children.iter().map(|id| id.value())
     }
 
     /// Check if this node is a root (no parent)
@@ -717,6 +987,25 @@ impl<T> Node<T> {
         connections.extend(self.children.iter().map(|id| id.value()));
         connections
     }
+
+    /// Rewrite this node's id and every id it references according to `map`
+    ///
+    /// Ids that are not keys of `map` are left unchanged.
+    fn remap_ids(&mut self, map: &HashMap<FloatId, FloatId>) {
+        let remap = |id: FloatId| map.get(&id).copied().unwrap_or(id);
+        let remap_set = |set: &HashSet<FloatId>| -> HashSet<FloatId> {
+            set.iter().map(|&id| remap(id)).collect()
+        };
+
+        self.id = remap(FloatId::from(self.id)).value();
+        self.parent = self.parent.map(remap);
+        self.left = self.left.map(remap);
+        self.right = self.right.map(remap);
+        self.children = remap_set(&self.children);
+        self.edges = remap_set(&self.edges);
+        self.incoming = remap_set(&self.incoming);
+        self.outgoing = remap_set(&self.outgoing);
+    }
 }
 
 impl<T> Hash for Node<T> {
@@ -733,12 +1022,50 @@ impl<T> PartialEq for Node<T> {
 
 impl<T> Eq for Node<T> {}
 
+impl<T: fmt::Debug> fmt::Debug for Node<T> {
+    /// Linked IDs are printed in ascending order, so the output does not
+    /// depend on the iteration order of the underlying sets
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sorted = |ids: &HashSet<FloatId>| {
+            let mut ids: Vec<FloatId> = ids.iter().copied().collect();
+            ids.sort_unstable();
+            ids.into_iter()
+                .map(|id| id.value())
+                .collect::<Vec<Number>>()
+        };
+        f.debug_struct("Node")
+            .field("value", &self.value)
+            .field("id", &self.id)
+            .field("parent", &self.parent.map(|id| id.value()))
+            .field("children", &sorted(&self.children))
+            .field("edges", &sorted(&self.edges))
+            .field("incoming", &sorted(&self.incoming))
+            .field("outgoing", &sorted(&self.outgoing))
+            .field("left", &self.left.map(|id| id.value()))
+            .field("right", &self.right.map(|id| id.value()))
+            .finish()
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for Node<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Node(value={})", self.value)
     }
 }
 
+/// Traversal orders supported by multi-root and bulk traversal helpers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraversalOrder {
+    /// Depth-first search, as in [`Tree::dfs`]
+    Dfs,
+    /// Breadth-first search, as in [`Tree::bfs`]
+    Bfs,
+    /// Preorder traversal, as in [`Tree::preorder`]
+    Preorder,
+    /// Postorder traversal, as in [`Tree::postorder`]
+    Postorder,
+}
+
 /// A tree structure that manages nodes
 ///
 /// A flexible tree structure that can represent various types of hierarchical data.
@@ -829,10 +1156,118 @@ impl<T: fmt::Display> fmt::Display for Node<T> {
 /// assert_eq!(preorder_result.len(), 2);
 /// assert_eq!(postorder_result.len(), 2);
 /// ```
-#[derive(Debug, Clone)]
+///
+/// ## Per-node metadata
+///
+/// Trees also carry an optional side-channel of per-node metadata (colors,
+/// visited flags, layout coordinates, ...) that lives outside of `T`. The
+/// metadata is scratch space: it is dropped with its node and is not
+/// carried over when the tree is cloned.
+///
+/// ```
+/// use jangal::{Tree, Node};
+///
+/// let mut tree = Tree::new();
+/// let id = tree.add_node(Node::new("root")).unwrap();
+///
+/// tree.set_meta(id, (1.5_f32, 2.0_f32));
+/// assert_eq!(tree.meta::<(f32, f32)>(id), Some(&(1.5, 2.0)));
+/// ```
+///
+/// ## Values that are not `Clone`
+///
+/// Building, lookup, linking, traversal and removal place no bounds on `T`.
+/// Only operations that copy values, such as [`Tree::to_level_order`] or
+/// [`Tree::clone_with_new_ids`], require `T: Clone`, and they state it in
+/// their signature.
+///
+/// ```
+/// use jangal::Tree;
+///
+/// // Implements neither Clone nor Ord
+/// struct Payload(String);
+///
+/// let mut tree = Tree::new();
+/// let (root_id, _) = tree.insert_and_get_mut(Payload("root".to_string()));
+/// let child_id = tree.add_child_value(root_id, Payload("child".to_string())).unwrap();
+///
+/// assert_eq!(tree.dfs(root_id).len(), 2);
+/// assert_eq!(tree.remove_node(child_id).unwrap().value.0, "child");
+/// ```
+///
+/// ## Thread safety
+///
+/// `Tree<T>` is `Send` and `Sync` whenever `T` is: metadata is required to
+/// be `Send + Sync`, there is no interior mutability, and the counter that
+/// hands out fresh node IDs is a global atomic, so IDs stay unique across
+/// threads. Shared `&Tree` references can be read from many threads at
+/// once, and mutation needs `&mut Tree`, which the borrow checker keeps
+/// exclusive. To mutate a tree that other threads also hold, wrap it in a
+/// [`SharedTree`].
 pub struct Tree<T> {
     nodes: HashMap<FloatId, Node<T>>,
     root_id: Option<FloatId>,
+    meta: HashMap<FloatId, Box<dyn Any + Send + Sync>>,
+    max_nodes: Option<usize>,
+    max_children: Option<usize>,
+    last_access: HashMap<FloatId, u64>,
+    access_order: BTreeSet<(u64, FloatId)>,
+    access_clock: u64,
+    max_recursion_depth: Option<usize>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Tree<T> {
+    /// Nodes are printed in ascending ID order, so the output is
+    /// reproducible across runs. Metadata and access bookkeeping are
+    /// omitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, Tree};
+    ///
+    /// let build = |ids: [f64; 3]| {
+    ///     let mut tree = Tree::new();
+    ///     for id in ids {
+    ///         tree.add_node_no_root(Node::with_id(id as i32, id));
+    ///     }
+    ///     tree
+    /// };
+    ///
+    /// let a = build([1.0, 2.0, 3.0]);
+    /// let b = build([3.0, 1.0, 2.0]);
+    /// assert_eq!(format!("{:?}", a), format!("{:?}", b));
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nodes: Vec<(&FloatId, &Node<T>)> = self.nodes.iter().collect();
+        nodes.sort_unstable_by_key(|(&id, _)| id);
+        let nodes: Vec<&Node<T>> = nodes.into_iter().map(|(_, node)| node).collect();
+
+        f.debug_struct("Tree")
+            .field("root_id", &self.root_id.map(|id| id.value()))
+            .field("nodes", &nodes)
+            .field("max_nodes", &self.max_nodes)
+            .field("max_children", &self.max_children)
+            .field("max_recursion_depth", &self.max_recursion_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone> Clone for Tree<T> {
+    /// Clone the tree's nodes and root, leaving the metadata behind
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            root_id: self.root_id,
+            meta: HashMap::new(),
+            max_nodes: self.max_nodes,
+            max_children: self.max_children,
+            last_access: self.last_access.clone(),
+            access_order: self.access_order.clone(),
+            access_clock: self.access_clock,
+            max_recursion_depth: self.max_recursion_depth,
+        }
+    }
 }
 
 impl<T> Tree<T> {
@@ -852,154 +1287,405 @@ impl<T> Tree<T> {
         Self {
             nodes: HashMap::new(),
             root_id: None,
+            meta: HashMap::new(),
+            max_nodes: None,
+            max_children: None,
+            last_access: HashMap::new(),
+            access_order: BTreeSet::new(),
+            access_clock: 0,
+            max_recursion_depth: None,
         }
     }
-}
 
-impl<T> TreeLike<T> for Tree<T> {
-    fn size(&self) -> usize {
-        self.nodes.len()
-    }
+    /// Build a binary tree from a level-order array
+    ///
+    /// Values are assigned as `left`/`right` children in breadth-first order,
+    /// skipping `None` slots, in the same format commonly used to share
+    /// binary-tree test data (e.g. `[1, 2, 3, null, 4]`). Each child is also
+    /// added to its parent's `children` set and has its `parent` set. An empty
+    /// array, or one whose first slot is `None`, produces an empty tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4)]);
+    /// assert_eq!(tree.size(), 4);
+    ///
+    /// let root = tree.root().unwrap();
+    /// assert_eq!(root.value, 1);
+    ///
+    /// let left = tree.get_node(root.left().unwrap()).unwrap();
+    /// assert_eq!(left.value, 2);
+    /// assert_eq!(left.left(), None);
+    /// assert_eq!(tree.get_node(left.right().unwrap()).unwrap().value, 4);
+    /// ```
+    pub fn from_level_order(values: Vec<Option<T>>) -> Self {
+        let mut tree = Self::new();
+        let mut values = values.into_iter();
+
+        let root_id = match values.next() {
+            Some(Some(value)) => tree.add_node(Node::new(value)).unwrap(),
+            _ => return tree,
+        };
+        tree.set_root(root_id);
 
-    fn is_empty(&self) -> bool {
-        self.nodes.is_empty()
+        let mut queue = VecDeque::new();
+        queue.push_back(root_id);
+
+        while let Some(parent_id) = queue.pop_front() {
+            for is_left in [true, false] {
+                let value = match values.next() {
+                    Some(Some(value)) => value,
+                    Some(None) => continue,
+                    None => return tree,
+                };
+
+                let child_id = tree.add_node(Node::new(value)).unwrap();
+                if let Some(parent) = tree.get_node_mut(parent_id) {
+                    if is_left {
+                        parent.set_left(child_id);
+                    } else {
+                        parent.set_right(child_id);
+                    }
+                    parent.add_child(child_id);
+                }
+                if let Some(child) = tree.get_node_mut(child_id) {
+                    child.set_parent(parent_id);
+                }
+                queue.push_back(child_id);
+            }
+        }
+
+        tree
     }
 
-    fn search_by_value(&self, value: &T) -> Option<Number>
+    /// Export a binary tree to a level-order array
+    ///
+    /// The inverse of [`Tree::from_level_order`]: produces the
+    /// `[root, left, right, ...]` representation starting at `start`, with
+    /// `None` for missing binary children. Only the `left`/`right` pointers are
+    /// followed, not the n-ary `children` set. Trailing `None` slots are
+    /// trimmed, and a missing `start` produces an empty array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let values = vec![Some(1), Some(2), Some(3), None, Some(4)];
+    /// let tree = Tree::from_level_order(values.clone());
+    ///
+    /// let root_id = tree.root_id().unwrap();
+    /// assert_eq!(tree.to_level_order(root_id), values);
+    /// ```
+    pub fn to_level_order(&self, start: Number) -> Vec<Option<T>>
     where
-        T: PartialEq,
+        T: Clone,
     {
-        for (id, node) in &self.nodes {
-            if node.value == *value {
-                return Some(id.value());
+        let mut result = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(Some(FloatId::from(start)));
+
+        while let Some(slot) = queue.pop_front() {
+            match slot.and_then(|id| self.nodes.get(&id)) {
+                Some(node) => {
+                    result.push(Some(node.value.clone()));
+                    queue.push_back(node.left);
+                    queue.push_back(node.right);
+                }
+                None => result.push(None),
             }
         }
-        None
-    }
 
-    fn num_nodes(&self, node_id: Number) -> usize {
-        if let Some(node) = self.nodes.get(&FloatId::from(node_id)) {
-            let mut count = 1; // Count the current node
-            for child_id in node.children() {
-                count += self.num_nodes(child_id);
-            }
-            return count;
+        while let Some(None) = result.last() {
+            result.pop();
         }
-        0
+
+        result
     }
 
-    fn is_balanced(&self, node_id: Number) -> bool {
-        if let Some(node) = self.nodes.get(&FloatId::from(node_id)) {
-            if node.is_leaf() {
-                return true;
+    /// Encode the n-ary `children` structure as a binary tree
+    ///
+    /// Uses the left-child right-sibling (LC-RS) encoding: each node's first
+    /// child becomes its `left`, and each next sibling becomes the previous
+    /// sibling's `right`. Siblings are ordered by ascending ID. Node IDs are
+    /// preserved, and each node's `parent`/`children` links in the result
+    /// follow the binary structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0            0
+    /// //   / | \   =>    /
+    /// //  1  2  3       1 - 2 - 3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(0)]).unwrap();
+    /// let binary = tree.to_left_child_right_sibling();
+    ///
+    /// assert_eq!(binary.to_level_order(0.0), vec![Some(0), Some(1), None, None, Some(2), None, Some(3)]);
+    /// assert_eq!(Tree::from_left_child_right_sibling(&binary), tree);
+    /// ```
+    pub fn to_left_child_right_sibling(&self) -> Tree<T>
+    where
+        T: Clone,
+    {
+        let mut binary = self.detached_copy();
+        for (&id, node) in &self.nodes {
+            let mut children: Vec<FloatId> = node
+                .children
+                .iter()
+                .copied()
+                .filter(|child_id| self.nodes.contains_key(child_id))
+                .collect();
+            children.sort_unstable();
+
+            let mut previous = id;
+            for (i, &child_id) in children.iter().enumerate() {
+                if let Some(prev) = binary.nodes.get_mut(&previous) {
+                    if i == 0 {
+                        prev.left = Some(child_id);
+                    } else {
+                        prev.right = Some(child_id);
+                    }
+                    prev.children.insert(child_id);
+                }
+                if let Some(child) = binary.nodes.get_mut(&child_id) {
+                    child.parent = Some(previous);
+                }
+                previous = child_id;
             }
+        }
+        binary
+    }
 
-            let mut heights = Vec::new();
-            for child_id in node.children() {
-                heights.push(self.height(child_id));
+    /// Decode a left-child right-sibling binary tree into n-ary `children`
+    ///
+    /// The inverse of [`Tree::to_left_child_right_sibling`]: a node's children
+    /// are its `left` followed by the chain of `right` links from there. The
+    /// result has `parent`/`children` links only; `left` and `right` are
+    /// cleared. Node IDs are preserved.
+    pub fn from_left_child_right_sibling(binary: &Tree<T>) -> Tree<T>
+    where
+        T: Clone,
+    {
+        let mut nary = binary.detached_copy();
+        for (&id, node) in &binary.nodes {
+            let mut seen = HashSet::new();
+            let mut next = node.left;
+            while let Some(child_id) = next {
+                if !seen.insert(child_id) {
+                    break;
+                }
+                let Some(child) = binary.nodes.get(&child_id) else {
+                    break;
+                };
+                if let Some(parent) = nary.nodes.get_mut(&id) {
+                    parent.children.insert(child_id);
+                }
+                if let Some(child) = nary.nodes.get_mut(&child_id) {
+                    child.parent = Some(id);
+                }
+                next = child.right;
             }
+        }
+        nary
+    }
 
-            if heights.is_empty() {
-                return true;
-            }
+    /// Copy every node and the root, dropping parent/child and left/right links
+    fn detached_copy(&self) -> Tree<T>
+    where
+        T: Clone,
+    {
+        let mut copy = Tree::new();
+        for (&id, node) in &self.nodes {
+            let mut node = node.clone();
+            node.parent = None;
+            node.children.clear();
+            node.left = None;
+            node.right = None;
+            copy.nodes.insert(id, node);
+        }
+        copy.root_id = self.root_id;
+        copy
+    }
+}
 
-            let min_height = heights.iter().min().unwrap();
-            let max_height = heights.iter().max().unwrap();
+impl Tree<usize> {
+    /// Build a tree from a parent array
+    ///
+    /// Node `i` holds the value `i` and has the ID `i`, and `parents[i]` is
+    /// the index of its parent (`None` for the root). This compact encoding
+    /// makes it easy to generate valid trees deterministically, e.g. for
+    /// property tests. An empty array produces an empty tree.
+    ///
+    /// The IDs `0..parents.len()` are reserved as in [`Node::with_id`], so
+    /// nodes added later with [`Node::new`] or [`Tree::add_child_value`] get
+    /// fresh IDs rather than replacing these.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TreeError::MultipleRoots`] or [`TreeError::MissingRoot`] if
+    /// the array does not contain exactly one root,
+    /// [`TreeError::InvalidParent`] if a parent index is out of range, and
+    /// [`TreeError::Cycle`] if the parent links form a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, TreeError};
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    /// assert_eq!(tree.size(), 4);
+    /// assert_eq!(tree.root_id(), Some(0.0));
+    /// assert_eq!(tree.get_node(3.0).unwrap().parent(), Some(1.0));
+    /// assert_eq!(tree.height(0.0), 2);
+    ///
+    /// assert_eq!(
+    ///     Tree::from_parent_array(&[None, None]).unwrap_err(),
+    ///     TreeError::MultipleRoots
+    /// );
+    /// ```
+    pub fn from_parent_array(parents: &[Option<usize>]) -> Result<Self, TreeError> {
+        let mut tree = Self::new();
+        if parents.is_empty() {
+            return Ok(tree);
+        }
 
-            // Check if the height difference is at most 1
-            if max_height - min_height > 1 {
-                return false;
+        let mut root = None;
+        for (index, parent) in parents.iter().enumerate() {
+            match *parent {
+                None if root.is_some() => return Err(TreeError::MultipleRoots),
+                None => root = Some(index),
+                Some(parent) if parent >= parents.len() => {
+                    return Err(TreeError::InvalidParent { index, parent })
+                }
+                Some(_) => {}
             }
+        }
+        let root = root.ok_or(TreeError::MissingRoot)?;
 
-            // Recursively check all children
-            for child_id in node.children() {
-                if !self.is_balanced(child_id) {
-                    return false;
+        for index in 0..parents.len() {
+            tree.add_node(Node::with_id(index, index as Number));
+        }
+        tree.set_root(root as Number);
+        for (index, parent) in parents.iter().enumerate() {
+            if let Some(parent) = *parent {
+                if let Some(node) = tree.get_node_mut(parent as Number) {
+                    node.add_child(index as Number);
+                }
+                if let Some(node) = tree.get_node_mut(index as Number) {
+                    node.set_parent(parent as Number);
                 }
             }
+        }
 
-            return true;
+        // With a single root and one parent per node, any node that cannot be
+        // reached from the root must sit on a cycle
+        if tree.num_nodes(root as Number) != parents.len() {
+            return Err(TreeError::Cycle);
         }
-        true
+
+        Ok(tree)
     }
 }
 
-impl<T> NodeBasedTree<T> for Tree<T> {
-    fn root_id(&self) -> Option<Number> {
-        self.root_id.map(|id| id.value())
+impl<T> TreeLike<T> for Tree<T> {
+    fn size(&self) -> usize {
+        self.nodes.len()
     }
 
-    fn get_node(&self, id: Number) -> Option<&Node<T>> {
-        self.nodes.get(&FloatId::from(id))
+    fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
     }
 
-    fn get_node_mut(&mut self, id: Number) -> Option<&mut Node<T>> {
-        self.nodes.get_mut(&FloatId::from(id))
+    fn search_by_value(&self, value: &T) -> Option<Number>
+    where
+        T: PartialEq,
+    {
+        for (id, node) in &self.nodes {
+            if node.value == *value {
+                return Some(id.value());
+            }
+        }
+        None
     }
 
-    fn height(&self, node_id: Number) -> usize {
-        if let Some(node) = self.nodes.get(&FloatId::from(node_id)) {
-            if node.is_leaf() {
-                return 0;
+    fn num_nodes(&self, node_id: Number) -> usize {
+        Tree::num_nodes(self, node_id)
+    }
+
+    fn is_balanced(&self, node_id: Number) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![FloatId::from(node_id)];
+
+        while let Some(current_id) = stack.pop() {
+            if !visited.insert(current_id) {
+                continue;
             }
-            let mut max_height = 0;
-            for child_id in node.children() {
-                let child_height = self.height(child_id);
-                max_height = max_height.max(child_height);
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+
+            let heights: Vec<usize> = node
+                .children_iter()
+                .map(|child_id| self.height(child_id))
+                .collect();
+            if let (Some(min_height), Some(max_height)) =
+                (heights.iter().min(), heights.iter().max())
+            {
+                // Check if the height difference is at most 1
+                if max_height - min_height > 1 {
+                    return false;
+                }
             }
-            return 1 + max_height;
+
+            stack.extend(node.children.iter().copied());
         }
-        0
+        true
     }
+}
 
-    fn depth(&self, node_id: Number) -> usize {
-        let mut current_id = FloatId::from(node_id);
-        let mut depth = 0;
+impl<T> NodeBasedTree<T> for Tree<T> {
+    fn root_id(&self) -> Option<Number> {
+        self.root_id.map(|id| id.value())
+    }
 
-        while let Some(node) = self.nodes.get(&current_id) {
-            if let Some(parent_id) = node.parent() {
-                current_id = FloatId::from(parent_id);
-                depth += 1;
-            } else {
-                break;
-            }
-        }
+    fn get_node(&self, id: Number) -> Option<&Node<T>> {
+        self.nodes.get(&FloatId::from(id))
+    }
 
-        depth
+    fn get_node_mut(&mut self, id: Number) -> Option<&mut Node<T>> {
+        self.nodes.get_mut(&FloatId::from(id))
+    }
+
+    fn height(&self, node_id: Number) -> usize {
+        Tree::height(self, node_id)
+    }
+
+    fn depth(&self, node_id: Number) -> usize {
+        Tree::depth(self, node_id)
     }
 
     fn num_leaves(&self, node_id: Number) -> usize {
-        if let Some(node) = self.nodes.get(&FloatId::from(node_id)) {
-            if node.is_leaf() {
-                return 1;
-            }
-            let mut count = 0;
-            for child_id in node.children() {
-                count += self.num_leaves(child_id);
-            }
-            return count;
-        }
-        0
+        Tree::num_leaves(self, node_id)
     }
 
     fn get_leaves(&self, node_id: Number) -> Vec<&Node<T>> {
-        if let Some(node) = self.nodes.get(&FloatId::from(node_id)) {
-            if node.is_leaf() {
-                return vec![node];
-            }
-            let mut leaves = Vec::new();
-            for child_id in node.children() {
-                leaves.extend(self.get_leaves(child_id));
-            }
-            return leaves;
-        }
-        Vec::new()
+        Tree::get_leaves(self, node_id)
     }
 
     fn dfs(&self, node_id: Number) -> Vec<&Node<T>> {
         let mut visited = HashSet::new();
         let mut result = Vec::new();
-        self.dfs_recursive(FloatId::from(node_id), &mut visited, &mut result);
+        self.dfs_recursive(FloatId::from(node_id), 0, &mut visited, &mut result);
         result
     }
 
@@ -1015,7 +1701,7 @@ impl<T> NodeBasedTree<T> for Tree<T> {
         while let Some(current_id) = queue.pop_front() {
             if let Some(node) = self.nodes.get(&current_id) {
                 result.push(node);
-                for child_id in node.children() {
+                for child_id in node.children_iter() {
                     let child_id = FloatId::from(child_id);
                     if !visited.contains(&child_id) {
                         visited.insert(child_id);
@@ -1030,13 +1716,13 @@ impl<T> NodeBasedTree<T> for Tree<T> {
 
     fn preorder(&self, node_id: Number) -> Vec<&Node<T>> {
         let mut result = Vec::new();
-        self.preorder_recursive(FloatId::from(node_id), &mut result);
+        self.preorder_recursive(FloatId::from(node_id), 0, &mut result);
         result
     }
 
     fn postorder(&self, node_id: Number) -> Vec<&Node<T>> {
         let mut result = Vec::new();
-        self.postorder_recursive(FloatId::from(node_id), &mut result);
+        self.postorder_recursive(FloatId::from(node_id), 0, &mut result);
         result
     }
 }
@@ -1045,7 +1731,11 @@ impl<T> Tree<T> {
     /// Add a node to the tree
     ///
     /// Adds a node to the tree and returns its ID. If this is the first node
-    /// added to the tree, it will automatically be set as the root.
+    /// added to the tree, it will automatically be set as the root; use
+    /// [`Tree::add_node_no_root`] to opt out of this.
+    ///
+    /// The node's ID is reserved as in [`Node::with_id`], so nodes created
+    /// later with [`Node::new`] never take it over.
     ///
     /// Users can choose whether to handle the returned ID or not.
     ///
@@ -1069,13 +1759,75 @@ impl<T> Tree<T> {
     /// ```
     pub fn add_node(&mut self, node: Node<T>) -> Option<Number> {
         let id = FloatId::from(node.id);
+        Node::<T>::reserve_id(node.id);
         self.nodes.insert(id, node);
+        self.track_untouched(id);
         if self.root_id.is_none() {
             self.root_id = Some(id);
         }
         Some(id.value())
     }
 
+    /// Add a node to the tree without touching the root
+    ///
+    /// Unlike [`Tree::add_node`], the first node added is not automatically
+    /// made the root. This is useful when building trees bottom-up or
+    /// importing nodes in arbitrary order; set the root explicitly with
+    /// [`Tree::set_root`] once it is known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let leaf_id = tree.add_node_no_root(Node::new("leaf")).unwrap();
+    /// let root_id = tree.add_node_no_root(Node::new("root")).unwrap();
+    /// assert_eq!(tree.root_id(), None);
+    ///
+    /// tree.set_root(root_id);
+    /// assert_eq!(tree.root_id(), Some(root_id));
+    /// assert_ne!(tree.root_id(), Some(leaf_id));
+    /// ```
+    pub fn add_node_no_root(&mut self, node: Node<T>) -> Option<Number> {
+        let id = FloatId::from(node.id);
+        Node::<T>::reserve_id(node.id);
+        self.nodes.insert(id, node);
+        self.track_untouched(id);
+        Some(id.value())
+    }
+
+    /// Add a new node holding `value` and return it for further setup
+    ///
+    /// Like [`Tree::add_node`], the first node added becomes the root and the
+    /// node counts toward [`Tree::set_max_nodes`]. Returning the node directly saves a second lookup through
+    /// [`Tree::get_node_mut`] when configuring nodes in a construction loop.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let mut tree = Tree::new();
+    /// let (root_id, _) = tree.insert_and_get_mut("root");
+    ///
+    /// let (child_id, child) = tree.insert_and_get_mut("child");
+    /// child.set_parent(root_id);
+    /// child.value = "renamed";
+    ///
+    /// assert_eq!(tree.root_id(), Some(root_id));
+    /// assert_eq!(tree[child_id].parent(), Some(root_id));
+    /// assert_eq!(tree[child_id].value, "renamed");
+    /// ```
+    pub fn insert_and_get_mut(&mut self, value: T) -> (Number, &mut Node<T>) {
+        let mut id = Node::<T>::generate_id();
+        while self.nodes.contains_key(&FloatId::from(id)) {
+            id = Node::<T>::generate_id();
+        }
+        self.add_node(Node::with_id(value, id));
+        (id, self.nodes.get_mut(&FloatId::from(id)).unwrap())
+    }
+
     /// Get a node by ID
     ///
     /// Returns a reference to the node with the given ID, or `None` if no such
@@ -1124,6 +1876,47 @@ impl<T> Tree<T> {
         self.nodes.get_mut(&FloatId::from(id))
     }
 
+    /// Get a node by an already-wrapped `FloatId`
+    ///
+    /// This is an advanced API for hot loops: it skips the `Number` to
+    /// `FloatId` conversion that `get_node` performs on every call. Prefer
+    /// `get_node` unless you already hold `FloatId` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{FloatId, Node, Tree};
+    ///
+    /// let mut tree = Tree::new();
+    /// let node_id = tree.add_node(Node::new("test")).unwrap();
+    ///
+    /// let id = FloatId::from(node_id);
+    /// assert_eq!(tree.get_node_by_floatid(id).unwrap().value, "test");
+    /// assert!(tree.get_node_by_floatid(FloatId::new(-1.0)).is_none());
+    /// ```
+    pub fn get_node_by_floatid(&self, id: FloatId) -> Option<&Node<T>> {
+        self.nodes.get(&id)
+    }
+
+    /// Check if a node with the given ID exists
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, Tree};
+    ///
+    /// let mut tree = Tree::new();
+    /// tree.add_node(Node::with_id("negative", -2.0));
+    /// tree.add_node(Node::with_id("fractional", 0.5));
+    ///
+    /// assert!(tree.contains_id(-2.0));
+    /// assert!(tree.contains_id(0.5));
+    /// assert!(!tree.contains_id(2.0));
+    /// ```
+    pub fn contains_id(&self, id: Number) -> bool {
+        self.nodes.contains_key(&FloatId::from(id))
+    }
+
     /// Get the root node
     ///
     /// Returns a reference to the root node of the tree, or `None` if the tree
@@ -1149,6 +1942,35 @@ impl<T> Tree<T> {
         self.root_id.and_then(|id| self.get_node(id.value()))
     }
 
+    /// Get the root node, explaining why there is none
+    ///
+    /// Returns [`TreeError::Empty`] if the tree has no nodes, and
+    /// [`TreeError::MissingRoot`] if it has nodes but no root is set. This
+    /// tells an empty tree apart from a single-leaf tree, which
+    /// [`Tree::height`] cannot since both report 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node, TreeError};
+    ///
+    /// let mut tree = Tree::new();
+    /// assert_eq!(tree.try_root().unwrap_err(), TreeError::Empty);
+    ///
+    /// let leaf_id = tree.add_node_no_root(Node::new("leaf")).unwrap();
+    /// assert_eq!(tree.try_root().unwrap_err(), TreeError::MissingRoot);
+    ///
+    /// tree.set_root(leaf_id);
+    /// assert_eq!(tree.try_root().unwrap().value, "leaf");
+    /// assert_eq!(tree.height(leaf_id), 0);
+    /// ```
+    pub fn try_root(&self) -> Result<&Node<T>, TreeError> {
+        if self.nodes.is_empty() {
+            return Err(TreeError::Empty);
+        }
+        self.root().ok_or(TreeError::MissingRoot)
+    }
+
     /// Get the root ID
     ///
     /// Returns the ID of the root node, or `None` if the tree is empty.
@@ -1178,31 +2000,23 @@ impl<T> Tree<T> {
     }
 
     /// Remove a node
-    #[allow(dead_code)]
-    pub fn remove_node(&mut self, id: Number) {
-        self.nodes.remove(&FloatId::from(id));
-    }
-
-    /// Get the minimum value in the tree
-    pub fn min(&self) -> Option<&T>
-    where
-        T: Ord,
-    {
-        self.nodes.values().map(|node| &node.value).min()
-    }
-
-    /// Get the maximum value in the tree
-    pub fn max(&self) -> Option<&T>
-    where
-        T: Ord,
-    {
-        self.nodes.values().map(|node| &node.value).max()
+    ///
+    /// Returns the removed node, or `None` if no such node exists. Links to
+    /// the node from other nodes are not updated.
+    pub fn remove_node(&mut self, id: Number) -> Option<Node<T>> {
+        let id = FloatId::from(id);
+        self.meta.remove(&id);
+        if let Some(tick) = self.last_access.remove(&id) {
+            self.access_order.remove(&(tick, id));
+        }
+        self.nodes.remove(&id)
     }
 
-    /// Set the root node
+    /// Attach metadata to a node
     ///
-    /// Sets the node with the given ID as the root of the tree. The node must
-    /// already exist in the tree.
+    /// Stores `meta` alongside the node with the given ID, replacing any
+    /// metadata previously attached to it. Returns `false` (and stores
+    /// nothing) if no such node exists.
     ///
     /// # Examples
     ///
@@ -1210,19 +2024,24 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// let node = Node::new("root");
-    /// let node_id = tree.add_node(node).unwrap();
+    /// let id = tree.add_node(Node::new("test")).unwrap();
     ///
-    /// tree.set_root(node_id);
-    /// assert_eq!(tree.root_id(), Some(node_id));
+    /// assert!(tree.set_meta(id, "red"));
+    /// assert!(!tree.set_meta(999.0, "blue"));
+    /// assert_eq!(tree.meta::<&str>(id), Some(&"red"));
     /// ```
-    pub fn set_root(&mut self, id: Number) {
-        self.root_id = Some(FloatId::from(id));
+    pub fn set_meta<M: Any + Send + Sync>(&mut self, id: Number, meta: M) -> bool {
+        let id = FloatId::from(id);
+        if !self.nodes.contains_key(&id) {
+            return false;
+        }
+        self.meta.insert(id, Box::new(meta));
+        true
     }
 
-    /// Get the number of nodes in the tree
+    /// Get the metadata attached to a node
     ///
-    /// Returns the total number of nodes currently in the tree.
+    /// Returns `None` if the node has no metadata or if it is not of type `M`.
     ///
     /// # Examples
     ///
@@ -1230,22 +2049,22 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// assert_eq!(tree.size(), 0);
-    ///
-    /// let node1 = Node::new("first");
-    /// let node2 = Node::new("second");
-    /// tree.add_node(node1);
-    /// tree.add_node(node2);
+    /// let id = tree.add_node(Node::new("test")).unwrap();
+    /// assert_eq!(tree.meta::<bool>(id), None);
     ///
-    /// assert_eq!(tree.size(), 2);
+    /// tree.set_meta(id, true);
+    /// assert_eq!(tree.meta::<bool>(id), Some(&true));
+    /// assert_eq!(tree.meta::<u32>(id), None);
     /// ```
-    pub fn size(&self) -> usize {
-        self.nodes.len()
+    pub fn meta<M: Any>(&self, id: Number) -> Option<&M> {
+        self.meta
+            .get(&FloatId::from(id))
+            .and_then(|meta| meta.downcast_ref())
     }
 
-    /// Check if the tree is empty
+    /// Get a mutable reference to the metadata attached to a node
     ///
-    /// Returns `true` if the tree contains no nodes, `false` otherwise.
+    /// Returns `None` if the node has no metadata or if it is not of type `M`.
     ///
     /// # Examples
     ///
@@ -1253,104 +2072,181 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// assert!(tree.is_empty());
+    /// let id = tree.add_node(Node::new("test")).unwrap();
+    /// tree.set_meta(id, 1_u32);
     ///
-    /// let node = Node::new("test");
-    /// tree.add_node(node);
-    /// assert!(!tree.is_empty());
+    /// if let Some(visits) = tree.meta_mut::<u32>(id) {
+    ///     *visits += 1;
+    /// }
+    /// assert_eq!(tree.meta::<u32>(id), Some(&2));
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.nodes.is_empty()
+    pub fn meta_mut<M: Any>(&mut self, id: Number) -> Option<&mut M> {
+        self.meta
+            .get_mut(&FloatId::from(id))
+            .and_then(|meta| meta.downcast_mut())
     }
 
-    /// Search for a node by its value
-    ///
-    /// Returns the ID of the first node found with the given value, or None if not found.
+    /// Remove the metadata attached to a node
     ///
-    /// # Arguments
+    /// Returns `true` if the node had metadata.
+    pub fn remove_meta(&mut self, id: Number) -> bool {
+        self.meta.remove(&FloatId::from(id)).is_some()
+    }
+
+    /// Remove the metadata attached to every node
+    pub fn clear_meta(&mut self) {
+        self.meta.clear();
+    }
+
+    /// Cap the number of nodes kept by [`Tree::add_child_value`]
     ///
-    /// * `value` - The value to search for
+    /// Once the cap is set, adding a child beyond it evicts the least
+    /// recently used leaf. Only leaves are eligible for eviction, so the tree
+    /// stays connected; the root and the node just added are never evicted.
+    /// Nodes already in the tree count as accessed before any tracked access.
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::Tree;
+    /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// let node = tree.add_node(jangal::Node::new(42));
-    /// tree.set_root(node.unwrap());
-    ///
-    /// let found_id = tree.search_by_value(&42);
-    /// assert!(found_id.is_some());
+    /// let root = tree.add_node(Node::new("root")).unwrap();
+    /// tree.set_max_nodes(3);
+    ///
+    /// let a = tree.add_child_value(root, "a").unwrap();
+    /// let b = tree.add_child_value(root, "b").unwrap();
+    /// tree.touch(a);
+    ///
+    /// // `b` is now the least recently used leaf
+    /// let c = tree.add_child_value(root, "c").unwrap();
+    /// assert_eq!(tree.size(), 3);
+    /// assert!(tree.get_node(b).is_none());
+    /// assert!(tree.get_node(a).is_some());
+    /// assert!(tree.get_node(c).is_some());
     /// ```
-    pub fn search_by_value(&self, value: &T) -> Option<Number>
-    where
-        T: PartialEq,
-    {
-        for (id, node) in &self.nodes {
-            if node.value == *value {
-                return Some(id.value());
-            }
+    pub fn set_max_nodes(&mut self, cap: usize) {
+        self.max_nodes = Some(cap);
+        let ids: Vec<FloatId> = self.nodes.keys().copied().collect();
+        for id in ids {
+            self.track_untouched(id);
         }
-        None
+        self.evict_to_capacity(None);
     }
 
-    /// Calculate the height of a node
+    /// Get the node cap set by [`Tree::set_max_nodes`]
+    pub fn max_nodes(&self) -> Option<usize> {
+        self.max_nodes
+    }
+
+    /// Create an empty tree whose nodes may have at most `k` children
     ///
-    /// The height of a node is the length of the longest path from the node
-    /// to a leaf. A leaf node has height 0.
+    /// Shorthand for [`Tree::new`] followed by [`Tree::set_max_children`].
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::{Tree, Node};
+    /// use jangal::{Node, Tree, TreeError};
+    ///
+    /// let mut tree = Tree::with_max_children(2);
+    /// let root = tree.add_node(Node::new("root")).unwrap();
+    /// tree.try_add_child_value(root, "left").unwrap();
+    /// tree.try_add_child_value(root, "right").unwrap();
+    ///
+    /// assert_eq!(
+    ///     tree.try_add_child_value(root, "extra"),
+    ///     Err(TreeError::TooManyChildren { max: 2 })
+    /// );
+    /// assert_eq!(tree.add_child_value(root, "extra"), None);
+    /// assert_eq!(tree.size(), 3);
+    /// ```
+    pub fn with_max_children(k: usize) -> Self {
+        let mut tree = Self::new();
+        tree.set_max_children(k);
+        tree
+    }
+
+    /// Cap the number of children [`Tree::add_child_value`] gives a node
+    ///
+    /// Once set, [`Tree::add_child_value`] and
+    /// [`Tree::try_add_child_value`] refuse to link a new child to a node
+    /// that already has `k` children. Nodes that already have more than
+    /// `k` children are left as they are; use [`Tree::overfull_nodes`] to
+    /// find them. Links made directly through [`Node::add_child`] or by
+    /// restructuring methods such as [`Tree::merge_duplicate_children`]
+    /// are not checked.
+    pub fn set_max_children(&mut self, k: usize) {
+        self.max_children = Some(k);
+    }
+
+    /// Get the child cap set by [`Tree::set_max_children`]
+    pub fn max_children(&self) -> Option<usize> {
+        self.max_children
+    }
+
+    /// List the nodes with more children than the cap allows
     ///
-    /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child = Node::new("child");
-    /// let grandchild = Node::new("grandchild");
+    /// IDs are returned in ascending order. Empty if no cap is set.
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child_id = tree.add_node(child).unwrap();
-    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    /// # Examples
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child_id);
-    /// }
-    /// if let Some(child_node) = tree.get_node_mut(child_id) {
-    ///     child_node.set_parent(root_id);
-    ///     child_node.add_child(grandchild_id);
-    /// }
-    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
-    ///     grandchild_node.set_parent(child_id);
-    /// }
+    /// ```
+    /// use jangal::Tree;
     ///
-    /// tree.set_root(root_id);
+    /// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(0)]).unwrap();
+    /// assert!(tree.overfull_nodes().is_empty());
     ///
-    /// assert_eq!(tree.height(root_id), 2);
-    /// assert_eq!(tree.height(child_id), 1);
-    /// assert_eq!(tree.height(grandchild_id), 0);
+    /// tree.set_max_children(2);
+    /// assert_eq!(tree.overfull_nodes(), vec![0.0]);
     /// ```
-    pub fn height(&self, node_id: Number) -> usize {
-        if let Some(node) = self.get_node(node_id) {
-            if node.is_leaf() {
-                return 0;
-            }
-            let mut max_height = 0;
-            for child_id in node.children() {
-                let child_height = self.height(child_id);
-                max_height = max_height.max(child_height);
-            }
-            return 1 + max_height;
+    pub fn overfull_nodes(&self) -> Vec<Number> {
+        let Some(max) = self.max_children else {
+            return Vec::new();
+        };
+        let mut ids: Vec<FloatId> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.children.len() > max)
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter().map(|id| id.value()).collect()
+    }
+
+    /// Mark a node as recently used
+    ///
+    /// Returns `false` if no such node exists.
+    pub fn touch(&mut self, id: Number) -> bool {
+        let id = FloatId::from(id);
+        if !self.nodes.contains_key(&id) {
+            return false;
+        }
+        self.access_clock += 1;
+        if let Some(tick) = self.last_access.insert(id, self.access_clock) {
+            self.access_order.remove(&(tick, id));
+        }
+        self.access_order.insert((self.access_clock, id));
+        true
+    }
+
+    /// Start tracking a node as accessed before any tracked access
+    ///
+    /// Only needed while a node cap is set, so that eviction can find the
+    /// least recently used leaf without scanning every node.
+    fn track_untouched(&mut self, id: FloatId) {
+        if self.max_nodes.is_some() && !self.last_access.contains_key(&id) {
+            self.last_access.insert(id, 0);
+            self.access_order.insert((0, id));
         }
-        0
     }
 
-    /// Calculate the depth of a node
+    /// Add a new node holding `value` as a child of `parent_id`
     ///
-    /// The depth of a node is the length of the path from the root to the node.
-    /// The root node has depth 0.
+    /// Returns the new node's ID, or `None` if the parent does not exist or
+    /// already has as many children as [`Tree::set_max_children`] allows;
+    /// [`Tree::try_add_child_value`] tells these cases apart. If a cap is
+    /// set with [`Tree::set_max_nodes`] and the tree grows beyond it, least
+    /// recently used leaves are evicted and detached from their parents.
     ///
     /// # Examples
     ///
@@ -1358,58 +2254,113 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child = Node::new("child");
-    /// let grandchild = Node::new("grandchild");
+    /// let root = tree.add_node(Node::new(1)).unwrap();
+    /// let child = tree.add_child_value(root, 2).unwrap();
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child_id = tree.add_node(child).unwrap();
-    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    /// assert_eq!(tree.get_node(child).unwrap().parent(), Some(root));
+    /// assert_eq!(tree.root().unwrap().children(), vec![child]);
+    /// assert_eq!(tree.add_child_value(999.0, 3), None);
+    /// ```
+    pub fn add_child_value(&mut self, parent_id: Number, value: T) -> Option<Number> {
+        self.try_add_child_value(parent_id, value).ok()
+    }
+
+    /// Add a new node holding `value` as a child of `parent_id`, or say why not
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child_id);
-    /// }
-    /// if let Some(child_node) = tree.get_node_mut(child_id) {
-    ///     child_node.set_parent(root_id);
-    ///     child_node.add_child(grandchild_id);
-    /// }
-    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
-    ///     grandchild_node.set_parent(child_id);
-    /// }
+    /// Behaves like [`Tree::add_child_value`], including eviction under
+    /// [`Tree::set_max_nodes`].
     ///
-    /// tree.set_root(root_id);
+    /// # Errors
     ///
-    /// assert_eq!(tree.depth(root_id), 0);
-    /// assert_eq!(tree.depth(child_id), 1);
-    /// assert_eq!(tree.depth(grandchild_id), 2);
-    /// ```
-    pub fn depth(&self, node_id: Number) -> usize {
-        self.depth_recursive(FloatId::from(node_id), &mut HashSet::new())
-    }
+    /// Returns [`TreeError::MissingNode`] if the parent does not exist, and
+    /// [`TreeError::TooManyChildren`] if it already has as many children as
+    /// [`Tree::set_max_children`] allows.
+    pub fn try_add_child_value(
+        &mut self,
+        parent_id: Number,
+        value: T,
+    ) -> Result<Number, TreeError> {
+        let parent = FloatId::from(parent_id);
+        let Some(parent_node) = self.nodes.get(&parent) else {
+            return Err(TreeError::MissingNode);
+        };
+        if let Some(max) = self.max_children {
+            if parent_node.children.len() >= max {
+                return Err(TreeError::TooManyChildren { max });
+            }
+        }
 
-    fn depth_recursive(&self, node_id: FloatId, visited: &mut HashSet<FloatId>) -> usize {
-        if visited.contains(&node_id) {
-            return 0; // Prevent infinite recursion
+        // `Node::id` is public, so a node may have been given a generated ID
+        // after the fact; never overwrite it
+        let mut child_id = Node::<T>::generate_id();
+        while self.nodes.contains_key(&FloatId::from(child_id)) {
+            child_id = Node::<T>::generate_id();
+        }
+        let mut child = Node::with_id(value, child_id);
+        child.set_parent(parent_id);
+        self.nodes.insert(FloatId::from(child_id), child);
+        if let Some(node) = self.nodes.get_mut(&parent) {
+            node.add_child(child_id);
         }
 
-        visited.insert(node_id);
+        self.touch(parent_id);
+        self.touch(child_id);
+        self.evict_to_capacity(Some(FloatId::from(child_id)));
+        Ok(child_id)
+    }
 
-        if let Some(node) = self.nodes.get(&node_id) {
-            if node.is_root() {
-                return 0;
-            }
-            if let Some(parent_id) = node.parent() {
-                return 1 + self.depth_recursive(FloatId::from(parent_id), visited);
+    /// Evict least recently used leaves until the tree fits its cap
+    fn evict_to_capacity(&mut self, keep: Option<FloatId>) {
+        let Some(cap) = self.max_nodes else {
+            return;
+        };
+        while self.nodes.len() > cap {
+            // Walk nodes from least to most recently used; only the non-leaf
+            // nodes used before the victim are skipped over
+            let victim = self.access_order.iter().map(|&(_, id)| id).find(|id| {
+                Some(*id) != self.root_id
+                    && Some(*id) != keep
+                    && self.nodes.get(id).is_some_and(|node| node.is_leaf())
+            });
+            let Some(victim) = victim else {
+                break;
+            };
+
+            let removed = self.remove_node(victim.value());
+            if let Some(parent_id) = removed.and_then(|node| node.parent()) {
+                if let Some(parent) = self.nodes.get_mut(&FloatId::from(parent_id)) {
+                    parent.remove_child(victim.value());
+                    if parent.left() == Some(victim.value()) {
+                        parent.clear_left();
+                    }
+                    if parent.right() == Some(victim.value()) {
+                        parent.clear_right();
+                    }
+                }
             }
         }
-        0
     }
 
-    /// Count the number of leaves in the subtree rooted at the given node
+    /// Get the minimum value in the tree
+    pub fn min(&self) -> Option<&T>
+    where
+        T: Ord,
+    {
+        self.nodes.values().map(|node| &node.value).min()
+    }
+
+    /// Get the maximum value in the tree
+    pub fn max(&self) -> Option<&T>
+    where
+        T: Ord,
+    {
+        self.nodes.values().map(|node| &node.value).max()
+    }
+
+    /// Count the number of distinct values in the tree
     ///
-    /// A leaf is a node with no children. This method recursively counts all
-    /// leaf nodes in the subtree.
+    /// Every node in the tree is considered, including nodes that are not
+    /// reachable from the root.
     ///
     /// # Examples
     ///
@@ -1417,159 +2368,250 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
-    /// let grandchild = Node::new("grandchild");
+    /// assert_eq!(tree.distinct_value_count(), 0);
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
-    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    /// tree.add_node(Node::new("a"));
+    /// tree.add_node(Node::new("b"));
+    /// tree.add_node(Node::new("a"));
+    /// assert_eq!(tree.distinct_value_count(), 2);
+    /// ```
+    pub fn distinct_value_count(&self) -> usize
+    where
+        T: Hash + Eq,
+    {
+        self.nodes
+            .values()
+            .map(|node| &node.value)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Apply a closure to every node's value in place
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    ///     child1_node.add_child(grandchild_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
-    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
-    ///     grandchild_node.set_parent(child1_id);
-    /// }
+    /// Every node in the tree is visited, including nodes that are not
+    /// reachable from the root, in no particular order. Only values change;
+    /// IDs and links are left untouched.
     ///
-    /// tree.set_root(root_id);
+    /// # Examples
     ///
-    /// assert_eq!(tree.num_leaves(root_id), 2);
-    /// assert_eq!(tree.num_leaves(child1_id), 1);
-    /// assert_eq!(tree.num_leaves(child2_id), 1);
     /// ```
-    pub fn num_leaves(&self, node_id: Number) -> usize {
-        if let Some(node) = self.get_node(node_id) {
-            if node.is_leaf() {
-                return 1;
-            }
-            let mut count = 0;
-            for child_id in node.children() {
-                count += self.num_leaves(child_id);
-            }
-            return count;
+    /// use jangal::Tree;
+    ///
+    /// let mut tree = Tree::from_level_order(vec![Some(" Root ".to_string()), Some("Leaf".to_string())]);
+    /// let root = tree.root_id().unwrap();
+    ///
+    /// tree.for_each_value_mut(|value| *value = value.trim().to_lowercase());
+    /// assert_eq!(tree[root].value, "root");
+    /// assert_eq!(tree.to_level_order(root)[1].as_deref(), Some("leaf"));
+    /// ```
+    pub fn for_each_value_mut<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        for node in self.nodes.values_mut() {
+            f(&mut node.value);
         }
-        0
     }
 
-    /// Count the total number of nodes in the subtree rooted at the given node
+    /// Iterate mutably over every node in the tree
     ///
-    /// This method recursively counts all nodes in the subtree, including the
-    /// root node itself.
+    /// Every node is yielded once, including nodes that are not reachable
+    /// from the root. The iteration order is unspecified. As with
+    /// [`Tree::get_node_mut`], changing a node's `id` through the returned
+    /// reference is not supported, since the tree indexes nodes by ID.
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::{Tree, Node};
-    ///
-    /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
+    /// use jangal::Tree;
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
+    /// let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3)]);
+    /// let root = tree.root_id().unwrap();
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
+    /// for node in tree.iter_mut() {
+    ///     if node.is_leaf() {
+    ///         node.value *= 10;
+    ///     }
     /// }
+    /// assert_eq!(tree.to_level_order(root), vec![Some(1), Some(20), Some(30)]);
+    /// ```
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Node<T>> + '_ {
+        self.nodes.values_mut()
+    }
+
+    /// Apply a closure to every node of a subtree in breadth-first order
     ///
-    /// tree.set_root(root_id);
+    /// Visits the same nodes in the same order as [`Tree::bfs`], giving
+    /// ordered mutable access without holding several `&mut` references at
+    /// once. A node's children are read after `f` has run on it, so `f` sees
+    /// every ancestor of a node updated before the node itself. A missing
+    /// `start` does nothing.
+    ///
+    /// # Examples
     ///
-    /// assert_eq!(tree.num_nodes(root_id), 3);
-    /// assert_eq!(tree.num_nodes(child1_id), 1);
-    /// assert_eq!(tree.num_nodes(child2_id), 1);
     /// ```
-    pub fn num_nodes(&self, node_id: Number) -> usize {
-        if let Some(node) = self.get_node(node_id) {
-            let mut count = 1;
-            for child_id in node.children() {
-                count += self.num_nodes(child_id);
+    /// use jangal::Tree;
+    ///
+    /// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(1)]).unwrap();
+    ///
+    /// let mut running_total = 0;
+    /// tree.for_each_bfs_mut(0.0, |node| {
+    ///     running_total += node.value;
+    ///     node.value = running_total;
+    /// });
+    /// assert_eq!(tree.values_bfs(0.0), vec![&0, &1, &3]);
+    /// ```
+    pub fn for_each_bfs_mut<F: FnMut(&mut Node<T>)>(&mut self, start: Number, mut f: F) {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        let start = FloatId::from(start);
+        queue.push_back(start);
+        visited.insert(start);
+
+        while let Some(current_id) = queue.pop_front() {
+            if let Some(node) = self.nodes.get_mut(&current_id) {
+                f(node);
+                for child_id in node.children_iter() {
+                    let child_id = FloatId::from(child_id);
+                    if visited.insert(child_id) {
+                        queue.push_back(child_id);
+                    }
+                }
             }
-            return count;
         }
-        0
     }
 
-    /// Check if the tree is balanced (all leaf nodes are at most one level apart)
+    /// Count the parent/child links whose values satisfy a predicate
     ///
-    /// A tree is considered balanced if the heights of all subtrees differ by
-    /// at most 1.
+    /// `f` is called with `(parent_value, child_value)` for every child link
+    /// of every node in the tree, including nodes that are not reachable
+    /// from the root. Links to missing nodes are skipped.
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::{Tree, Node};
+    /// use jangal::Tree;
     ///
-    /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
+    /// //     5
+    /// //    / \
+    /// //   3   8
+    /// //  /
+    /// // 4
+    /// let tree = Tree::from_level_order(vec![Some(5), Some(3), Some(8), Some(4)]);
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
+    /// assert_eq!(tree.count_edges_where(|parent, child| child < parent), 1);
+    /// assert_eq!(tree.count_edges_where(|parent, child| child > parent), 2);
+    /// ```
+    pub fn count_edges_where<F: Fn(&T, &T) -> bool>(&self, f: F) -> usize {
+        self.nodes
+            .values()
+            .flat_map(|parent| {
+                parent
+                    .children
+                    .iter()
+                    .filter_map(|child_id| self.nodes.get(child_id))
+                    .map(move |child| (parent, child))
+            })
+            .filter(|(parent, child)| f(&parent.value, &child.value))
+            .count()
+    }
+
+    /// Count the nodes whose subtree satisfies a predicate
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
+    /// `f` is called as `f(self, id)` once for every node in the tree,
+    /// including nodes that are not reachable from the root, in ascending
+    /// ID order. It receives the whole tree so it can judge the subtree
+    /// rooted at `id` with methods such as [`Tree::is_balanced`],
+    /// [`Tree::num_nodes`] or [`Tree::preorder`]. Each call is independent,
+    /// so a predicate that walks the subtree makes the whole count
+    /// quadratic in the worst case.
     ///
-    /// tree.set_root(root_id);
+    /// # Examples
     ///
-    /// // This tree is balanced: both children are at the same level
-    /// assert!(tree.is_balanced(root_id));
     /// ```
-    pub fn is_balanced(&self, node_id: Number) -> bool {
-        if let Some(node) = self.get_node(node_id) {
-            if node.is_leaf() {
-                return true;
-            }
-
-            let mut heights = Vec::new();
-            for child_id in node.children() {
-                heights.push(self.height(child_id));
-            }
-            heights.sort_by(|a, b| b.cmp(a));
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// //   |
+    /// //   4
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(3)]).unwrap();
+    ///
+    /// assert_eq!(tree.count_subtrees_where(|tree, id| tree.is_balanced(id)), 4);
+    /// let sum_over_4 = |tree: &Tree<usize>, id| {
+    ///     tree.preorder(id).iter().map(|node| node.value).sum::<usize>() > 4
+    /// };
+    /// assert_eq!(tree.count_subtrees_where(sum_over_4), 3);
+    /// ```
+    pub fn count_subtrees_where<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&Tree<T>, Number) -> bool,
+    {
+        let mut ids: Vec<FloatId> = self.nodes.keys().copied().collect();
+        ids.sort_unstable();
+        ids.into_iter().filter(|id| f(self, id.value())).count()
+    }
 
-            if let Some(&max_height) = heights.first() {
-                return heights.iter().all(|&h| max_height - h <= 1);
-            }
-        }
-        true
+    /// List every parent→child link as a `(parent, child)` pair of IDs
+    ///
+    /// Covers the `children` sets of every node in the tree, including
+    /// nodes that are not reachable from the root; links to missing nodes
+    /// are skipped. Pairs are sorted by parent ID, then child ID, so the
+    /// output is deterministic.
+    ///
+    /// This is the shape graph libraries such as `petgraph` accept without
+    /// jangal depending on them. `petgraph::Graph::from_edges` wants integer
+    /// node indices, so call [`Tree::renumber`] first to make the IDs
+    /// `0, 1, 2, ...` and cast them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    /// assert_eq!(tree.edge_list(), vec![(0.0, 1.0), (0.0, 2.0), (1.0, 3.0)]);
+    ///
+    /// tree.renumber();
+    /// let edges: Vec<(u32, u32)> = tree
+    ///     .edge_list()
+    ///     .into_iter()
+    ///     .map(|(parent, child)| (parent as u32, child as u32))
+    ///     .collect();
+    /// // let graph = petgraph::Graph::<(), ()>::from_edges(&edges);
+    /// assert_eq!(edges, vec![(0, 1), (0, 2), (1, 3)]);
+    /// ```
+    pub fn edge_list(&self) -> Vec<(Number, Number)> {
+        let mut edges: Vec<(FloatId, FloatId)> = self
+            .nodes
+            .iter()
+            .flat_map(|(&parent_id, parent)| {
+                parent
+                    .children
+                    .iter()
+                    .filter(|child_id| self.nodes.contains_key(child_id))
+                    .map(move |&child_id| (parent_id, child_id))
+            })
+            .collect();
+        edges.sort_unstable();
+        edges
+            .into_iter()
+            .map(|(parent_id, child_id)| (parent_id.value(), child_id.value()))
+            .collect()
     }
 
-    /// Get all leaf values in the subtree
+    /// Set the root node
     ///
-    /// Returns a vector containing references to all leaf nodes
-    /// in the subtree rooted at the given node.
+    /// Sets the node with the given ID as the root of the tree and returns
+    /// `true`. IDs of nodes not in the tree, including any ID on an empty
+    /// tree, are ignored: the current root is left unchanged and `false` is
+    /// returned.
     ///
     /// # Examples
     ///
@@ -1577,193 +2619,214 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
-    /// let grandchild = Node::new("grandchild");
-    ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
-    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    /// assert!(!tree.set_root(1.0));
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    ///     child1_node.add_child(grandchild_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
-    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
-    ///     grandchild_node.set_parent(child1_id);
-    /// }
+    /// let node = Node::new("root");
+    /// let node_id = tree.add_node(node).unwrap();
     ///
-    /// tree.set_root(root_id);
+    /// assert!(tree.set_root(node_id));
+    /// assert_eq!(tree.root_id(), Some(node_id));
     ///
-    /// let leaves = tree.get_leaves(root_id);
-    /// assert_eq!(leaves.len(), 2);
-    /// assert!(leaves.iter().any(|node| node.value == "child2"));
-    /// assert!(leaves.iter().any(|node| node.value == "grandchild"));
+    /// assert!(!tree.set_root(-1.0));
+    /// assert_eq!(tree.root_id(), Some(node_id));
     /// ```
-    pub fn get_leaves(&self, node_id: Number) -> Vec<&Node<T>> {
-        if let Some(node) = self.get_node(node_id) {
-            if node.is_leaf() {
-                return vec![node];
-            }
-            let mut leaves = Vec::new();
-            for child_id in node.children() {
-                leaves.extend(self.get_leaves(child_id));
-            }
-            return leaves;
+    pub fn set_root(&mut self, id: Number) -> bool {
+        let id = FloatId::from(id);
+        if !self.nodes.contains_key(&id) {
+            return false;
         }
-        Vec::new()
+        self.root_id = Some(id);
+        true
     }
 
-    /// Perform depth-first search traversal
+    /// Clone the tree, assigning every node a fresh unique ID
     ///
-    /// Traverses the subtree in depth-first order, visiting nodes as deep as
-    /// possible before backtracking. Returns a vector of nodes in traversal order.
+    /// Returns the copy together with a map from each original ID to its new
+    /// ID. All references between nodes (parent, children, left/right and
+    /// edges) are rewritten, so the copy can be merged into the same forest
+    /// as the original without ID collisions. Metadata is not copied.
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::{Tree, Node};
-    ///
-    /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
-    /// let grandchild = Node::new("grandchild");
+    /// use jangal::{FloatId, Tree};
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
-    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    /// let tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3)]);
+    /// let (copy, id_map) = tree.clone_with_new_ids();
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    ///     child1_node.add_child(grandchild_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
-    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
-    ///     grandchild_node.set_parent(child1_id);
-    /// }
-    ///
-    /// tree.set_root(root_id);
-    ///
-    /// let dfs_result = tree.dfs(root_id);
-    /// assert_eq!(dfs_result.len(), 4);
+    /// let root_id = tree.root_id().unwrap();
+    /// let new_root_id = copy.root_id().unwrap();
+    /// assert_ne!(root_id, new_root_id);
+    /// assert_eq!(id_map[&FloatId::from(root_id)], new_root_id);
+    /// assert_eq!(copy.to_level_order(new_root_id), tree.to_level_order(root_id));
     /// ```
-    pub fn dfs(&self, node_id: Number) -> Vec<&Node<T>> {
-        let mut visited = HashSet::new();
-        let mut result = Vec::new();
-        self.dfs_recursive(FloatId::from(node_id), &mut visited, &mut result);
-        result
-    }
-
-    fn dfs_recursive<'a>(
-        &'a self,
-        node_id: FloatId,
-        visited: &mut HashSet<FloatId>,
-        result: &mut Vec<&'a Node<T>>,
-    ) {
-        if visited.contains(&node_id) {
-            return;
+    pub fn clone_with_new_ids(&self) -> (Tree<T>, HashMap<FloatId, Number>)
+    where
+        T: Clone,
+    {
+        let map: HashMap<FloatId, FloatId> = self
+            .nodes
+            .keys()
+            .map(|&id| (id, FloatId::from(Node::<T>::generate_id())))
+            .collect();
+
+        let mut copy = Tree::new();
+        for node in self.nodes.values() {
+            let mut node = node.clone();
+            node.remap_ids(&map);
+            copy.nodes.insert(FloatId::from(node.id), node);
         }
+        copy.root_id = self.root_id.and_then(|id| map.get(&id).copied());
 
-        visited.insert(node_id);
-
-        if let Some(node) = self.nodes.get(&node_id) {
-            result.push(node);
-            for child_id in node.children() {
-                self.dfs_recursive(FloatId::from(child_id), visited, result);
-            }
-        }
+        let id_map = map
+            .into_iter()
+            .map(|(old, new)| (old, new.value()))
+            .collect();
+        (copy, id_map)
     }
 
-    /// Perform breadth-first search traversal
+    /// Reassign node IDs to `0, 1, 2, ...` in breadth-first order
     ///
-    /// Traverses the subtree level by level, visiting all nodes at the current
-    /// level before moving to the next level. Returns a vector of nodes in traversal order.
+    /// Nodes reachable from the root are numbered first, visiting children
+    /// in ascending ID order; any remaining nodes follow in ascending ID
+    /// order. All references between nodes (parent, children, left/right and
+    /// edges), the root and attached metadata are rewritten. Returns a map
+    /// from each old ID to its new ID. The new IDs are reserved as in
+    /// [`Node::with_id`], so nodes added afterwards never reuse them.
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::{Tree, Node};
+    /// use jangal::{FloatId, Tree};
     ///
-    /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
-    /// let grandchild = Node::new("grandchild");
+    /// let mut tree = Tree::from_level_order(vec![Some("a"), Some("b"), Some("c")]);
+    /// let old_root = tree.root_id().unwrap();
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
-    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    /// let id_map = tree.renumber();
+    /// assert_eq!(id_map[&FloatId::from(old_root)], 0.0);
+    /// assert_eq!(tree.root_id(), Some(0.0));
+    /// assert_eq!(tree[0.0].value, "a");
+    /// assert_eq!(tree.to_level_order(0.0), vec![Some("a"), Some("b"), Some("c")]);
+    /// ```
+    pub fn renumber(&mut self) -> HashMap<FloatId, Number> {
+        let mut order: Vec<FloatId> = match self.root_id {
+            Some(root) => self
+                .bfs_sorted(root.value())
+                .into_iter()
+                .map(|node| FloatId::from(node.id))
+                .collect(),
+            None => Vec::new(),
+        };
+        let reached: HashSet<FloatId> = order.iter().copied().collect();
+        let mut rest: Vec<FloatId> = self
+            .nodes
+            .keys()
+            .copied()
+            .filter(|id| !reached.contains(id))
+            .collect();
+        rest.sort_unstable();
+        order.extend(rest);
+
+        let map: HashMap<FloatId, FloatId> = order
+            .into_iter()
+            .enumerate()
+            .map(|(i, old)| (old, FloatId::from(i as Number)))
+            .collect();
+
+        self.remap(&map);
+        map.into_iter()
+            .map(|(old, new)| (old, new.value()))
+            .collect()
+    }
+
+    /// Rename every node according to a map from old ID to new ID
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    ///     child1_node.add_child(grandchild_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
-    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
-    ///     grandchild_node.set_parent(child1_id);
-    /// }
+    /// All references between nodes (parent, children, left/right and
+    /// edges), the root and attached metadata are rewritten, as in
+    /// [`Tree::renumber`], whose returned map has this same shape.
     ///
-    /// tree.set_root(root_id);
+    /// # Errors
+    ///
+    /// Returns [`TreeError::InvalidIdMap`] and leaves the tree unchanged
+    /// unless the map has exactly one entry per node in the tree and no two
+    /// entries share a new ID.
+    ///
+    /// # Examples
     ///
-    /// let bfs_result = tree.bfs(root_id);
-    /// assert_eq!(bfs_result.len(), 4);
     /// ```
-    pub fn bfs(&self, node_id: Number) -> Vec<&Node<T>> {
-        let mut visited = HashSet::new();
-        let mut queue = VecDeque::new();
-        let mut result = Vec::new();
+    /// use std::collections::HashMap;
+    /// use jangal::{FloatId, Tree, TreeError};
+    ///
+    /// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0)]).unwrap();
+    ///
+    /// let map: HashMap<FloatId, f64> =
+    ///     HashMap::from([(0.0.into(), 10.0), (1.0.into(), 20.0), (2.0.into(), 30.0)]);
+    /// tree.apply_id_map(&map).unwrap();
+    /// assert_eq!(tree.root_id(), Some(10.0));
+    /// assert_eq!(tree[20.0].parent(), Some(10.0));
+    ///
+    /// let collision: HashMap<FloatId, f64> =
+    ///     HashMap::from([(10.0.into(), 1.0), (20.0.into(), 1.0), (30.0.into(), 2.0)]);
+    /// assert_eq!(tree.apply_id_map(&collision), Err(TreeError::InvalidIdMap));
+    /// assert_eq!(tree.root_id(), Some(10.0));
+    /// ```
+    pub fn apply_id_map(&mut self, map: &HashMap<FloatId, Number>) -> Result<(), TreeError> {
+        if map.len() != self.nodes.len() || !map.keys().all(|id| self.nodes.contains_key(id)) {
+            return Err(TreeError::InvalidIdMap);
+        }
+        let map: HashMap<FloatId, FloatId> = map
+            .iter()
+            .map(|(&old, &new)| (old, FloatId::from(new)))
+            .collect();
+        let targets: HashSet<FloatId> = map.values().copied().collect();
+        if targets.len() != map.len() {
+            return Err(TreeError::InvalidIdMap);
+        }
 
-        let node_id = FloatId::from(node_id);
-        queue.push_back(node_id);
-        visited.insert(node_id);
+        self.remap(&map);
+        Ok(())
+    }
 
-        while let Some(current_id) = queue.pop_front() {
-            if let Some(node) = self.nodes.get(&current_id) {
-                result.push(node);
-                for child_id in node.children() {
-                    let child_id = FloatId::from(child_id);
-                    if !visited.contains(&child_id) {
-                        visited.insert(child_id);
-                        queue.push_back(child_id);
-                    }
-                }
-            }
+    /// Rewrite every node, the root, metadata and access times through `map`
+    ///
+    /// `map` must cover every node; entries for IDs it does not cover are
+    /// dropped from the metadata and access times. The new IDs are reserved
+    /// as in [`Node::with_id`].
+    fn remap(&mut self, map: &HashMap<FloatId, FloatId>) {
+        for id in map.values() {
+            Node::<T>::reserve_id(id.value());
         }
-
-        result
+        self.nodes = self
+            .nodes
+            .drain()
+            .map(|(_, mut node)| {
+                node.remap_ids(map);
+                (FloatId::from(node.id), node)
+            })
+            .collect();
+        self.root_id = self.root_id.and_then(|id| map.get(&id).copied());
+        self.meta = self
+            .meta
+            .drain()
+            .filter_map(|(id, meta)| Some((*map.get(&id)?, meta)))
+            .collect();
+        self.last_access = self
+            .last_access
+            .drain()
+            .filter_map(|(id, tick)| Some((*map.get(&id)?, tick)))
+            .collect();
+        self.access_order = self
+            .last_access
+            .iter()
+            .map(|(&id, &tick)| (tick, id))
+            .collect();
     }
 
-    /// Perform preorder traversal
+    /// Get the IDs of all nodes without a parent
     ///
-    /// Traverses the subtree in preorder: root, left subtree, right subtree.
-    /// Returns a vector of nodes in traversal order.
+    /// A well-formed tree has exactly one such node, but a `Tree` can also
+    /// hold a forest, or nodes that were accidentally left disconnected. The
+    /// IDs are returned in ascending order.
     ///
     /// # Examples
     ///
@@ -1771,403 +2834,5191 @@ impl<T> Tree<T> {
     /// use jangal::{Tree, Node};
     ///
     /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
+    /// let a = tree.add_node(Node::with_id("a", 1.0)).unwrap();
+    /// let b = tree.add_node(Node::with_id("b", 2.0)).unwrap();
+    /// let c = tree.add_node(Node::with_id("c", 3.0)).unwrap();
+    /// tree.get_node_mut(a).unwrap().add_child(c);
+    /// tree.get_node_mut(c).unwrap().set_parent(a);
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
+    /// assert_eq!(tree.roots(), vec![a, b]);
+    /// ```
+    pub fn roots(&self) -> Vec<Number> {
+        let mut roots: Vec<FloatId> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.is_root())
+            .map(|(&id, _)| id)
+            .collect();
+        roots.sort_unstable();
+        roots.into_iter().map(|id| id.value()).collect()
+    }
+
+    /// Check that the nodes form a single well-formed tree
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
+    /// Verifies that exactly one node has no parent, that every other node is
+    /// listed as a child by exactly one node which is also its `parent`, that
+    /// no link refers to a missing node, that [`Tree::root_id`] is the node
+    /// without a parent, and that every node is reachable from the root,
+    /// which rules out cycles. Graph edges are not considered.
+    /// An empty tree is valid, as with [`Tree::from_parent_array`].
     ///
-    /// tree.set_root(root_id);
+    /// # Errors
+    ///
+    /// Returns [`TreeError::MultipleRoots`] or [`TreeError::MissingRoot`] if
+    /// there is not exactly one root, [`TreeError::MultipleParents`] if a node
+    /// is shared between parents, [`TreeError::InconsistentLinks`] if parent
+    /// and child links disagree, [`TreeError::RootMismatch`] if the root is
+    /// some other node, and [`TreeError::Cycle`] if some nodes are cut off
+    /// from the root by a cycle.
+    ///
+    /// # Examples
     ///
-    /// let preorder_result = tree.preorder(root_id);
-    /// assert_eq!(preorder_result.len(), 3);
     /// ```
-    pub fn preorder(&self, node_id: Number) -> Vec<&Node<T>> {
-        let mut result = Vec::new();
-        self.preorder_recursive(FloatId::from(node_id), &mut result);
-        result
-    }
+    /// use jangal::{Node, Tree, TreeError};
+    ///
+    /// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0)]).unwrap();
+    /// assert!(tree.validate_tree().is_ok());
+    ///
+    /// // The root must be the parentless node
+    /// tree.set_root(2.0);
+    /// assert_eq!(tree.validate_tree(), Err(TreeError::RootMismatch));
+    /// tree.set_root(0.0);
+    ///
+    /// // A second parentless node makes a forest
+    /// tree.add_node(Node::new(3));
+    /// assert_eq!(tree.validate_tree(), Err(TreeError::MultipleRoots));
+    /// assert!(!tree.is_tree());
+    /// ```
+    pub fn validate_tree(&self) -> Result<(), TreeError> {
+        if self.nodes.is_empty() {
+            return Ok(());
+        }
 
-    fn preorder_recursive<'a>(&'a self, node_id: FloatId, result: &mut Vec<&'a Node<T>>) {
-        if let Some(node) = self.nodes.get(&node_id) {
-            result.push(node);
-            for child_id in node.children() {
-                self.preorder_recursive(FloatId::from(child_id), result);
+        let mut root = None;
+        let mut listed_by: HashMap<FloatId, FloatId> = HashMap::new();
+        for (&id, node) in &self.nodes {
+            if node.parent.is_none() {
+                if root.is_some() {
+                    return Err(TreeError::MultipleRoots);
+                }
+                root = Some(id);
             }
+            for &child_id in &node.children {
+                if !self.nodes.contains_key(&child_id) {
+                    return Err(TreeError::InconsistentLinks);
+                }
+                if listed_by.insert(child_id, id).is_some() {
+                    return Err(TreeError::MultipleParents);
+                }
+            }
+        }
+        let root = root.ok_or(TreeError::MissingRoot)?;
+
+        for (id, node) in &self.nodes {
+            if node.parent != listed_by.get(id).copied() {
+                return Err(TreeError::InconsistentLinks);
+            }
+        }
+        if self.root_id != Some(root) {
+            return Err(TreeError::RootMismatch);
         }
+
+        // With one root and one consistent parent per node, any node that
+        // cannot be reached from the root must sit on a cycle
+        if self.num_nodes(root.value()) != self.nodes.len() {
+            return Err(TreeError::Cycle);
+        }
+        Ok(())
     }
 
-    /// Perform postorder traversal
-    ///
-    /// Traverses the subtree in postorder: left subtree, right subtree, root.
-    /// Returns a vector of nodes in traversal order.
+    /// Check if the nodes form a single well-formed tree
     ///
-    /// # Examples
+    /// Shorthand for [`Tree::validate_tree`]`().is_ok()`.
+    pub fn is_tree(&self) -> bool {
+        self.validate_tree().is_ok()
+    }
+
+    /// Set every node's `parent` from the `children` sets
     ///
-    /// ```
-    /// use jangal::{Tree, Node};
+    /// Repairs trees built with [`Node::add_child`] but without the matching
+    /// [`Node::set_parent`] calls: each node listed as a child gets that node
+    /// as its parent, and every node no one lists as a child loses its
+    /// parent. Children that refer to missing nodes are ignored. The root,
+    /// `children` sets and `left`/`right` links are left untouched.
     ///
-    /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
+    /// # Errors
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
+    /// Returns [`TreeError::MultipleParents`] if a node is listed as a child
+    /// by more than one node, in which case no links are changed.
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
+    /// # Examples
     ///
-    /// tree.set_root(root_id);
+    /// ```
+    /// use jangal::{Node, Tree};
     ///
-    /// let postorder_result = tree.postorder(root_id);
-    /// assert_eq!(postorder_result.len(), 3);
+    /// let mut tree = Tree::new();
+    /// let root = tree.add_node(Node::new("root")).unwrap();
+    /// let child = tree.add_node(Node::new("child")).unwrap();
+    /// tree.get_node_mut(root).unwrap().add_child(child);
+    /// assert!(!tree.is_tree());
+    ///
+    /// tree.rebuild_parent_links().unwrap();
+    /// assert_eq!(tree[child].parent(), Some(root));
+    /// assert!(tree.is_tree());
     /// ```
-    pub fn postorder(&self, node_id: Number) -> Vec<&Node<T>> {
-        let mut result = Vec::new();
-        self.postorder_recursive(FloatId::from(node_id), &mut result);
-        result
-    }
-
-    fn postorder_recursive<'a>(&'a self, node_id: FloatId, result: &mut Vec<&'a Node<T>>) {
-        if let Some(node) = self.nodes.get(&node_id) {
-            for child_id in node.children() {
-                self.postorder_recursive(FloatId::from(child_id), result);
+    pub fn rebuild_parent_links(&mut self) -> Result<(), TreeError> {
+        let mut parents: HashMap<FloatId, FloatId> = HashMap::new();
+        for (&id, node) in &self.nodes {
+            for &child_id in &node.children {
+                if !self.nodes.contains_key(&child_id) {
+                    continue;
+                }
+                if parents.insert(child_id, id).is_some() {
+                    return Err(TreeError::MultipleParents);
+                }
             }
         }
-        if let Some(node) = self.nodes.get(&node_id) {
-            result.push(node);
+
+        for (id, node) in &mut self.nodes {
+            node.parent = parents.get(id).copied();
         }
+        Ok(())
     }
 
-    /// Perform inorder traversal
+    /// Rebuild every node's `children` set from the `parent` links
     ///
-    /// Traverses the subtree in inorder: left subtree, root, right subtree.
-    /// Returns a vector of nodes in traversal order.
+    /// The inverse of [`Tree::rebuild_parent_links`]: all `children` sets
+    /// are cleared, then each node is added as a child of its `parent`.
+    /// Parents that refer to missing nodes are left in place but contribute
+    /// no child. The root and `left`/`right` links are left untouched.
     ///
     /// # Examples
     ///
     /// ```
-    /// use jangal::{Tree, Node};
+    /// use jangal::{Node, Tree};
     ///
     /// let mut tree = Tree::new();
-    /// let root = Node::new("root");
-    /// let child1 = Node::new("child1");
-    /// let child2 = Node::new("child2");
+    /// let root = tree.add_node(Node::new("root")).unwrap();
+    /// let child = tree.add_node(Node::new("child")).unwrap();
+    /// tree.get_node_mut(child).unwrap().set_parent(root);
+    /// assert!(tree[root].is_leaf());
+    ///
+    /// tree.rebuild_children_links();
+    /// assert_eq!(tree[root].children(), vec![child]);
+    /// assert!(tree.is_tree());
+    /// ```
+    pub fn rebuild_children_links(&mut self) {
+        let links: Vec<(FloatId, FloatId)> = self
+            .nodes
+            .iter()
+            .filter_map(|(&id, node)| Some((node.parent?, id)))
+            .collect();
+
+        for node in self.nodes.values_mut() {
+            node.children.clear();
+        }
+        for (parent_id, child_id) in links {
+            if let Some(parent) = self.nodes.get_mut(&parent_id) {
+                parent.children.insert(child_id);
+            }
+        }
+    }
+
+    /// Traverse every tree of the forest held in this `Tree`
     ///
-    /// let root_id = tree.add_node(root).unwrap();
-    /// let child1_id = tree.add_node(child1).unwrap();
-    /// let child2_id = tree.add_node(child2).unwrap();
+    /// Runs the traversal given by `order` from each of [`Tree::roots`], in
+    /// ascending root ID order, and returns one traversal per root. Unlike a
+    /// traversal from [`Tree::root_id`], this never silently skips nodes that
+    /// are disconnected from the designated root.
     ///
-    /// // Set up relationships
-    /// if let Some(root_node) = tree.get_node_mut(root_id) {
-    ///     root_node.add_child(child1_id);
-    ///     root_node.add_child(child2_id);
-    /// }
-    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
-    ///     child1_node.set_parent(root_id);
-    /// }
-    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
-    ///     child2_node.set_parent(root_id);
-    /// }
+    /// # Examples
     ///
-    /// tree.set_root(root_id);
+    /// ```
+    /// use jangal::{Tree, Node, TraversalOrder};
     ///
-    /// let inorder_result = tree.inorder(root_id);
-    /// assert_eq!(inorder_result.len(), 3);
+    /// let mut tree = Tree::new();
+    /// let a = tree.add_node(Node::with_id("a", 1.0)).unwrap();
+    /// let b = tree.add_node(Node::with_id("b", 2.0)).unwrap();
+    /// let c = tree.add_node(Node::with_id("c", 3.0)).unwrap();
+    /// tree.get_node_mut(b).unwrap().add_child(c);
+    /// tree.get_node_mut(c).unwrap().set_parent(b);
+    ///
+    /// let forest = tree.traverse_forest(TraversalOrder::Bfs);
+    /// assert_eq!(forest.len(), 2);
+    /// assert_eq!(forest[0].len(), 1);
+    /// assert_eq!(forest[1].len(), 2);
     /// ```
-    pub fn inorder(&self, node_id: Number) -> Vec<&Node<T>> {
-        let mut result = Vec::new();
-        self.inorder_recursive(FloatId::from(node_id), &mut result);
-        result
+    pub fn traverse_forest(&self, order: TraversalOrder) -> Vec<Vec<&Node<T>>> {
+        self.roots()
+            .into_iter()
+            .map(|root_id| self.traverse(root_id, order))
+            .collect()
     }
 
-    fn inorder_recursive<'a>(&'a self, node_id: FloatId, result: &mut Vec<&'a Node<T>>) {
-        if let Some(node) = self.nodes.get(&node_id) {
-            for child_id in node.children() {
-                self.inorder_recursive(FloatId::from(child_id), result);
-            }
-            result.push(node);
+    fn traverse(&self, node_id: Number, order: TraversalOrder) -> Vec<&Node<T>> {
+        match order {
+            TraversalOrder::Dfs => self.dfs(node_id),
+            TraversalOrder::Bfs => self.bfs(node_id),
+            TraversalOrder::Preorder => self.preorder(node_id),
+            TraversalOrder::Postorder => self.postorder(node_id),
         }
     }
-}
 
-impl<T> Default for Tree<T> {
-    /// Create a new empty tree using the default implementation
+    /// Collect the values of a subtree in breadth-first order
+    ///
+    /// A shorthand for mapping the nodes from [`Tree::bfs`] to their values.
+    /// [`Tree::values_dfs`], [`Tree::values_preorder`] and
+    /// [`Tree::values_postorder`] do the same for the other traversals, and
+    /// each has a `_cloned` variant returning owned values.
     ///
     /// # Examples
     ///
     /// ```
     /// use jangal::Tree;
     ///
-    /// let tree: Tree<String> = Tree::default();
-    /// assert!(tree.is_empty());
-    /// assert_eq!(tree.size(), 0);
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(tree.values_bfs(0.0), vec![&0, &1, &2]);
+    /// assert_eq!(tree.values_postorder_cloned(0.0), vec![2, 1, 0]);
     /// ```
-    fn default() -> Self {
-        Self::new()
+    pub fn values_bfs(&self, start: Number) -> Vec<&T> {
+        self.traversal_values(start, TraversalOrder::Bfs)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Clone the values of a subtree in breadth-first order
+    pub fn values_bfs_cloned(&self, start: Number) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.values_bfs(start).into_iter().cloned().collect()
+    }
 
-    #[test]
-    fn test_node_core_functionality() {
+    /// Collect the values of a subtree in depth-first order
+    pub fn values_dfs(&self, start: Number) -> Vec<&T> {
+        self.traversal_values(start, TraversalOrder::Dfs)
+    }
+
+    /// Clone the values of a subtree in depth-first order
+    pub fn values_dfs_cloned(&self, start: Number) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.values_dfs(start).into_iter().cloned().collect()
+    }
+
+    /// Collect the values of a subtree in preorder order
+    pub fn values_preorder(&self, start: Number) -> Vec<&T> {
+        self.traversal_values(start, TraversalOrder::Preorder)
+    }
+
+    /// Clone the values of a subtree in preorder order
+    pub fn values_preorder_cloned(&self, start: Number) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.values_preorder(start).into_iter().cloned().collect()
+    }
+
+    /// Collect the values of a subtree in postorder order
+    pub fn values_postorder(&self, start: Number) -> Vec<&T> {
+        self.traversal_values(start, TraversalOrder::Postorder)
+    }
+
+    /// Clone the values of a subtree in postorder order
+    pub fn values_postorder_cloned(&self, start: Number) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.values_postorder(start).into_iter().cloned().collect()
+    }
+
+    fn traversal_values(&self, start: Number, order: TraversalOrder) -> Vec<&T> {
+        self.traverse(start, order)
+            .into_iter()
+            .map(|node| &node.value)
+            .collect()
+    }
+
+    /// Consume the tree and return its values in traversal order
+    ///
+    /// The values come out in the order [`Tree::traverse_forest`] visits
+    /// the nodes with `order`: each tree of the forest in turn, in ascending
+    /// root ID order. Any nodes that no root reaches, such as nodes on a
+    /// cycle of parent links or below the recursion limit, follow in
+    /// ascending ID order, so every value is returned exactly once. The
+    /// values are moved out of the node map, so `T` need not be `Clone`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{TraversalOrder, Tree};
+    ///
+    /// // Implements neither Clone nor Debug
+    /// struct Payload(String);
+    ///
+    /// let mut tree = Tree::new();
+    /// let (root_id, _) = tree.insert_and_get_mut(Payload("root".to_string()));
+    /// let child_id = tree.add_child_value(root_id, Payload("child".to_string())).unwrap();
+    /// tree.add_child_value(child_id, Payload("leaf".to_string()));
+    ///
+    /// let names: Vec<String> = tree
+    ///     .flatten(TraversalOrder::Postorder)
+    ///     .into_iter()
+    ///     .map(|payload| payload.0)
+    ///     .collect();
+    /// assert_eq!(names, vec!["leaf", "child", "root"]);
+    /// ```
+    pub fn flatten(mut self, order: TraversalOrder) -> Vec<T> {
+        let mut ids: Vec<FloatId> = self
+            .traverse_forest(order)
+            .into_iter()
+            .flatten()
+            .map(|node| FloatId::from(node.id))
+            .collect();
+        let visited: HashSet<FloatId> = ids.iter().copied().collect();
+        let mut rest: Vec<FloatId> = self
+            .nodes
+            .keys()
+            .copied()
+            .filter(|id| !visited.contains(id))
+            .collect();
+        rest.sort_unstable();
+        ids.extend(rest);
+
+        ids.into_iter()
+            .filter_map(|id| self.nodes.remove(&id))
+            .map(|node| node.value)
+            .collect()
+    }
+
+    /// Limit how deep the recursive traversals may go
+    ///
+    /// [`Tree::dfs`], [`Tree::preorder`], [`Tree::postorder`] and
+    /// [`Tree::inorder`] recurse once per level, so a very deep tree can
+    /// overflow the stack. With a limit set, they stop descending below
+    /// `max_depth` levels under the start node and return the truncated
+    /// result, while [`Tree::try_traverse`] reports [`TreeError::TooDeep`].
+    /// This is a guard for untrusted input. These four are the only
+    /// recursive `Tree` methods; the rest, such as [`Tree::bfs`],
+    /// [`Tree::postorder_iter`], [`Tree::get_leaves`], [`Tree::depth`] and
+    /// structural equality, walk the tree with an explicit stack or queue
+    /// and are unaffected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, TraversalOrder, TreeError};
+    ///
+    /// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(1), Some(2)]).unwrap();
+    /// tree.set_max_recursion_depth(2);
+    ///
+    /// assert_eq!(tree.preorder(0.0).len(), 3);
+    /// assert_eq!(
+    ///     tree.try_traverse(0.0, TraversalOrder::Preorder).unwrap_err(),
+    ///     TreeError::TooDeep
+    /// );
+    /// assert_eq!(tree.try_traverse(1.0, TraversalOrder::Preorder).unwrap().len(), 3);
+    /// ```
+    pub fn set_max_recursion_depth(&mut self, max_depth: usize) {
+        self.max_recursion_depth = Some(max_depth);
+    }
+
+    /// Remove the recursion limit set by [`Tree::set_max_recursion_depth`]
+    pub fn clear_max_recursion_depth(&mut self) {
+        self.max_recursion_depth = None;
+    }
+
+    /// Get the recursion limit set by [`Tree::set_max_recursion_depth`]
+    pub fn max_recursion_depth(&self) -> Option<usize> {
+        self.max_recursion_depth
+    }
+
+    fn within_recursion_limit(&self, depth: usize) -> bool {
+        self.max_recursion_depth.is_none_or(|max| depth <= max)
+    }
+
+    /// Traverse a subtree, failing instead of truncating at the depth limit
+    ///
+    /// Returns the same nodes as the matching traversal method, or
+    /// [`TreeError::TooDeep`] if the walk would go deeper than the limit set
+    /// by [`Tree::set_max_recursion_depth`].
+    pub fn try_traverse(
+        &self,
+        node_id: Number,
+        order: TraversalOrder,
+    ) -> Result<Vec<&Node<T>>, TreeError> {
+        let start = FloatId::from(node_id);
+        let mut result = Vec::new();
+        let complete = match order {
+            TraversalOrder::Dfs => self.dfs_recursive(start, 0, &mut HashSet::new(), &mut result),
+            TraversalOrder::Bfs => {
+                result = self.bfs(node_id);
+                true
+            }
+            TraversalOrder::Preorder => self.preorder_recursive(start, 0, &mut result),
+            TraversalOrder::Postorder => self.postorder_recursive(start, 0, &mut result),
+        };
+        if complete {
+            Ok(result)
+        } else {
+            Err(TreeError::TooDeep)
+        }
+    }
+
+    /// Swap the positions of two subtrees
+    ///
+    /// Each of `a` and `b` takes the other's place under its parent,
+    /// including the `left`/`right` slot it occupied, and brings its whole
+    /// subtree along. If one of them is the root, the other becomes the root.
+    /// Returns `false` and leaves the tree unchanged if either node is
+    /// missing, if they are the same node, or if one is an ancestor of the
+    /// other, since the swap would then create a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     1            1
+    /// //    / \          / \
+    /// //   2   3   =>   3   2
+    /// //  /                /
+    /// // 4                4
+    /// let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4)]);
+    /// let root = tree.root_id().unwrap();
+    /// let (left, right) = (tree[root].left().unwrap(), tree[root].right().unwrap());
+    ///
+    /// assert!(tree.swap_subtrees(left, right));
+    /// assert_eq!(tree.to_level_order(root), vec![Some(1), Some(3), Some(2), None, None, Some(4)]);
+    /// assert!(!tree.swap_subtrees(root, left));
+    /// ```
+    pub fn swap_subtrees(&mut self, a: Number, b: Number) -> bool {
+        let (a, b) = (FloatId::from(a), FloatId::from(b));
+        if a == b
+            || !self.nodes.contains_key(&a)
+            || !self.nodes.contains_key(&b)
+            || self.is_ancestor(a.value(), b.value())
+            || self.is_ancestor(b.value(), a.value())
+        {
+            return false;
+        }
+
+        let parent_a = self.nodes[&a].parent;
+        let parent_b = self.nodes[&b].parent;
+        // Some(true) for a `left` child, Some(false) for a `right` child
+        let slot = |parent: Option<FloatId>, id: FloatId| {
+            let parent = self.nodes.get(&parent?)?;
+            if parent.left == Some(id) {
+                Some(true)
+            } else if parent.right == Some(id) {
+                Some(false)
+            } else {
+                None
+            }
+        };
+        let slot_a = slot(parent_a, a);
+        let slot_b = slot(parent_b, b);
+
+        for (parent, old) in [(parent_a, a), (parent_b, b)] {
+            if let Some(parent) = parent.and_then(|id| self.nodes.get_mut(&id)) {
+                parent.children.remove(&old);
+            }
+        }
+        for (parent, slot, new) in [(parent_a, slot_a, b), (parent_b, slot_b, a)] {
+            if let Some(parent) = parent.and_then(|id| self.nodes.get_mut(&id)) {
+                parent.children.insert(new);
+                match slot {
+                    Some(true) => parent.left = Some(new),
+                    Some(false) => parent.right = Some(new),
+                    None => {}
+                }
+            }
+        }
+        if let Some(node) = self.nodes.get_mut(&a) {
+            node.parent = parent_b;
+        }
+        if let Some(node) = self.nodes.get_mut(&b) {
+            node.parent = parent_a;
+        }
+
+        if self.root_id == Some(a) {
+            self.root_id = Some(b);
+        } else if self.root_id == Some(b) {
+            self.root_id = Some(a);
+        }
+        true
+    }
+
+    /// Keep only the subtree rooted at the given node
+    ///
+    /// Every node outside the subtree is removed from the tree, and `node_id`
+    /// becomes the new root with its parent cleared. If no such node exists,
+    /// the tree is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4)]);
+    /// let left_id = tree.root().unwrap().left().unwrap();
+    ///
+    /// tree.keep_only_subtree(left_id);
+    /// assert_eq!(tree.size(), 2);
+    /// assert_eq!(tree.root_id(), Some(left_id));
+    /// assert!(tree.root().unwrap().is_root());
+    /// ```
+    pub fn keep_only_subtree(&mut self, node_id: Number) {
+        let root = FloatId::from(node_id);
+        if !self.nodes.contains_key(&root) {
+            return;
+        }
+
+        let mut keep = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(current_id) = stack.pop() {
+            if !keep.insert(current_id) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get(&current_id) {
+                stack.extend(
+                    node.children_iter()
+                        .map(FloatId::from)
+                        .filter(|id| self.nodes.contains_key(id)),
+                );
+            }
+        }
+
+        self.nodes.retain(|id, _| keep.contains(id));
+        self.meta.retain(|id, _| keep.contains(id));
+        self.last_access.retain(|id, _| keep.contains(id));
+        self.access_order.retain(|(_, id)| keep.contains(id));
+        if let Some(node) = self.nodes.get_mut(&root) {
+            node.remove_parent();
+        }
+        self.root_id = Some(root);
+    }
+
+    /// Merge the children of a node that hold equal values
+    ///
+    /// Children are grouped by value; in each group the child with the
+    /// smallest ID survives, and every other child is removed after
+    /// `combine(&mut survivor_value, removed_value)` has folded its value
+    /// into the survivor's, in ascending ID order. The removed children's
+    /// own children are moved under the survivor and kept as they are, even
+    /// if some of them now hold equal values; call this again on the
+    /// survivor to merge those too. `left`/`right` links to or from a
+    /// removed child are cleared. Returns the number of children removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, Tree};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = tree.add_node(Node::with_id("root".to_string(), 0.0)).unwrap();
+    /// for (id, value) in [(1.0, "a"), (2.0, "b"), (3.0, "a")] {
+    ///     tree.add_node(Node::with_id(value.to_string(), id));
+    ///     tree.get_node_mut(root).unwrap().add_child(id);
+    ///     tree.get_node_mut(id).unwrap().set_parent(root);
+    /// }
+    /// tree.add_node(Node::with_id("c".to_string(), 4.0));
+    /// tree.get_node_mut(3.0).unwrap().add_child(4.0);
+    /// tree.get_node_mut(4.0).unwrap().set_parent(3.0);
+    ///
+    /// let merged = tree.merge_duplicate_children(root, |survivor, removed| {
+    ///     survivor.push_str(&removed);
+    /// });
+    /// assert_eq!(merged, 1);
+    /// assert_eq!(tree[1.0].value, "aa");
+    /// assert!(!tree.contains_id(3.0));
+    /// assert_eq!(tree[4.0].parent(), Some(1.0));
+    /// assert!(tree.is_tree());
+    /// ```
+    pub fn merge_duplicate_children<F>(&mut self, node_id: Number, mut combine: F) -> usize
+    where
+        T: PartialEq,
+        F: FnMut(&mut T, T),
+    {
+        let parent_id = FloatId::from(node_id);
+        let Some(parent) = self.nodes.get(&parent_id) else {
+            return 0;
+        };
+        let mut children: Vec<FloatId> = parent
+            .children
+            .iter()
+            .copied()
+            .filter(|child_id| self.nodes.contains_key(child_id))
+            .collect();
+        children.sort_unstable();
+
+        // Pair each duplicate with the first earlier child holding its value
+        let mut survivors: Vec<FloatId> = Vec::new();
+        let mut duplicates: Vec<(FloatId, FloatId)> = Vec::new();
+        for child_id in children {
+            let value = &self.nodes[&child_id].value;
+            match survivors
+                .iter()
+                .find(|survivor| self.nodes[*survivor].value == *value)
+            {
+                Some(&survivor) => duplicates.push((child_id, survivor)),
+                None => survivors.push(child_id),
+            }
+        }
+
+        let merged = duplicates.len();
+        for (duplicate_id, survivor_id) in duplicates {
+            let Some(duplicate) = self.remove_node(duplicate_id.value()) else {
+                continue;
+            };
+            if let Some(parent) = self.nodes.get_mut(&parent_id) {
+                parent.children.remove(&duplicate_id);
+                if parent.left == Some(duplicate_id) {
+                    parent.left = None;
+                }
+                if parent.right == Some(duplicate_id) {
+                    parent.right = None;
+                }
+            }
+            for &grandchild_id in &duplicate.children {
+                if let Some(grandchild) = self.nodes.get_mut(&grandchild_id) {
+                    grandchild.parent = Some(survivor_id);
+                }
+            }
+            if let Some(survivor) = self.nodes.get_mut(&survivor_id) {
+                survivor.children.extend(&duplicate.children);
+                combine(&mut survivor.value, duplicate.value);
+            }
+        }
+        merged
+    }
+
+    /// Get the number of nodes in the tree
+    ///
+    /// Returns the total number of nodes currently in the tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// assert_eq!(tree.size(), 0);
+    ///
+    /// let node1 = Node::new("first");
+    /// let node2 = Node::new("second");
+    /// tree.add_node(node1);
+    /// tree.add_node(node2);
+    ///
+    /// assert_eq!(tree.size(), 2);
+    /// ```
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if the tree is empty
+    ///
+    /// Returns `true` if the tree contains no nodes, `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// assert!(tree.is_empty());
+    ///
+    /// let node = Node::new("test");
+    /// tree.add_node(node);
+    /// assert!(!tree.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Search for a node by its value
+    ///
+    /// Returns the ID of the first node found with the given value, or None if not found.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to search for
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let mut tree = Tree::new();
+    /// let node = tree.add_node(jangal::Node::new(42));
+    /// tree.set_root(node.unwrap());
+    ///
+    /// let found_id = tree.search_by_value(&42);
+    /// assert!(found_id.is_some());
+    /// ```
+    pub fn search_by_value(&self, value: &T) -> Option<Number>
+    where
+        T: PartialEq,
+    {
+        for (id, node) in &self.nodes {
+            if node.value == *value {
+                return Some(id.value());
+            }
+        }
+        None
+    }
+
+    /// Check if the subtree rooted at a node contains a value
+    ///
+    /// Unlike [`Tree::search_by_value`], only `node_id` and its descendants
+    /// are searched, and the search stops at the first match. Returns
+    /// `false` if no such node exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    ///
+    /// assert!(tree.subtree_contains(1.0, &3));
+    /// assert!(!tree.subtree_contains(2.0, &3));
+    /// assert!(!tree.subtree_contains(99.0, &3));
+    /// ```
+    pub fn subtree_contains(&self, node_id: Number, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let mut stack = vec![FloatId::from(node_id)];
+        while let Some(current_id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                if node.value == *value {
+                    return true;
+                }
+                stack.extend(node.children.iter().copied());
+            }
+        }
+        false
+    }
+
+    /// Calculate the height of a node
+    ///
+    /// The height of a node is the length of the longest path from the node
+    /// to a leaf. A leaf node has height 0, and so does a missing node, so
+    /// use [`Tree::try_root`] to tell an empty tree from a single leaf.
+    /// Computed iteratively, so it is safe on deep trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child = Node::new("child");
+    /// let grandchild = Node::new("grandchild");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child_id = tree.add_node(child).unwrap();
+    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child_id);
+    /// }
+    /// if let Some(child_node) = tree.get_node_mut(child_id) {
+    ///     child_node.set_parent(root_id);
+    ///     child_node.add_child(grandchild_id);
+    /// }
+    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
+    ///     grandchild_node.set_parent(child_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// assert_eq!(tree.height(root_id), 2);
+    /// assert_eq!(tree.height(child_id), 1);
+    /// assert_eq!(tree.height(grandchild_id), 0);
+    /// ```
+    pub fn height(&self, node_id: Number) -> usize {
+        self.deepest_node(node_id).map_or(0, |(_, depth)| depth)
+    }
+
+    /// Calculate the height of a node, or `None` if it does not exist
+    ///
+    /// Like [`Tree::height`], but a missing `node_id` yields `None` instead
+    /// of being reported as a leaf. The subtree is walked with an explicit
+    /// stack, so arbitrarily deep trees are fine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(tree.checked_height(0.0), Some(2));
+    /// assert_eq!(tree.checked_height(2.0), Some(0));
+    /// assert_eq!(tree.checked_height(99.0), None);
+    /// assert_eq!(tree.height(99.0), 0);
+    /// ```
+    pub fn checked_height(&self, node_id: Number) -> Option<usize> {
+        self.deepest_node(node_id).map(|(_, depth)| depth)
+    }
+
+    /// Count the levels of a subtree
+    ///
+    /// The number of nodes on the longest downward path from `start`, which
+    /// is [`Tree::height`] (an edge count) plus one. Unlike `height`, this
+    /// tells a missing `start` apart from a single node: a missing `start`
+    /// has 0 levels and a leaf has 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(tree.num_levels(0.0), 3);
+    /// assert_eq!(tree.num_levels(2.0), 1);
+    /// assert_eq!(tree.num_levels(99.0), 0);
+    /// assert_eq!(tree.height(99.0), tree.height(2.0));
+    /// ```
+    pub fn num_levels(&self, start: Number) -> usize {
+        self.deepest_node(start).map_or(0, |(_, depth)| depth + 1)
+    }
+
+    /// Find the deepest node in a subtree
+    ///
+    /// Returns the ID of a node at maximum depth below `start` together with
+    /// that depth, which equals [`Tree::height`] of `start`. If several nodes
+    /// are tied, any one of them may be returned. A leaf `start` yields
+    /// `(start, 0)` and a missing `start` yields `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(2)]).unwrap();
+    ///
+    /// assert_eq!(tree.deepest_node(0.0), Some((3.0, 2)));
+    /// assert_eq!(tree.deepest_node(1.0), Some((1.0, 0)));
+    /// assert_eq!(tree.deepest_node(99.0), None);
+    /// ```
+    pub fn deepest_node(&self, start: Number) -> Option<(Number, usize)> {
+        let mut deepest: Option<(FloatId, usize)> = None;
+        let mut stack = vec![(FloatId::from(start), 0)];
+
+        while let Some((current_id, depth)) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                if deepest.is_none_or(|(_, max_depth)| depth > max_depth) {
+                    deepest = Some((current_id, depth));
+                }
+                for child_id in node.children_iter() {
+                    stack.push((FloatId::from(child_id), depth + 1));
+                }
+            }
+        }
+
+        deepest.map(|(id, depth)| (id.value(), depth))
+    }
+
+    /// Calculate the depth of a node
+    ///
+    /// The depth of a node is the length of the path from the root to the node.
+    /// The root node has depth 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child = Node::new("child");
+    /// let grandchild = Node::new("grandchild");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child_id = tree.add_node(child).unwrap();
+    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child_id);
+    /// }
+    /// if let Some(child_node) = tree.get_node_mut(child_id) {
+    ///     child_node.set_parent(root_id);
+    ///     child_node.add_child(grandchild_id);
+    /// }
+    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
+    ///     grandchild_node.set_parent(child_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// assert_eq!(tree.depth(root_id), 0);
+    /// assert_eq!(tree.depth(child_id), 1);
+    /// assert_eq!(tree.depth(grandchild_id), 2);
+    /// ```
+    pub fn depth(&self, node_id: Number) -> usize {
+        let mut visited = HashSet::new();
+        let mut current_id = FloatId::from(node_id);
+        let mut depth = 0;
+
+        // Stop at the root, a missing node, or a node seen before on a cycle
+        while visited.insert(current_id) {
+            let Some(parent_id) = self.nodes.get(&current_id).and_then(|node| node.parent) else {
+                break;
+            };
+            current_id = parent_id;
+            depth += 1;
+        }
+
+        depth
+    }
+
+    /// Check if one node is a proper ancestor of another
+    ///
+    /// Walks `descendant`'s parent chain looking for `ancestor`. A node is
+    /// not its own ancestor, so equal IDs return `false`, as do missing
+    /// nodes. Cycles in the parent links end the walk instead of looping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(1), Some(0)]).unwrap();
+    ///
+    /// assert!(tree.is_ancestor(0.0, 2.0));
+    /// assert!(tree.is_ancestor(1.0, 2.0));
+    /// assert!(!tree.is_ancestor(3.0, 2.0));
+    /// assert!(!tree.is_ancestor(2.0, 0.0));
+    /// assert!(!tree.is_ancestor(1.0, 1.0));
+    /// ```
+    pub fn is_ancestor(&self, ancestor: Number, descendant: Number) -> bool {
+        let ancestor = FloatId::from(ancestor);
+        let mut current = FloatId::from(descendant);
+        let mut visited = HashSet::new();
+
+        while visited.insert(current) {
+            match self.nodes.get(&current).and_then(|node| node.parent) {
+                Some(parent_id) if parent_id == ancestor => return true,
+                Some(parent_id) => current = parent_id,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Collect a node followed by its ancestors, nearest first
+    ///
+    /// Stops at the root, at a missing parent, or when the parent links
+    /// loop back on themselves. A missing node yields an empty chain.
+    fn ancestor_chain(&self, id: FloatId) -> Vec<FloatId> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = Some(id);
+        while let Some(current_id) = current {
+            if !self.nodes.contains_key(&current_id) || !visited.insert(current_id) {
+                break;
+            }
+            chain.push(current_id);
+            current = self.nodes[&current_id].parent;
+        }
+        chain
+    }
+
+    /// Find the lowest common ancestor of two nodes
+    ///
+    /// Returns the deepest node that has both `a` and `b` in its subtree,
+    /// where a node counts as part of its own subtree. Returns `None` if
+    /// either node is missing or they are in different trees of a forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //  / \
+    /// // 3   4
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(tree.lowest_common_ancestor(3.0, 4.0), Some(1.0));
+    /// assert_eq!(tree.lowest_common_ancestor(3.0, 2.0), Some(0.0));
+    /// assert_eq!(tree.lowest_common_ancestor(1.0, 3.0), Some(1.0));
+    /// ```
+    pub fn lowest_common_ancestor(&self, a: Number, b: Number) -> Option<Number> {
+        let ancestors_a: HashSet<FloatId> =
+            self.ancestor_chain(FloatId::from(a)).into_iter().collect();
+        self.ancestor_chain(FloatId::from(b))
+            .into_iter()
+            .find(|id| ancestors_a.contains(id))
+            .map(|id| id.value())
+    }
+
+    /// Find the path between two nodes
+    ///
+    /// Returns the IDs from `from` up to the lowest common ancestor and back
+    /// down to `to`, inclusive of both ends, or `None` if no path exists.
+    /// The path from a node to itself is just that node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(tree.path_between(3.0, 2.0), Some(vec![3.0, 1.0, 0.0, 2.0]));
+    /// assert_eq!(tree.path_between(0.0, 4.0), Some(vec![0.0, 1.0, 4.0]));
+    /// assert_eq!(tree.path_between(3.0, 99.0), None);
+    /// ```
+    pub fn path_between(&self, from: Number, to: Number) -> Option<Vec<Number>> {
+        let lca = FloatId::from(self.lowest_common_ancestor(from, to)?);
+
+        let mut path: Vec<Number> = self
+            .ancestor_chain(FloatId::from(from))
+            .into_iter()
+            .take_while(|&id| id != lca)
+            .map(|id| id.value())
+            .collect();
+        path.push(lca.value());
+
+        let down: Vec<Number> = self
+            .ancestor_chain(FloatId::from(to))
+            .into_iter()
+            .take_while(|&id| id != lca)
+            .map(|id| id.value())
+            .collect();
+        path.extend(down.into_iter().rev());
+        Some(path)
+    }
+
+    /// Count the nodes on the path between two nodes that hold a value
+    ///
+    /// Both endpoints are included in the path, as returned by
+    /// [`Tree::path_between`]. Returns `None` if no path exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //      "a"
+    /// //      / \
+    /// //   "b"   "a"
+    /// //    |
+    /// //   "a"
+    /// let mut tree = Tree::from_level_order(vec![Some("a"), Some("b"), Some("a"), Some("a")]);
+    /// let root = tree.root_id().unwrap();
+    /// let left = tree[root].left().unwrap();
+    /// let (leaf, right) = (tree[left].left().unwrap(), tree[root].right().unwrap());
+    ///
+    /// assert_eq!(tree.count_on_path(leaf, right, &"a"), Some(3));
+    /// assert_eq!(tree.count_on_path(leaf, root, &"b"), Some(1));
+    /// assert_eq!(tree.count_on_path(leaf, 99.0, &"a"), None);
+    /// ```
+    pub fn count_on_path(&self, from: Number, to: Number, value: &T) -> Option<usize>
+    where
+        T: PartialEq,
+    {
+        let path = self.path_between(from, to)?;
+        Some(
+            path.into_iter()
+                .filter(|&id| self.nodes[&FloatId::from(id)].value == *value)
+                .count(),
+        )
+    }
+
+    /// Find every node exactly `k` edges away from `source`
+    ///
+    /// The tree is treated as undirected: a breadth-first search steps from
+    /// each node to its parent and to its children, so the result includes
+    /// descendants, ancestors and nodes in other branches. IDs are returned
+    /// in ascending order. `k = 0` yields just `source`, and a missing
+    /// `source` yields nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //       0
+    /// //      / \
+    /// //     1   2
+    /// //    / \   \
+    /// //   3   4   5
+    /// let tree =
+    ///     Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1), Some(2)]).unwrap();
+    ///
+    /// assert_eq!(tree.nodes_at_distance(3.0, 1), vec![1.0]);
+    /// assert_eq!(tree.nodes_at_distance(3.0, 2), vec![0.0, 4.0]);
+    /// assert_eq!(tree.nodes_at_distance(3.0, 4), vec![5.0]);
+    /// assert!(tree.nodes_at_distance(3.0, 5).is_empty());
+    /// ```
+    pub fn nodes_at_distance(&self, source: Number, k: usize) -> Vec<Number> {
+        let source = FloatId::from(source);
+        if !self.nodes.contains_key(&source) {
+            return Vec::new();
+        }
+
+        let mut visited = HashSet::from([source]);
+        let mut frontier = vec![source];
+        for _ in 0..k {
+            let mut next = Vec::new();
+            for current_id in frontier {
+                let node = &self.nodes[&current_id];
+                let neighbors = node.parent.iter().chain(&node.children);
+                for &neighbor in neighbors {
+                    if self.nodes.contains_key(&neighbor) && visited.insert(neighbor) {
+                        next.push(neighbor);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return Vec::new();
+            }
+            frontier = next;
+        }
+
+        frontier.sort_unstable();
+        frontier.into_iter().map(|id| id.value()).collect()
+    }
+
+    /// Calculate the distance between every pair of nodes
+    ///
+    /// The distance is the number of edges on the path between two nodes
+    /// through their lowest common ancestor. Each unordered pair is stored
+    /// once, keyed with the smaller ID first; a node's distance to itself is
+    /// not stored, nor are pairs in different trees of a forest.
+    ///
+    /// This compares every pair against each node's ancestor chain, so it
+    /// takes O(n² · h) time and O(n²) memory for `n` nodes of height `h`.
+    /// It is meant for small trees of up to a few hundred nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{FloatId, Tree};
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    ///
+    /// let distances = tree.all_pairs_distances();
+    /// assert_eq!(distances.len(), 6);
+    /// assert_eq!(distances[&(FloatId::from(2.0), FloatId::from(3.0))], 3);
+    /// assert_eq!(distances[&(FloatId::from(0.0), FloatId::from(3.0))], 2);
+    /// assert!(!distances.contains_key(&(FloatId::from(3.0), FloatId::from(2.0))));
+    /// ```
+    pub fn all_pairs_distances(&self) -> HashMap<(FloatId, FloatId), usize> {
+        let mut ids: Vec<FloatId> = self.nodes.keys().copied().collect();
+        ids.sort_unstable();
+
+        // For each node, the distance up to each of its ancestors
+        let chains: Vec<Vec<FloatId>> = ids.iter().map(|&id| self.ancestor_chain(id)).collect();
+        let lookups: Vec<HashMap<FloatId, usize>> = chains
+            .iter()
+            .map(|chain| chain.iter().enumerate().map(|(i, &id)| (id, i)).collect())
+            .collect();
+
+        let mut distances = HashMap::new();
+        for (i, &a) in ids.iter().enumerate() {
+            for (j, &b) in ids.iter().enumerate().skip(i + 1) {
+                let lca = chains[j]
+                    .iter()
+                    .enumerate()
+                    .find_map(|(up_b, id)| lookups[i].get(id).map(|&up_a| up_a + up_b));
+                if let Some(distance) = lca {
+                    distances.insert((a, b), distance);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Calculate the depth of every node reachable from `start` in one pass
+    ///
+    /// Depths are relative to `start`, which has depth 0, and are computed
+    /// with a single breadth-first search instead of one `depth` call per
+    /// node. A missing `start` produces an empty map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{FloatId, Tree};
+    ///
+    /// let tree = Tree::from_level_order(vec![Some("a"), Some("b"), Some("c"), Some("d")]);
+    /// let root_id = tree.root_id().unwrap();
+    ///
+    /// let depths = tree.all_depths(root_id);
+    /// assert_eq!(depths.len(), 4);
+    /// assert_eq!(depths[&FloatId::from(root_id)], 0);
+    /// for (id, depth) in &depths {
+    ///     assert_eq!(*depth, tree.depth(id.value()));
+    /// }
+    /// ```
+    pub fn all_depths(&self, start: Number) -> HashMap<FloatId, usize> {
+        let mut depths = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        let start = FloatId::from(start);
+        if self.nodes.contains_key(&start) {
+            depths.insert(start, 0);
+            queue.push_back(start);
+        }
+
+        while let Some(current_id) = queue.pop_front() {
+            let depth = depths[&current_id];
+            if let Some(node) = self.nodes.get(&current_id) {
+                for child_id in node.children_iter() {
+                    let child_id = FloatId::from(child_id);
+                    if self.nodes.contains_key(&child_id) && !depths.contains_key(&child_id) {
+                        depths.insert(child_id, depth + 1);
+                        queue.push_back(child_id);
+                    }
+                }
+            }
+        }
+
+        depths
+    }
+
+    /// Compute a bottom-up aggregate for every subtree and store it
+    ///
+    /// Each node's aggregate starts as `init` of its own value and is then
+    /// folded with the aggregate of each child, in ascending child ID
+    /// order, using `combine`. With `init = |size| *size` and
+    /// `combine = |total, child| total + child`, every directory in a file
+    /// tree ends up holding the total size below it. The trees under every
+    /// parentless node are covered; nodes cut off from them by a cycle are
+    /// skipped.
+    ///
+    /// Every aggregate is attached to its node as metadata (see
+    /// [`Tree::set_meta`]), replacing what was there, and all of them are
+    /// also returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{FloatId, Tree};
+    ///
+    /// //     1
+    /// //    / \
+    /// //   2   3
+    /// //   |
+    /// //   4
+    /// let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4)]);
+    /// let root = tree.root_id().unwrap();
+    ///
+    /// let sums = tree.annotate_subtree(|value| *value as u64, |total, child| total + child);
+    /// assert_eq!(sums[&FloatId::from(root)], 10);
+    /// assert_eq!(tree.meta::<u64>(root), Some(&10));
+    ///
+    /// let left = tree[root].left().unwrap();
+    /// assert_eq!(tree.meta::<u64>(left), Some(&6));
+    /// ```
+    pub fn annotate_subtree<A, F, G>(&mut self, mut init: F, mut combine: G) -> HashMap<FloatId, A>
+    where
+        A: Clone + Any + Send + Sync,
+        F: FnMut(&T) -> A,
+        G: FnMut(A, &A) -> A,
+    {
+        let mut aggregates: HashMap<FloatId, A> = HashMap::new();
+        let mut expanded = HashSet::new();
+
+        for root in self.roots() {
+            let mut stack = vec![(FloatId::from(root), false)];
+            while let Some((current_id, children_done)) = stack.pop() {
+                let Some(node) = self.nodes.get(&current_id) else {
+                    continue;
+                };
+                let mut children: Vec<FloatId> = node
+                    .children
+                    .iter()
+                    .copied()
+                    .filter(|child_id| self.nodes.contains_key(child_id))
+                    .collect();
+                children.sort_unstable();
+
+                if !children_done {
+                    if !expanded.insert(current_id) {
+                        continue;
+                    }
+                    stack.push((current_id, true));
+                    stack.extend(children.into_iter().map(|child_id| (child_id, false)));
+                    continue;
+                }
+
+                let aggregate = children
+                    .iter()
+                    .filter_map(|child_id| aggregates.get(child_id))
+                    .fold(init(&node.value), &mut combine);
+                aggregates.insert(current_id, aggregate);
+            }
+        }
+
+        for (id, aggregate) in &aggregates {
+            self.meta.insert(*id, Box::new(aggregate.clone()));
+        }
+        aggregates
+    }
+
+    /// Count the number of leaves in the subtree rooted at the given node
+    ///
+    /// A leaf is a node with no children. This method counts all leaf nodes
+    /// in the subtree using an explicit stack, so it is safe on deep trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    /// let grandchild = Node::new("grandchild");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    ///     child1_node.add_child(grandchild_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
+    ///     grandchild_node.set_parent(child1_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// assert_eq!(tree.num_leaves(root_id), 2);
+    /// assert_eq!(tree.num_leaves(child1_id), 1);
+    /// assert_eq!(tree.num_leaves(child2_id), 1);
+    /// ```
+    pub fn num_leaves(&self, node_id: Number) -> usize {
+        let mut count = 0;
+        let mut stack = vec![FloatId::from(node_id)];
+
+        while let Some(current_id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                if node.is_leaf() {
+                    count += 1;
+                }
+                stack.extend(node.children_iter().map(FloatId::from));
+            }
+        }
+
+        count
+    }
+
+    /// Find the leftmost leaf of a subtree
+    ///
+    /// Starting at `start`, repeatedly steps to the first child until a leaf
+    /// is reached. Binary links take precedence: a node's `left` is followed
+    /// if set, then its `right`. Otherwise the child with the smallest ID is
+    /// the first child, matching the order of [`Tree::dfs_sorted`]. A leaf
+    /// `start` is its own leftmost leaf; a missing `start` yields `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //       5
+    /// //      / \
+    /// //     3   8
+    /// //      \
+    /// //       4
+    /// let tree = Tree::from_level_order(vec![Some(5), Some(3), Some(8), None, Some(4)]);
+    /// let root = tree.root_id().unwrap();
+    ///
+    /// assert_eq!(tree[tree.leftmost_leaf(root).unwrap()].value, 4);
+    /// assert_eq!(tree[tree.rightmost_leaf(root).unwrap()].value, 8);
+    /// ```
+    pub fn leftmost_leaf(&self, start: Number) -> Option<Number> {
+        self.extreme_leaf(start, |node| {
+            node.left
+                .or(node.right)
+                .or_else(|| node.children.iter().copied().min())
+        })
+    }
+
+    /// Find the rightmost leaf of a subtree
+    ///
+    /// The mirror image of [`Tree::leftmost_leaf`]: follows `right`, then
+    /// `left`, and otherwise the child with the largest ID.
+    pub fn rightmost_leaf(&self, start: Number) -> Option<Number> {
+        self.extreme_leaf(start, |node| {
+            node.right
+                .or(node.left)
+                .or_else(|| node.children.iter().copied().max())
+        })
+    }
+
+    /// Follow `next_child` from `start` until reaching a node with no child
+    fn extreme_leaf<F>(&self, start: Number, next_child: F) -> Option<Number>
+    where
+        F: Fn(&Node<T>) -> Option<FloatId>,
+    {
+        let mut current = FloatId::from(start);
+        let mut visited = HashSet::new();
+        loop {
+            let node = self.nodes.get(&current)?;
+            match next_child(node) {
+                Some(child_id) if self.nodes.contains_key(&child_id) && visited.insert(current) => {
+                    current = child_id;
+                }
+                _ => return Some(current.value()),
+            }
+        }
+    }
+
+    /// Get the depth of every leaf in a subtree
+    ///
+    /// Depths are relative to `start`, which has depth 0, and are collected
+    /// in a single depth-first pass. The result is sorted in ascending order,
+    /// which makes it convenient for histograms or averages of leaf depth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    ///
+    /// let depths = tree.leaf_depths(0.0);
+    /// assert_eq!(depths, vec![1, 2]);
+    ///
+    /// let mean = depths.iter().sum::<usize>() as f64 / depths.len() as f64;
+    /// assert_eq!(mean, 1.5);
+    /// ```
+    pub fn leaf_depths(&self, start: Number) -> Vec<usize> {
+        let mut depths = Vec::new();
+        let mut stack = vec![(FloatId::from(start), 0)];
+
+        while let Some((current_id, depth)) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                if node.is_leaf() {
+                    depths.push(depth);
+                }
+                stack.extend(node.children.iter().map(|&child_id| (child_id, depth + 1)));
+            }
+        }
+
+        depths.sort_unstable();
+        depths
+    }
+
+    /// List every leaf below `start` with its path and value
+    ///
+    /// Each entry holds the IDs from `start` down to a leaf, inclusive of
+    /// both ends, and a reference to the leaf's value: the shape of an
+    /// `if path then value` rule when the tree is a decision tree. Leaves
+    /// are listed in [`Tree::dfs_sorted`] order. A leaf `start` yields a
+    /// single one-node path, and a missing `start` yields nothing. The walk
+    /// uses an explicit stack, so it is safe on deep trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //  / \
+    /// // 3   4
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(
+    ///     tree.leaf_rules(0.0),
+    ///     vec![
+    ///         (vec![0.0, 1.0, 3.0], &3),
+    ///         (vec![0.0, 1.0, 4.0], &4),
+    ///         (vec![0.0, 2.0], &2),
+    ///     ]
+    /// );
+    /// assert_eq!(tree.leaf_rules(1.0).len(), 2);
+    /// ```
+    pub fn leaf_rules(&self, start: Number) -> Vec<(Vec<Number>, &T)> {
+        let mut rules = Vec::new();
+        let mut path: Vec<Number> = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(FloatId::from(start), 0)];
+
+        while let Some((current_id, depth)) = stack.pop() {
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+            if !visited.insert(current_id) {
+                continue;
+            }
+            path.truncate(depth);
+            path.push(current_id.value());
+            if node.is_leaf() {
+                rules.push((path.clone(), &node.value));
+                continue;
+            }
+            let mut children: Vec<FloatId> = node.children.iter().copied().collect();
+            children.sort_unstable_by(|a, b| b.cmp(a));
+            stack.extend(children.into_iter().map(|child_id| (child_id, depth + 1)));
+        }
+
+        rules
+    }
+
+    /// Count the total number of nodes in the subtree rooted at the given node
+    ///
+    /// This method counts all nodes in the subtree, including the root node
+    /// itself, using an explicit stack so it is safe on deep trees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// assert_eq!(tree.num_nodes(root_id), 3);
+    /// assert_eq!(tree.num_nodes(child1_id), 1);
+    /// assert_eq!(tree.num_nodes(child2_id), 1);
+    /// ```
+    pub fn num_nodes(&self, node_id: Number) -> usize {
+        let mut count = 0;
+        let mut stack = vec![FloatId::from(node_id)];
+
+        while let Some(current_id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                count += 1;
+                stack.extend(node.children_iter().map(FloatId::from));
+            }
+        }
+
+        count
+    }
+
+    /// Count the internal (non-leaf) nodes in the subtree rooted at the given node
+    ///
+    /// Equivalent to `num_nodes(node_id) - num_leaves(node_id)`, computed in a
+    /// single pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4)]);
+    /// let root_id = tree.root_id().unwrap();
+    ///
+    /// assert_eq!(tree.num_internal(root_id), 2);
+    /// assert_eq!(tree.num_internal(999.0), 0);
+    /// ```
+    pub fn num_internal(&self, node_id: Number) -> usize {
+        let mut count = 0;
+        let mut stack = vec![FloatId::from(node_id)];
+
+        while let Some(current_id) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                if !node.is_leaf() {
+                    count += 1;
+                }
+                stack.extend(node.children_iter().map(FloatId::from));
+            }
+        }
+
+        count
+    }
+
+    /// Check if the tree is balanced (all leaf nodes are at most one level apart)
+    ///
+    /// A tree is considered balanced if the heights of all subtrees differ by
+    /// at most 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// // This tree is balanced: both children are at the same level
+    /// assert!(tree.is_balanced(root_id));
+    /// ```
+    pub fn is_balanced(&self, node_id: Number) -> bool {
+        if let Some(node) = self.get_node(node_id) {
+            if node.is_leaf() {
+                return true;
+            }
+
+            let mut heights = Vec::new();
+            for child_id in node.children_iter() {
+                heights.push(self.height(child_id));
+            }
+            heights.sort_by(|a, b| b.cmp(a));
+
+            if let Some(&max_height) = heights.first() {
+                return heights.iter().all(|&h| max_height - h <= 1);
+            }
+        }
+        true
+    }
+
+    /// Find every downward path of exactly `len` edges within a subtree
+    ///
+    /// Paths may start at any node in the subtree rooted at `start` (not only
+    /// at `start` itself) and always follow parent-to-child links. Each path
+    /// is returned as the list of its `len + 1` node IDs, top to bottom.
+    /// Paths are ordered by their starting node in [`Tree::dfs_sorted`]
+    /// order, then by ascending child IDs. A `len` of 0 returns one
+    /// single-node path per node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    ///
+    /// assert_eq!(
+    ///     tree.paths_of_length(0.0, 1),
+    ///     vec![vec![0.0, 1.0], vec![0.0, 2.0], vec![1.0, 3.0]]
+    /// );
+    /// assert_eq!(tree.paths_of_length(0.0, 2), vec![vec![0.0, 1.0, 3.0]]);
+    /// assert!(tree.paths_of_length(0.0, 3).is_empty());
+    /// ```
+    pub fn paths_of_length(&self, start: Number, len: usize) -> Vec<Vec<Number>> {
+        let mut paths = Vec::new();
+
+        for first in self.dfs_sorted(start) {
+            let mut stack = vec![vec![FloatId::from(first.id)]];
+            while let Some(path) = stack.pop() {
+                if path.len() == len + 1 {
+                    paths.push(path.iter().map(|id| id.value()).collect());
+                    continue;
+                }
+                if let Some(node) = self.nodes.get(path.last().unwrap()) {
+                    let mut children: Vec<FloatId> = node
+                        .children
+                        .iter()
+                        .copied()
+                        .filter(|id| self.nodes.contains_key(id))
+                        .collect();
+                    children.sort_unstable_by(|a, b| b.cmp(a));
+                    for child_id in children {
+                        let mut extended = path.clone();
+                        extended.push(child_id);
+                        stack.push(extended);
+                    }
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Calculate the balance factor of a binary node
+    ///
+    /// The balance factor is `height(left) - height(right)`, following the
+    /// binary `left`/`right` pointers. A missing child counts as height -1,
+    /// so a leaf has a balance factor of 0 and a node with only a left leaf
+    /// child has a balance factor of 1. AVL trees keep this within `-1..=1`
+    /// for every node. Returns 0 for a missing node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //     1
+    /// //    /
+    /// //   2
+    /// //  /
+    /// // 3
+    /// let tree = Tree::from_level_order(vec![Some(1), Some(2), None, Some(3)]);
+    /// let root_id = tree.root_id().unwrap();
+    /// let left_id = tree.root().unwrap().left().unwrap();
+    ///
+    /// assert_eq!(tree.balance_factor(root_id), 2);
+    /// assert_eq!(tree.balance_factor(left_id), 1);
+    /// ```
+    pub fn balance_factor(&self, node_id: Number) -> i64 {
+        match self.get_node(node_id) {
+            Some(node) => self.binary_height(node.left) - self.binary_height(node.right),
+            None => 0,
+        }
+    }
+
+    /// Height of a binary subtree following `left`/`right`, -1 if missing
+    fn binary_height(&self, node_id: Option<FloatId>) -> i64 {
+        let mut max_height = -1;
+        let mut stack: Vec<(FloatId, i64)> = node_id.into_iter().map(|id| (id, 0)).collect();
+
+        while let Some((current_id, depth)) = stack.pop() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                max_height = max_height.max(depth);
+                for child_id in [node.left, node.right].into_iter().flatten() {
+                    stack.push((child_id, depth + 1));
+                }
+            }
+        }
+
+        max_height
+    }
+
+    /// Get all leaf values in the subtree
+    ///
+    /// Returns a vector containing references to all leaf nodes
+    /// in the subtree rooted at the given node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    /// let grandchild = Node::new("grandchild");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    ///     child1_node.add_child(grandchild_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
+    ///     grandchild_node.set_parent(child1_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// let leaves = tree.get_leaves(root_id);
+    /// assert_eq!(leaves.len(), 2);
+    /// assert!(leaves.iter().any(|node| node.value == "child2"));
+    /// assert!(leaves.iter().any(|node| node.value == "grandchild"));
+    /// ```
+    pub fn get_leaves(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut leaves = Vec::new();
+        let mut stack = vec![FloatId::from(node_id)];
+
+        while let Some(current_id) = stack.pop() {
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+            if !visited.insert(current_id) {
+                continue;
+            }
+            if node.is_leaf() {
+                leaves.push(node);
+            } else {
+                stack.extend(node.children.iter().copied());
+            }
+        }
+
+        leaves
+    }
+
+    /// Perform depth-first search traversal
+    ///
+    /// Traverses the subtree in depth-first order, visiting nodes as deep as
+    /// possible before backtracking. Returns a vector of nodes in traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    /// let grandchild = Node::new("grandchild");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    ///     child1_node.add_child(grandchild_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
+    ///     grandchild_node.set_parent(child1_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// let dfs_result = tree.dfs(root_id);
+    /// assert_eq!(dfs_result.len(), 4);
+    /// ```
+    pub fn dfs(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        self.dfs_recursive(FloatId::from(node_id), 0, &mut visited, &mut result);
+        result
+    }
+
+    fn dfs_recursive<'a>(
+        &'a self,
+        node_id: FloatId,
+        depth: usize,
+        visited: &mut HashSet<FloatId>,
+        result: &mut Vec<&'a Node<T>>,
+    ) -> bool {
+        if visited.contains(&node_id) {
+            return true;
+        }
+        if !self.within_recursion_limit(depth) {
+            return false;
+        }
+
+        visited.insert(node_id);
+
+        let mut complete = true;
+        if let Some(node) = self.nodes.get(&node_id) {
+            result.push(node);
+            for child_id in node.children_iter() {
+                complete &= self.dfs_recursive(FloatId::from(child_id), depth + 1, visited, result);
+            }
+        }
+        complete
+    }
+
+    /// Perform breadth-first search traversal
+    ///
+    /// Traverses the subtree level by level, visiting all nodes at the current
+    /// level before moving to the next level. Returns a vector of nodes in traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    /// let grandchild = Node::new("grandchild");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    /// let grandchild_id = tree.add_node(grandchild).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    ///     child1_node.add_child(grandchild_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    /// if let Some(grandchild_node) = tree.get_node_mut(grandchild_id) {
+    ///     grandchild_node.set_parent(child1_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// let bfs_result = tree.bfs(root_id);
+    /// assert_eq!(bfs_result.len(), 4);
+    /// ```
+    pub fn bfs(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut result = Vec::new();
+
+        let node_id = FloatId::from(node_id);
+        queue.push_back(node_id);
+        visited.insert(node_id);
+
+        while let Some(current_id) = queue.pop_front() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                result.push(node);
+                for child_id in node.children_iter() {
+                    let child_id = FloatId::from(child_id);
+                    if !visited.contains(&child_id) {
+                        visited.insert(child_id);
+                        queue.push_back(child_id);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Get a node's children ordered by value
+    ///
+    /// Children with equal values keep ascending ID order, so the result is
+    /// reproducible. Child IDs that do not refer to a node in the tree are
+    /// skipped, and a missing node has no children.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, Tree};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root_id = tree.add_node(Node::with_id("root", 0.0)).unwrap();
+    /// for (id, value) in [(1.0, "pear"), (2.0, "apple"), (3.0, "fig"), (4.0, "apple")] {
+    ///     tree.add_node(Node::with_id(value, id));
+    ///     tree.get_node_mut(root_id).unwrap().add_child(id);
+    ///     tree.get_node_mut(id).unwrap().set_parent(root_id);
+    /// }
+    ///
+    /// assert_eq!(tree.children_sorted(root_id), vec![2.0, 4.0, 3.0, 1.0]);
+    /// ```
+    pub fn children_sorted(&self, node_id: Number) -> Vec<Number>
+    where
+        T: Ord,
+    {
+        let mut children = self.children_by_id(node_id);
+        children.sort_by(|a, b| a.value.cmp(&b.value));
+        children.into_iter().map(|node| node.id).collect()
+    }
+
+    /// Get a node's children ordered by a key computed from each value
+    ///
+    /// Like [`Tree::children_sorted`], for values that are not `Ord`
+    /// themselves. `key` is called once per child, and children with equal
+    /// keys keep ascending ID order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, Tree};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root_id = tree.add_node(Node::with_id(0.0, 0.0)).unwrap();
+    /// for (id, weight) in [(1.0, 2.5), (2.0, -1.0), (3.0, 0.5)] {
+    ///     tree.add_node(Node::with_id(weight, id));
+    ///     tree.get_node_mut(root_id).unwrap().add_child(id);
+    ///     tree.get_node_mut(id).unwrap().set_parent(root_id);
+    /// }
+    ///
+    /// let by_weight = tree.children_sorted_by_key(root_id, |w: &f64| (*w * 10.0) as i64);
+    /// assert_eq!(by_weight, vec![2.0, 3.0, 1.0]);
+    /// ```
+    pub fn children_sorted_by_key<K, F>(&self, node_id: Number, mut key: F) -> Vec<Number>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        let mut children: Vec<(K, &Node<T>)> = self
+            .children_by_id(node_id)
+            .into_iter()
+            .map(|node| (key(&node.value), node))
+            .collect();
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+        children.into_iter().map(|(_, node)| node.id).collect()
+    }
+
+    /// The existing children of a node in ascending ID order
+    fn children_by_id(&self, node_id: Number) -> Vec<&Node<T>> {
+        let Some(node) = self.nodes.get(&FloatId::from(node_id)) else {
+            return Vec::new();
+        };
+        let mut children: Vec<FloatId> = node.children.iter().copied().collect();
+        children.sort_unstable();
+        children
+            .into_iter()
+            .filter_map(|child_id| self.nodes.get(&child_id))
+            .collect()
+    }
+
+    /// Perform depth-first search traversal in ascending id order
+    ///
+    /// Like [`Tree::dfs`], but children are visited in ascending id order
+    /// instead of the unspecified order of the underlying set, so the output
+    /// is reproducible across runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root_id = tree.add_node(Node::with_id("root", 1.0)).unwrap();
+    /// for id in [4.0, 2.0, 3.0] {
+    ///     tree.add_node(Node::with_id("child", id));
+    ///     tree.get_node_mut(root_id).unwrap().add_child(id);
+    ///     tree.get_node_mut(id).unwrap().set_parent(root_id);
+    /// }
+    ///
+    /// let ids: Vec<f64> = tree.dfs_sorted(root_id).iter().map(|n| n.id).collect();
+    /// assert_eq!(ids, vec![1.0, 2.0, 3.0, 4.0]);
+    /// ```
+    pub fn dfs_sorted(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        let mut stack = vec![FloatId::from(node_id)];
+
+        while let Some(current_id) = stack.pop() {
+            if !visited.insert(current_id) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get(&current_id) {
+                result.push(node);
+                let mut children: Vec<FloatId> = node.children.iter().copied().collect();
+                children.sort_unstable_by(|a, b| b.cmp(a));
+                stack.extend(children);
+            }
+        }
+
+        result
+    }
+
+    /// Perform breadth-first search traversal in ascending id order
+    ///
+    /// Like [`Tree::bfs`], but the children of each node are enqueued in
+    /// ascending id order instead of the unspecified order of the underlying
+    /// set, so the output is reproducible across runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root_id = tree.add_node(Node::with_id("root", 1.0)).unwrap();
+    /// for id in [4.0, 2.0, 3.0] {
+    ///     tree.add_node(Node::with_id("child", id));
+    ///     tree.get_node_mut(root_id).unwrap().add_child(id);
+    ///     tree.get_node_mut(id).unwrap().set_parent(root_id);
+    /// }
+    ///
+    /// let ids: Vec<f64> = tree.bfs_sorted(root_id).iter().map(|n| n.id).collect();
+    /// assert_eq!(ids, vec![1.0, 2.0, 3.0, 4.0]);
+    /// ```
+    pub fn bfs_sorted(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut result = Vec::new();
+
+        let node_id = FloatId::from(node_id);
+        queue.push_back(node_id);
+        visited.insert(node_id);
+
+        while let Some(current_id) = queue.pop_front() {
+            if let Some(node) = self.nodes.get(&current_id) {
+                result.push(node);
+                let mut children: Vec<FloatId> = node.children.iter().copied().collect();
+                children.sort_unstable();
+                for child_id in children {
+                    if visited.insert(child_id) {
+                        queue.push_back(child_id);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Perform preorder traversal
+    ///
+    /// Traverses the subtree in preorder: root, left subtree, right subtree.
+    /// Returns a vector of nodes in traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// let preorder_result = tree.preorder(root_id);
+    /// assert_eq!(preorder_result.len(), 3);
+    /// ```
+    pub fn preorder(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        self.preorder_recursive(FloatId::from(node_id), 0, &mut result);
+        result
+    }
+
+    fn preorder_recursive<'a>(
+        &'a self,
+        node_id: FloatId,
+        depth: usize,
+        result: &mut Vec<&'a Node<T>>,
+    ) -> bool {
+        if !self.within_recursion_limit(depth) {
+            return false;
+        }
+
+        let mut complete = true;
+        if let Some(node) = self.nodes.get(&node_id) {
+            result.push(node);
+            for child_id in node.children_iter() {
+                complete &= self.preorder_recursive(FloatId::from(child_id), depth + 1, result);
+            }
+        }
+        complete
+    }
+
+    /// Iterate over a subtree in preorder, yielding each node with its depth
+    ///
+    /// Depths are relative to `start`, which has depth 0, and are tracked
+    /// during the traversal rather than recomputed per node. The iterator is
+    /// lazy and uses an explicit stack. A missing `start` yields nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(1)]).unwrap();
+    ///
+    /// let lines: Vec<String> = tree
+    ///     .iter_with_depth(0.0)
+    ///     .map(|(node, depth)| format!("{}{}", "  ".repeat(depth), node.value))
+    ///     .collect();
+    /// assert_eq!(lines, vec!["0", "  1", "    2"]);
+    /// ```
+    pub fn iter_with_depth(&self, start: Number) -> impl Iterator<Item = (&Node<T>, usize)> + '_ {
+        let mut stack = vec![(FloatId::from(start), 0)];
+        std::iter::from_fn(move || {
+            while let Some((current_id, depth)) = stack.pop() {
+                if let Some(node) = self.nodes.get(&current_id) {
+                    stack.extend(node.children.iter().map(|&child_id| (child_id, depth + 1)));
+                    return Some((node, depth));
+                }
+            }
+            None
+        })
+    }
+
+    /// Walk a subtree, driving a [`Visitor`]
+    ///
+    /// Each node is entered before its children and left after them, with
+    /// depths relative to `start`. Children are visited in ascending ID order
+    /// so the walk is deterministic. A missing `start` visits nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Node, Tree, Visitor};
+    ///
+    /// struct Brackets(String);
+    ///
+    /// impl Visitor<usize> for Brackets {
+    ///     fn on_enter(&mut self, node: &Node<usize>, _depth: usize) {
+    ///         self.0.push_str(&format!("({}", node.value));
+    ///     }
+    ///
+    ///     fn on_leave(&mut self, _node: &Node<usize>, _depth: usize) {
+    ///         self.0.push(')');
+    ///     }
+    /// }
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    /// let mut visitor = Brackets(String::new());
+    /// tree.accept(0.0, &mut visitor);
+    /// assert_eq!(visitor.0, "(0(1(3))(2))");
+    /// ```
+    pub fn accept<V: Visitor<T>>(&self, start: Number, visitor: &mut V) {
+        let Some(start_node) = self.nodes.get(&FloatId::from(start)) else {
+            return;
+        };
+
+        let sorted_children = |node: &Node<T>| {
+            let mut children: Vec<FloatId> = node.children.iter().copied().collect();
+            children.sort_unstable_by(|a, b| b.cmp(a));
+            children
+        };
+
+        visitor.on_enter(start_node, 0);
+        let mut stack = vec![(start_node, sorted_children(start_node))];
+        while let Some((node, pending)) = stack.last_mut() {
+            let node = *node;
+            match pending.pop() {
+                Some(child_id) => {
+                    if let Some(child) = self.nodes.get(&child_id) {
+                        visitor.on_enter(child, stack.len());
+                        stack.push((child, sorted_children(child)));
+                    }
+                }
+                None => {
+                    stack.pop();
+                    visitor.on_leave(node, stack.len());
+                }
+            }
+        }
+    }
+
+    /// Perform postorder traversal
+    ///
+    /// Traverses the subtree in postorder: left subtree, right subtree, root.
+    /// Returns a vector of nodes in traversal order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// let postorder_result = tree.postorder(root_id);
+    /// assert_eq!(postorder_result.len(), 3);
+    /// ```
+    pub fn postorder(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        self.postorder_recursive(FloatId::from(node_id), 0, &mut result);
+        result
+    }
+
+    fn postorder_recursive<'a>(
+        &'a self,
+        node_id: FloatId,
+        depth: usize,
+        result: &mut Vec<&'a Node<T>>,
+    ) -> bool {
+        if !self.within_recursion_limit(depth) {
+            return false;
+        }
+
+        let mut complete = true;
+        if let Some(node) = self.nodes.get(&node_id) {
+            for child_id in node.children_iter() {
+                complete &= self.postorder_recursive(FloatId::from(child_id), depth + 1, result);
+            }
+        }
+        if let Some(node) = self.nodes.get(&node_id) {
+            result.push(node);
+        }
+        complete
+    }
+
+    /// Perform postorder traversal without recursion
+    ///
+    /// Produces exactly the same order as [`Tree::postorder`], but keeps the
+    /// pending children of each node on an explicit stack, so it is safe on
+    /// trees far deeper than the call stack allows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let parents: Vec<Option<usize>> = (0..100_000)
+    ///     .map(|i| if i == 0 { None } else { Some(i - 1) })
+    ///     .collect();
+    /// let tree = Tree::from_parent_array(&parents).unwrap();
+    ///
+    /// let order = tree.postorder_iter(0.0);
+    /// assert_eq!(order.len(), 100_000);
+    /// assert_eq!(order.first().unwrap().value, 99_999);
+    /// assert_eq!(order.last().unwrap().value, 0);
+    /// ```
+    pub fn postorder_iter(&self, start: Number) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        let Some(start_node) = self.nodes.get(&FloatId::from(start)) else {
+            return result;
+        };
+
+        let mut stack = vec![(start_node, start_node.children.iter())];
+        while let Some((node, pending)) = stack.last_mut() {
+            let node = *node;
+            match pending.next() {
+                Some(child_id) => {
+                    if let Some(child) = self.nodes.get(child_id) {
+                        stack.push((child, child.children.iter()));
+                    }
+                }
+                None => {
+                    stack.pop();
+                    result.push(node);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Perform inorder traversal
+    ///
+    /// Traverses the subtree in inorder: left subtree, root, right subtree.
+    /// Returns a vector of nodes in traversal order.
+    ///
+    /// This walks the n-ary `children` set, which has no notion of left and
+    /// right. For binary trees built with `left`/`right` links, use
+    /// [`Tree::binary_inorder`] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let root = Node::new("root");
+    /// let child1 = Node::new("child1");
+    /// let child2 = Node::new("child2");
+    ///
+    /// let root_id = tree.add_node(root).unwrap();
+    /// let child1_id = tree.add_node(child1).unwrap();
+    /// let child2_id = tree.add_node(child2).unwrap();
+    ///
+    /// // Set up relationships
+    /// if let Some(root_node) = tree.get_node_mut(root_id) {
+    ///     root_node.add_child(child1_id);
+    ///     root_node.add_child(child2_id);
+    /// }
+    /// if let Some(child1_node) = tree.get_node_mut(child1_id) {
+    ///     child1_node.set_parent(root_id);
+    /// }
+    /// if let Some(child2_node) = tree.get_node_mut(child2_id) {
+    ///     child2_node.set_parent(root_id);
+    /// }
+    ///
+    /// tree.set_root(root_id);
+    ///
+    /// let inorder_result = tree.inorder(root_id);
+    /// assert_eq!(inorder_result.len(), 3);
+    /// ```
+    pub fn inorder(&self, node_id: Number) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        self.inorder_recursive(FloatId::from(node_id), 0, &mut result);
+        result
+    }
+
+    fn inorder_recursive<'a>(
+        &'a self,
+        node_id: FloatId,
+        depth: usize,
+        result: &mut Vec<&'a Node<T>>,
+    ) -> bool {
+        if !self.within_recursion_limit(depth) {
+            return false;
+        }
+
+        let mut complete = true;
+        if let Some(node) = self.nodes.get(&node_id) {
+            for child_id in node.children_iter() {
+                complete &= self.inorder_recursive(FloatId::from(child_id), depth + 1, result);
+            }
+            result.push(node);
+        }
+        complete
+    }
+
+    /// Perform a preorder traversal along `left`/`right` links
+    ///
+    /// Visits each node, then its left subtree, then its right subtree,
+    /// following only the binary `left`/`right` pointers and ignoring the
+    /// n-ary `children` set, so the order is fully determined by the tree.
+    /// [`Tree::binary_inorder`] and [`Tree::binary_postorder`] do the same
+    /// for the other orders. All three use an explicit stack, so
+    /// degenerate, list-like trees are fine, and stop at links that lead to
+    /// missing or already visited nodes. A missing `start` yields nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //       4
+    /// //      / \
+    /// //     2   6
+    /// //    / \   \
+    /// //   1   3   7
+    /// let tree = Tree::from_level_order(vec![
+    ///     Some(4), Some(2), Some(6), Some(1), Some(3), None, Some(7),
+    /// ]);
+    /// let root_id = tree.root_id().unwrap();
+    /// let values = |nodes: Vec<&jangal::Node<i32>>| -> Vec<i32> {
+    ///     nodes.iter().map(|node| node.value).collect()
+    /// };
+    ///
+    /// assert_eq!(values(tree.binary_preorder(root_id)), vec![4, 2, 1, 3, 6, 7]);
+    /// assert_eq!(values(tree.binary_inorder(root_id)), vec![1, 2, 3, 4, 6, 7]);
+    /// assert_eq!(values(tree.binary_postorder(root_id)), vec![1, 3, 2, 7, 6, 4]);
+    /// ```
+    pub fn binary_preorder(&self, start: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        let mut stack = vec![FloatId::from(start)];
+
+        while let Some(current_id) = stack.pop() {
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+            if !visited.insert(current_id) {
+                continue;
+            }
+            result.push(node);
+            stack.extend(node.right);
+            stack.extend(node.left);
+        }
+
+        result
+    }
+
+    /// Perform an inorder traversal along `left`/`right` links
+    ///
+    /// Visits the left subtree, then the node, then the right subtree, so a
+    /// binary search tree comes out in ascending order. See
+    /// [`Tree::binary_preorder`].
+    pub fn binary_inorder(&self, start: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        let mut stack: Vec<&Node<T>> = Vec::new();
+        let mut current = Some(FloatId::from(start));
+
+        loop {
+            // Walk down the left spine, then emit and turn right
+            while let Some(node) = current
+                .filter(|&id| visited.insert(id))
+                .and_then(|id| self.nodes.get(&id))
+            {
+                stack.push(node);
+                current = node.left;
+            }
+            let Some(node) = stack.pop() else {
+                break;
+            };
+            result.push(node);
+            current = node.right;
+        }
+
+        result
+    }
+
+    /// Perform a postorder traversal along `left`/`right` links
+    ///
+    /// Visits the left subtree, then the right subtree, then the node. See
+    /// [`Tree::binary_preorder`].
+    pub fn binary_postorder(&self, start: Number) -> Vec<&Node<T>> {
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        let mut stack = vec![FloatId::from(start)];
+
+        // Node, right, left reversed is left, right, node
+        while let Some(current_id) = stack.pop() {
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+            if !visited.insert(current_id) {
+                continue;
+            }
+            result.push(node);
+            stack.extend(node.left);
+            stack.extend(node.right);
+        }
+
+        result.reverse();
+        result
+    }
+}
+
+impl<T> Default for Tree<T> {
+    /// Create a new empty tree using the default implementation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let tree: Tree<String> = Tree::default();
+    /// assert!(tree.is_empty());
+    /// assert_eq!(tree.size(), 0);
+    /// ```
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<Number> for Tree<T> {
+    type Output = Node<T>;
+
+    /// Get a node by ID
+    ///
+    /// # Panics
+    ///
+    /// Panics if no node with the given ID exists, like indexing a `Vec` out
+    /// of bounds. Use [`Tree::get_node`] for a non-panicking lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{Tree, Node};
+    ///
+    /// let mut tree = Tree::new();
+    /// let id = tree.add_node(Node::new("test")).unwrap();
+    /// assert_eq!(tree[id].value, "test");
+    /// ```
+    ///
+    /// ```should_panic
+    /// use jangal::Tree;
+    ///
+    /// let tree: Tree<i32> = Tree::new();
+    /// let _ = &tree[1.0];
+    /// ```
+    fn index(&self, id: Number) -> &Self::Output {
+        match self.get_node(id) {
+            Some(node) => node,
+            None => panic!("no node with id {} in tree", id),
+        }
+    }
+}
+
+impl<T> Tree<T> {
+    /// Fingerprint the subtree below every node
+    ///
+    /// Each node's fingerprint is a hash of its value together with the
+    /// fingerprints of its children, Merkle-style, so nodes whose subtrees
+    /// hold equal values in the same shape get equal fingerprints no matter
+    /// their IDs. That makes repeated subtrees easy to find, for example to
+    /// share common subexpressions. The `children` set is unordered, so
+    /// reordering siblings does not change a fingerprint, while `left` and
+    /// `right` children are told apart. This is the same hash that
+    /// `Tree`'s [`Hash`] implementation uses for the root.
+    ///
+    /// Every node in the tree gets an entry, including nodes not reachable
+    /// from the root, and each is hashed once. Equal subtrees always have
+    /// equal fingerprints, but as with any 64-bit hash, different subtrees
+    /// may rarely collide, so confirm a match before relying on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{FloatId, Node, Tree};
+    ///
+    /// // (a + b) * (a + b)
+    /// let mut tree = Tree::new();
+    /// let times = tree.add_node(Node::with_id("*", 0.0)).unwrap();
+    /// for (id, value, parent) in [
+    ///     (1.0, "+", 0.0),
+    ///     (2.0, "a", 1.0),
+    ///     (3.0, "b", 1.0),
+    ///     (4.0, "+", 0.0),
+    ///     (5.0, "b", 4.0),
+    ///     (6.0, "a", 4.0),
+    /// ] {
+    ///     tree.add_node(Node::with_id(value, id));
+    ///     tree.get_node_mut(parent).unwrap().add_child(id);
+    ///     tree.get_node_mut(id).unwrap().set_parent(parent);
+    /// }
+    ///
+    /// let prints = tree.subtree_fingerprints();
+    /// let print = |id: f64| prints[&FloatId::from(id)];
+    /// assert_eq!(prints.len(), 7);
+    /// assert_eq!(print(1.0), print(4.0));
+    /// assert_eq!(print(2.0), print(6.0));
+    /// assert_ne!(print(1.0), print(times));
+    /// ```
+    pub fn subtree_fingerprints(&self) -> HashMap<FloatId, u64>
+    where
+        T: Hash,
+    {
+        let mut ids: Vec<FloatId> = self.nodes.keys().copied().collect();
+        ids.sort_unstable();
+
+        // Start from the roots so that each subtree is walked only once
+        let mut hashes = HashMap::new();
+        for start in ids.iter().filter(|&id| self.nodes[id].parent.is_none()) {
+            self.extend_subtree_hashes(*start, &mut hashes);
+        }
+        for start in ids {
+            if !hashes.contains_key(&start) {
+                self.extend_subtree_hashes(start, &mut hashes);
+            }
+        }
+        hashes
+    }
+
+    /// Check whether two trees have the same shape
+    ///
+    /// Node IDs and values are ignored, and children are treated as
+    /// unordered, so two trees are isomorphic if one can be turned into the
+    /// other by reordering siblings. Only the `children` links of the nodes
+    /// reachable from each root are compared; `left`/`right` pointers and
+    /// nodes outside the rooted tree play no part. Two empty trees are
+    /// isomorphic.
+    ///
+    /// Uses the AHU algorithm: every subtree is given a canonical label,
+    /// shared between both trees, from the sorted labels of its children,
+    /// and the trees are isomorphic exactly when their roots get the same
+    /// label. This takes O(n log n) time for `n` nodes overall, and the
+    /// labelling uses an explicit stack, so deep trees are fine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// //   0          0
+    /// //  / \        / \
+    /// // 1   2      1   2
+    /// // |              |
+    /// // 3              3
+    /// let a = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    /// let b = Tree::from_parent_array(&[None, Some(0), Some(0), Some(2)]).unwrap();
+    /// let path = Tree::from_parent_array(&[None, Some(0), Some(1), Some(2)]).unwrap();
+    ///
+    /// assert!(a.is_isomorphic(&b));
+    /// assert!(!a.is_isomorphic(&path));
+    /// ```
+    pub fn is_isomorphic<U>(&self, other: &Tree<U>) -> bool {
+        let mut table = HashMap::new();
+        match (self.root_id, other.root_id) {
+            (Some(root), Some(other_root)) => {
+                self.canonical_label(root, &mut table)
+                    == other.canonical_label(other_root, &mut table)
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// AHU label of the subtree rooted at `start`
+    ///
+    /// `table` assigns a small integer to each distinct sorted list of child
+    /// labels; sharing it between trees makes their labels comparable.
+    fn canonical_label(
+        &self,
+        start: FloatId,
+        table: &mut HashMap<Vec<usize>, usize>,
+    ) -> Option<usize> {
+        let mut labels: HashMap<FloatId, usize> = HashMap::new();
+        let mut expanded = HashSet::new();
+        let mut stack = vec![(start, false)];
+
+        while let Some((current_id, children_done)) = stack.pop() {
+            if labels.contains_key(&current_id) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+
+            if !children_done {
+                // A node already expanded but not yet labelled is on a cycle
+                if !expanded.insert(current_id) {
+                    continue;
+                }
+                stack.push((current_id, true));
+                stack.extend(node.children.iter().map(|&child_id| (child_id, false)));
+                continue;
+            }
+
+            let mut child_labels: Vec<usize> = node
+                .children
+                .iter()
+                .filter_map(|child_id| labels.get(child_id).copied())
+                .collect();
+            child_labels.sort_unstable();
+            let next = table.len();
+            let label = *table.entry(child_labels).or_insert(next);
+            labels.insert(current_id, label);
+        }
+
+        labels.get(&start).copied()
+    }
+
+    /// Structural hash of every node in the subtree rooted at `start`
+    ///
+    /// Each node's hash combines its value, the hashes of its `left`/`right`
+    /// children, and the sorted hashes of its `children` set, so it does not
+    /// depend on node IDs or on the set's iteration order. Computed
+    /// bottom-up with an explicit stack.
+    fn subtree_hashes(&self, start: FloatId) -> HashMap<FloatId, u64>
+    where
+        T: Hash,
+    {
+        let mut hashes = HashMap::new();
+        self.extend_subtree_hashes(start, &mut hashes);
+        hashes
+    }
+
+    /// Add the hashes of the subtree rooted at `start` to `hashes`
+    ///
+    /// Nodes already in `hashes` are reused rather than hashed again.
+    fn extend_subtree_hashes(&self, start: FloatId, hashes: &mut HashMap<FloatId, u64>)
+    where
+        T: Hash,
+    {
+        let mut expanded = HashSet::new();
+        let mut stack = vec![(start, false)];
+
+        while let Some((current_id, children_done)) = stack.pop() {
+            if hashes.contains_key(&current_id) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+
+            if !children_done {
+                // A node already expanded but not yet hashed is on a cycle
+                if !expanded.insert(current_id) {
+                    continue;
+                }
+                stack.push((current_id, true));
+                let links = node.children.iter().chain(&node.left).chain(&node.right);
+                stack.extend(links.map(|&child_id| (child_id, false)));
+                continue;
+            }
+
+            let link_hash = |id: Option<FloatId>| id.and_then(|id| hashes.get(&id).copied());
+            let mut child_hashes: Vec<u64> = node
+                .children
+                .iter()
+                .filter_map(|child_id| hashes.get(child_id).copied())
+                .collect();
+            child_hashes.sort_unstable();
+
+            let mut hasher = DefaultHasher::new();
+            node.value.hash(&mut hasher);
+            link_hash(node.left).hash(&mut hasher);
+            link_hash(node.right).hash(&mut hasher);
+            child_hashes.hash(&mut hasher);
+            hashes.insert(current_id, hasher.finish());
+        }
+    }
+
+    /// Label every node in the subtree rooted at `start` with its equality class
+    ///
+    /// Two nodes get the same class exactly when their subtrees are equal:
+    /// equal values, `left`/`right` children of the same class, and the same
+    /// classes among their `children` in any order. `table` can be shared
+    /// between trees, so that their labels are comparable. Computed
+    /// bottom-up with an explicit stack, in the same order as
+    /// [`Tree::extend_subtree_hashes`]; a link back to a node that is still
+    /// being labelled is treated as missing.
+    fn equality_classes<'a>(
+        &'a self,
+        start: FloatId,
+        table: &mut ClassTable<'a, T>,
+    ) -> HashMap<FloatId, usize>
+    where
+        T: PartialEq,
+    {
+        let mut classes = HashMap::new();
+        let mut expanded = HashSet::new();
+        let mut stack = vec![(start, false)];
+
+        while let Some((current_id, children_done)) = stack.pop() {
+            if classes.contains_key(&current_id) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&current_id) else {
+                continue;
+            };
+
+            if !children_done {
+                // A node already expanded but not yet labelled is on a cycle
+                if !expanded.insert(current_id) {
+                    continue;
+                }
+                stack.push((current_id, true));
+                let links = node.children.iter().chain(&node.left).chain(&node.right);
+                stack.extend(links.map(|&child_id| (child_id, false)));
+                continue;
+            }
+
+            let link_class = |id: Option<FloatId>| id.and_then(|id| classes.get(&id).copied());
+            let mut child_classes: Vec<usize> = node
+                .children
+                .iter()
+                .filter_map(|child_id| classes.get(child_id).copied())
+                .collect();
+            child_classes.sort_unstable();
+
+            let shape = (link_class(node.left), link_class(node.right), child_classes);
+            classes.insert(current_id, table.class_of(&node.value, shape));
+        }
+
+        classes
+    }
+}
+
+/// A node's children by equality class: `left`, `right`, then `children`
+type ClassShape = (Option<usize>, Option<usize>, Vec<usize>);
+
+/// Equality classes of subtrees, numbered in order of discovery
+///
+/// Values only need `PartialEq`, so each shape keeps the values seen with
+/// it and new values are compared against them one by one.
+struct ClassTable<'a, T> {
+    by_shape: HashMap<ClassShape, Vec<(&'a T, usize)>>,
+    len: usize,
+}
+
+impl<'a, T: PartialEq> ClassTable<'a, T> {
+    fn new() -> Self {
+        Self {
+            by_shape: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Get the class of a node with this value and shape, adding it if new
+    fn class_of(&mut self, value: &'a T, shape: ClassShape) -> usize {
+        let classes = self.by_shape.entry(shape).or_default();
+        if let Some(&(_, class)) = classes.iter().find(|(seen, _)| *seen == value) {
+            return class;
+        }
+        let class = self.len;
+        self.len += 1;
+        classes.push((value, class));
+        class
+    }
+}
+
+impl<T: PartialEq> PartialEq for Tree<T> {
+    /// Structural equality
+    ///
+    /// Two trees are equal if the subtrees reachable from their roots have
+    /// equal values in the same shape: the same `left`/`right` children and
+    /// the same `children` (in any order, since children are unordered).
+    /// Node IDs, metadata, and nodes not reachable from the root are ignored.
+    /// The comparison uses an explicit stack, so deep trees are fine, and a
+    /// link back to a node still being compared is treated as missing, as
+    /// in the `Hash` impl.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    ///
+    /// let a = Tree::from_level_order(vec![Some(1), Some(2), Some(3)]);
+    /// let b = Tree::from_level_order(vec![Some(1), Some(2), Some(3)]);
+    /// let c = Tree::from_level_order(vec![Some(1), Some(3), Some(2)]);
+    ///
+    /// assert_ne!(a.root_id(), b.root_id());
+    /// assert_eq!(a, b);
+    /// assert_ne!(a, c);
+    /// ```
+    fn eq(&self, other: &Self) -> bool {
+        match (self.root_id, other.root_id) {
+            (Some(id), Some(other_id)) => {
+                let mut table = ClassTable::new();
+                let classes = self.equality_classes(id, &mut table);
+                let other_classes = other.equality_classes(other_id, &mut table);
+                classes.get(&id) == other_classes.get(&other_id)
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Eq> Eq for Tree<T> {}
+
+impl<T: Hash> Hash for Tree<T> {
+    /// Structural hash, consistent with the structural `PartialEq`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut seen = HashSet::new();
+    /// seen.insert(Tree::from_level_order(vec![Some(1), Some(2)]));
+    /// seen.insert(Tree::from_level_order(vec![Some(1), Some(2)]));
+    /// seen.insert(Tree::from_level_order(vec![Some(1), None, Some(2)]));
+    /// assert_eq!(seen.len(), 2);
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let root_hash = self
+            .root_id
+            .and_then(|id| self.subtree_hashes(id).get(&id).copied());
+        root_hash.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_core_functionality() {
         let node = Node::new(42);
         assert_eq!(node.value, 42);
         assert!(node.is_root());
         assert!(node.is_leaf());
         assert_eq!(node.num_children(), 0);
 
-        // Test with custom ID
-        let node_with_id = Node::with_id("test", 123.0);
-        assert_eq!(node_with_id.value, "test");
-        assert_eq!(node_with_id.id, 123.0);
+        // Test with custom ID
+        let node_with_id = Node::with_id("test", 123.0);
+        assert_eq!(node_with_id.value, "test");
+        assert_eq!(node_with_id.id, 123.0);
+
+        // Test display
+        let display_str = format!("{}", node);
+        assert_eq!(display_str, "Node(value=42)");
+    }
+
+    #[test]
+    fn test_debug_output_is_sorted() {
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0)]).unwrap();
+        tree.get_node_mut(0.0)
+            .unwrap()
+            .add_edge(2.0, None, None, None);
+        tree.get_node_mut(0.0)
+            .unwrap()
+            .add_edge(1.0, None, None, None);
+        tree.set_meta(1.0, "ignored");
+
+        assert_eq!(
+            format!("{:?}", tree),
+            "Tree { root_id: Some(0.0), nodes: [\
+             Node { value: 0, id: 0.0, parent: None, children: [1.0, 2.0], edges: [1.0, 2.0], \
+             incoming: [], outgoing: [], left: None, right: None }, \
+             Node { value: 1, id: 1.0, parent: Some(0.0), children: [], edges: [], \
+             incoming: [], outgoing: [], left: None, right: None }, \
+             Node { value: 2, id: 2.0, parent: Some(0.0), children: [], edges: [], \
+             incoming: [], outgoing: [], left: None, right: None }\
+             ], max_nodes: None, max_children: None, max_recursion_depth: None, .. }"
+        );
+
+        // The same nodes inserted in another order print identically
+        let mut reordered = Tree::new();
+        for id in [2, 0, 1] {
+            let node = tree.get_node(id as Number).unwrap().clone();
+            reordered.add_node_no_root(node);
+        }
+        reordered.set_root(0.0);
+        assert_eq!(format!("{:?}", reordered), format!("{:?}", tree));
+    }
+
+    #[test]
+    fn test_node_relationships() {
+        let mut parent = Node::new("parent");
+        let mut child = Node::new("child");
+
+        parent.add_child(child.id);
+        child.set_parent(parent.id);
+
+        assert_eq!(parent.children(), vec![child.id]);
+        assert_eq!(child.parent(), Some(parent.id));
+        assert!(!parent.is_leaf());
+        assert!(child.is_leaf());
+        assert!(!child.is_root());
+
+        // Test multiple children
+        let child2 = Node::new("child2");
+        parent.add_child(child2.id);
+        assert_eq!(parent.num_children(), 2);
+
+        // Test removing child
+        parent.remove_child(child2.id);
+        assert_eq!(parent.num_children(), 1);
+
+        // Test clearing relationships
+        parent.remove_child(child.id);
+        child.remove_parent();
+        assert!(parent.is_root() && parent.is_leaf());
+        assert!(child.is_root() && child.is_leaf());
+    }
+
+    #[test]
+    fn test_node_self_loops_and_multi_edges() {
+        let mut node = Node::new("A");
+        let other = Node::new("B");
+
+        // Multi-edges collapse into a single edge
+        node.add_edge(other.id, None, None, None);
+        node.add_edge(other.id, None, None, None);
+        assert_eq!(node.edges.len(), 1);
+        assert!(!node.has_self_loop());
+
+        // Checked insertion rejects self-loops and leaves the node untouched
+        assert_eq!(
+            node.add_edge_checked(node.id, None, Some(true), None),
+            Err(TreeError::SelfLoop)
+        );
+        assert!(node.outgoing.is_empty());
+        assert!(node
+            .add_edge_checked(other.id, None, Some(true), None)
+            .is_ok());
+        assert_eq!(node.outgoing.len(), 1);
+
+        // Unchecked insertion accepts them, directed or not
+        node.add_edge(node.id, None, Some(true), None);
+        assert!(node.has_self_loop());
+    }
+
+    #[test]
+    fn test_binary_tree_operations() {
+        let mut root = Node::new(10);
+        let left = Node::new(5);
+        let right = Node::new(15);
+
+        root.set_left(left.id);
+        root.set_right(right.id);
+
+        assert_eq!(root.left(), Some(left.id));
+        assert_eq!(root.right(), Some(right.id));
+        assert!(root.has_left());
+        assert!(root.has_right());
+        assert!(!root.is_binary_leaf());
+
+        root.clear_left();
+        root.clear_right();
+        assert_eq!(root.left(), None);
+        assert_eq!(root.right(), None);
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn test_float_id_functionality() {
+        use std::collections::HashMap;
+
+        let id1 = FloatId::new(1.5);
+        let id2 = FloatId::new(1.5);
+        let id3 = FloatId::new(2.5);
+
+        // Test equality and hashing
+        assert_eq!(id1, id2);
+        assert_ne!(id1, id3);
+
+        let mut map = HashMap::new();
+        map.insert(id1, "first");
+        map.insert(id2, "second");
+        map.insert(id3, "third");
+
+        assert_eq!(map.get(&id1), Some(&"second"));
+        assert_eq!(map.len(), 2);
+
+        // Test NaN handling
+        let nan1 = FloatId::new(f64::NAN);
+        let nan2 = FloatId::new(f64::NAN);
+        let regular = FloatId::new(1.0);
+
+        assert_eq!(nan1, nan2);
+        assert_ne!(nan1, regular);
+
+        // Equal ids hash alike even when their bits differ
+        let mut map = HashMap::new();
+        map.insert(FloatId::new(0.0), "zero");
+        map.insert(FloatId::new(f64::NAN), "nan");
+        assert_eq!(map.get(&FloatId::new(-0.0)), Some(&"zero"));
+        assert_eq!(map.get(&FloatId::new(-f64::NAN)), Some(&"nan"));
+
+        // Test conversion
+        let value = 3.14159;
+        let float_id = FloatId::new(value);
+        assert_eq!(float_id.value(), value);
+
+        let converted_to_f64: f64 = float_id.into();
+        assert_eq!(converted_to_f64, value);
+    }
+
+    #[test]
+    fn test_negative_and_fractional_ids() {
+        let mut tree = Tree::new();
+        let root = tree.add_node(Node::with_id("root", -1.0)).unwrap();
+        for (value, id) in [("half", 0.5), ("neg_frac", -2.25), ("zero", -0.0)] {
+            tree.add_node(Node::with_id(value, id));
+            tree.get_node_mut(root).unwrap().add_child(id);
+            tree.get_node_mut(id).unwrap().set_parent(root);
+        }
+        // Generated IDs are positive whole numbers and never land on these
+        let generated: Vec<Number> = (0..100)
+            .map(|_| tree.add_child_value(root, "generated").unwrap())
+            .collect();
+
+        assert_eq!(tree.size(), 104);
+        assert!(tree.is_tree());
+        for id in [-1.0, 0.5, -2.25, 0.0, -0.0] {
+            assert!(tree.contains_id(id), "missing {}", id);
+        }
+        for id in [1.5, -0.5, -2.0, f64::NAN] {
+            assert!(!tree.contains_id(id), "unexpected {}", id);
+        }
+        for id in generated {
+            assert!(id > 0.0 && id.fract() == 0.0);
+            assert_eq!(tree[id].value, "generated");
+        }
+        assert_eq!(tree[0.0].value, "zero");
+        assert_eq!(tree[0.5].parent(), Some(-1.0));
+        assert_eq!(tree.depth(-2.25), 1);
+
+        let ids: Vec<Number> = tree.dfs_sorted(root).iter().map(|n| n.id).take(4).collect();
+        assert_eq!(ids, vec![-1.0, -2.25, -0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_float_id_ordering() {
+        use std::collections::BTreeMap;
+
+        let mut ids = [
+            FloatId::new(f64::NAN),
+            FloatId::new(2.5),
+            FloatId::new(-1.0),
+            FloatId::new(f64::INFINITY),
+            FloatId::new(0.0),
+        ];
+        ids.sort();
+        let values: Vec<f64> = ids.iter().take(4).map(|id| id.value()).collect();
+        assert_eq!(values, vec![-1.0, 0.0, 2.5, f64::INFINITY]);
+        assert!(ids[4].value().is_nan());
+
+        // Ordering agrees with equality
+        assert_eq!(
+            FloatId::new(f64::NAN).cmp(&FloatId::new(f64::NAN)),
+            Ordering::Equal
+        );
+        assert_eq!(FloatId::new(-0.0).cmp(&FloatId::new(0.0)), Ordering::Equal);
+
+        let mut map = BTreeMap::new();
+        map.insert(FloatId::new(3.0), "c");
+        map.insert(FloatId::new(1.0), "a");
+        map.insert(FloatId::new(2.0), "b");
+        assert_eq!(
+            map.values().copied().collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_tree_core_operations_and_properties() {
+        let mut tree = Tree::<&str>::new();
+
+        let root = Node::new("root");
+        let child1 = Node::new("child1");
+        let child2 = Node::new("child2");
+        let grandchild1 = Node::new("grandchild1");
+        let grandchild2 = Node::new("grandchild2");
+
+        let root_id = tree.add_node(root).unwrap();
+        let child1_id = tree.add_node(child1).unwrap();
+        let child2_id = tree.add_node(child2).unwrap();
+        let grandchild1_id = tree.add_node(grandchild1).unwrap();
+        let grandchild2_id = tree.add_node(grandchild2).unwrap();
+
+        // Set up relationships
+        if let Some(root_node) = tree.get_node_mut(root_id) {
+            root_node.add_child(child1_id);
+            root_node.add_child(child2_id);
+        }
+
+        if let Some(child1_node) = tree.get_node_mut(child1_id) {
+            child1_node.set_parent(root_id);
+            child1_node.add_child(grandchild1_id);
+        }
+
+        if let Some(child2_node) = tree.get_node_mut(child2_id) {
+            child2_node.set_parent(root_id);
+            child2_node.add_child(grandchild2_id);
+        }
+
+        if let Some(grandchild1_node) = tree.get_node_mut(grandchild1_id) {
+            grandchild1_node.set_parent(child1_id);
+        }
+
+        if let Some(grandchild2_node) = tree.get_node_mut(grandchild2_id) {
+            grandchild2_node.set_parent(child2_id);
+        }
+
+        tree.set_root(root_id);
+
+        // Test core properties
+        assert_eq!(tree.size(), 5);
+        assert_eq!(tree.height(root_id), 2);
+        assert_eq!(tree.depth(child1_id), 1);
+        assert_eq!(tree.depth(grandchild1_id), 2);
+        assert_eq!(tree.num_leaves(root_id), 2);
+        assert_eq!(tree.num_nodes(root_id), 5);
+        assert!(tree.is_balanced(root_id));
+
+        let leaves = tree.get_leaves(root_id);
+        assert_eq!(leaves.len(), 2);
+        assert!(leaves.iter().any(|node| node.value == "grandchild1"));
+        assert!(leaves.iter().any(|node| node.value == "grandchild2"));
+
+        // Test all traversal types
+        let dfs_result = tree.dfs(root_id);
+        let bfs_result = tree.bfs(root_id);
+        let preorder_result = tree.preorder(root_id);
+        let postorder_result = tree.postorder(root_id);
+
+        assert_eq!(dfs_result.len(), 5);
+        assert_eq!(bfs_result.len(), 5);
+        assert_eq!(preorder_result.len(), 5);
+        assert_eq!(postorder_result.len(), 5);
+
+        // Verify traversal order
+        assert_eq!(preorder_result[0].id, root_id);
+        assert_eq!(postorder_result[4].id, root_id);
+
+        // Test trait methods work correctly
+        assert!(!tree.is_empty());
+
+        // Test search by value through trait
+        assert_eq!(tree.search_by_value(&"root"), Some(root_id));
+        assert_eq!(tree.search_by_value(&"child1"), Some(child1_id));
+        assert_eq!(tree.search_by_value(&"grandchild1"), Some(grandchild1_id));
+        assert_eq!(tree.search_by_value(&"nonexistent"), None);
+
+        // Test node count through trait
+        assert_eq!(tree.num_nodes(root_id), 5); // root + child1 + child2 + grandchild1 + grandchild2
+        assert_eq!(tree.num_nodes(child1_id), 2); // child1 + grandchild1
+        assert_eq!(tree.num_nodes(grandchild1_id), 1); // grandchild1 only
+
+        // Test balance check through trait
+        assert!(tree.is_balanced(root_id)); // balanced tree
+        assert!(tree.is_balanced(child1_id)); // balanced subtree
+        assert!(tree.is_balanced(grandchild1_id)); // leaf node is always balanced
+    }
+
+    #[test]
+    fn test_infinite_recursion() {
+        let mut tree = Tree::new();
+        let node1 = Node::new("root");
+        let node2 = Node::new("child");
+
+        let id1 = tree.add_node(node1).unwrap();
+        let id2 = tree.add_node(node2).unwrap();
+
+        // Set up parent-child relationship
+        if let Some(parent) = tree.get_node_mut(id1) {
+            parent.add_child(id2);
+        }
+        if let Some(child) = tree.get_node_mut(id2) {
+            child.set_parent(id1);
+        }
+
+        // Test that trait methods don't cause infinite recursion
+        assert_eq!(tree.size(), 2);
+        assert!(!tree.is_empty());
+
+        // Test search by value through trait (should not crash)
+        if let Some(found_id) = tree.search_by_value(&"root") {
+            assert_eq!(found_id, id1);
+        } else {
+            panic!("Should have found 'root' node");
+        }
+
+        // Test node count through trait (should not crash)
+        let node_count = tree.num_nodes(id1);
+        assert_eq!(node_count, 2);
+
+        // Test balance check through trait (should not crash)
+        let is_balanced = tree.is_balanced(id1);
+        assert!(is_balanced);
+    }
+
+    #[test]
+    fn test_deep_chain_metrics() {
+        let mut tree = Tree::new();
+        let depth = 50_000;
+
+        let root_id = tree.add_node(Node::new(0)).unwrap();
+        let mut parent_id = root_id;
+        for i in 1..=depth {
+            let child_id = tree.add_node(Node::new(i)).unwrap();
+            tree.get_node_mut(parent_id).unwrap().add_child(child_id);
+            tree.get_node_mut(child_id).unwrap().set_parent(parent_id);
+            parent_id = child_id;
+        }
+
+        // Iterative implementations must not overflow the stack
+        assert_eq!(tree.num_nodes(root_id), depth + 1);
+        assert_eq!(tree.num_leaves(root_id), 1);
+        assert_eq!(tree.height(root_id), depth);
+        assert_eq!(NodeBasedTree::height(&tree, root_id), depth);
+        assert_eq!(tree.height(parent_id), 0);
+        assert_eq!(tree.checked_height(root_id), Some(depth));
+        assert_eq!(tree.checked_height(-1.0), None);
+        assert_eq!(tree.get_leaves(root_id).len(), 1);
+        assert_eq!(NodeBasedTree::get_leaves(&tree, root_id)[0].id, parent_id);
+        assert_eq!(tree.depth(parent_id), depth);
+        assert_eq!(NodeBasedTree::depth(&tree, parent_id), depth);
+        assert!(tree.is_balanced(root_id));
+
+        // A parent cycle ends the walk instead of looping
+        tree.get_node_mut(root_id).unwrap().set_parent(parent_id);
+        tree.get_node_mut(parent_id).unwrap().add_child(root_id);
+        assert_eq!(tree.depth(root_id), depth + 1);
+        assert!(tree.get_leaves(root_id).is_empty());
+    }
+
+    #[test]
+    fn test_from_level_order() {
+        let tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4), Some(5)]);
+
+        assert_eq!(tree.size(), 5);
+        let root_id = tree.root_id().unwrap();
+        let root = tree.get_node(root_id).unwrap();
+        assert_eq!(root.value, 1);
+        assert_eq!(root.num_children(), 2);
+
+        let left = tree.get_node(root.left().unwrap()).unwrap();
+        let right = tree.get_node(root.right().unwrap()).unwrap();
+        assert_eq!((left.value, right.value), (2, 3));
+        assert_eq!(left.parent(), Some(root_id));
+        assert_eq!(left.left(), None);
+        assert_eq!(tree.get_node(left.right().unwrap()).unwrap().value, 4);
+        assert_eq!(tree.get_node(right.left().unwrap()).unwrap().value, 5);
+        assert_eq!(right.right(), None);
+        assert_eq!(tree.height(root_id), 2);
+
+        // Empty input and a missing root both produce an empty tree
+        assert!(Tree::<i32>::from_level_order(Vec::new()).is_empty());
+        assert!(Tree::from_level_order(vec![None, Some(1)]).is_empty());
+    }
+
+    #[test]
+    fn test_level_order_round_trip() {
+        let values = vec![
+            Some(1),
+            Some(2),
+            Some(3),
+            None,
+            Some(4),
+            Some(5),
+            None,
+            Some(6),
+        ];
+        let tree = Tree::from_level_order(values.clone());
+        let root_id = tree.root_id().unwrap();
+        assert_eq!(tree.to_level_order(root_id), values);
+
+        // Exporting a subtree starts from that node
+        let left_id = tree.get_node(root_id).unwrap().left().unwrap();
+        assert_eq!(
+            tree.to_level_order(left_id),
+            vec![Some(2), None, Some(4), Some(6)]
+        );
+
+        // Missing start node
+        assert!(tree.to_level_order(-1.0).is_empty());
+    }
+
+    #[test]
+    fn test_node_metadata() {
+        let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3)]);
+        let root_id = tree.root_id().unwrap();
+
+        // Mark every node as unvisited, then visit the root
+        let ids: Vec<Number> = tree.bfs(root_id).iter().map(|node| node.id).collect();
+        for &id in &ids {
+            assert!(tree.set_meta(id, false));
+        }
+        *tree.meta_mut::<bool>(root_id).unwrap() = true;
+
+        assert_eq!(tree.meta::<bool>(root_id), Some(&true));
+        assert_eq!(tree.meta::<bool>(ids[1]), Some(&false));
+        assert_eq!(tree.meta::<i32>(root_id), None);
+
+        // Metadata is not cloned and is dropped with its node
+        assert_eq!(tree.clone().meta::<bool>(root_id), None);
+        tree.remove_node(ids[1]);
+        assert_eq!(tree.meta::<bool>(ids[1]), None);
+        assert!(!tree.set_meta(ids[1], true));
+
+        assert!(tree.remove_meta(ids[2]));
+        assert!(!tree.remove_meta(ids[2]));
+        tree.clear_meta();
+        assert_eq!(tree.meta::<bool>(root_id), None);
+    }
+
+    #[test]
+    fn test_keep_only_subtree() {
+        let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4), Some(5)]);
+        let root_id = tree.root_id().unwrap();
+        let left_id = tree.get_node(root_id).unwrap().left().unwrap();
+        let right_id = tree.get_node(root_id).unwrap().right().unwrap();
+
+        tree.keep_only_subtree(left_id);
+
+        assert_eq!(tree.size(), 3);
+        assert_eq!(tree.root_id(), Some(left_id));
+        assert!(tree.get_node(left_id).unwrap().is_root());
+        assert!(tree.get_node(root_id).is_none());
+        assert!(tree.get_node(right_id).is_none());
+        let mut values: Vec<i32> = tree.bfs(left_id).iter().map(|node| node.value).collect();
+        values.sort();
+        assert_eq!(values, vec![2, 4, 5]);
+
+        // Missing ids leave the tree untouched
+        tree.keep_only_subtree(-1.0);
+        assert_eq!(tree.size(), 3);
+    }
+
+    #[test]
+    fn test_merge_duplicate_children() {
+        // root -> a(1) -> x(4)
+        //      -> b(2)
+        //      -> a(3) -> x(5), y(6)
+        //      -> a(7)
+        let mut tree = Tree::new();
+        let root = tree
+            .add_node(Node::with_id("root".to_string(), 0.0))
+            .unwrap();
+        let children = [(1.0, "a"), (2.0, "b"), (3.0, "a"), (7.0, "a")];
+        let grandchildren = [(4.0, "x", 1.0), (5.0, "x", 3.0), (6.0, "y", 3.0)];
+        for (id, value) in children {
+            tree.add_node(Node::with_id(value.to_string(), id));
+            tree.get_node_mut(root).unwrap().add_child(id);
+            tree.get_node_mut(id).unwrap().set_parent(root);
+        }
+        for (id, value, parent) in grandchildren {
+            tree.add_node(Node::with_id(value.to_string(), id));
+            tree.get_node_mut(parent).unwrap().add_child(id);
+            tree.get_node_mut(id).unwrap().set_parent(parent);
+        }
+        tree.get_node_mut(root).unwrap().set_left(3.0);
+        tree.set_meta(7.0, "dropped");
+
+        let mut combined = Vec::new();
+        let merged = tree.merge_duplicate_children(root, |survivor, removed| {
+            combined.push(removed.clone());
+            survivor.push_str(&removed);
+        });
+
+        assert_eq!(merged, 2);
+        assert_eq!(combined, vec!["a", "a"]);
+        assert_eq!(tree.size(), 6);
+        assert!(tree.is_tree());
+        assert_eq!(tree[1.0].value, "aaa");
+        assert_eq!(tree[root].left(), None);
+        assert!(tree.meta::<&str>(7.0).is_none());
+
+        // Conflicting grandchildren are kept side by side under the survivor
+        let mut grandchildren = tree[1.0].children();
+        grandchildren.sort_by(f64::total_cmp);
+        assert_eq!(grandchildren, vec![4.0, 5.0, 6.0]);
+        assert_eq!(tree.merge_duplicate_children(1.0, |_, _| {}), 1);
+        assert_eq!(tree[1.0].num_children(), 2);
+
+        assert_eq!(tree.merge_duplicate_children(root, |_, _| {}), 0);
+        assert_eq!(tree.merge_duplicate_children(99.0, |_, _| {}), 0);
+    }
+
+    #[test]
+    fn test_all_pairs_distances() {
+        for n in [1, 2, 5, 30] {
+            let parents: Vec<Option<usize>> =
+                (0..n).map(|i| (i > 0).then(|| (i * 7 + 3) % i)).collect();
+            let tree = Tree::from_parent_array(&parents).unwrap();
+
+            let distances = tree.all_pairs_distances();
+            assert_eq!(distances.len(), n * (n - 1) / 2);
+            for (&(a, b), &distance) in &distances {
+                assert!(a < b);
+                let path = tree.path_between(a.value(), b.value()).unwrap();
+                assert_eq!(distance, path.len() - 1);
+            }
+        }
+
+        // Pairs across a forest have no distance
+        let mut tree = Tree::from_parent_array(&[None, Some(0)]).unwrap();
+        tree.add_node(Node::with_id(2, 2.0));
+        let distances = tree.all_pairs_distances();
+        assert_eq!(distances.len(), 1);
+        assert_eq!(distances[&(FloatId::from(0.0), FloatId::from(1.0))], 1);
+        assert!(Tree::<i32>::new().all_pairs_distances().is_empty());
+    }
+
+    #[test]
+    fn test_apply_id_map() {
+        //     0
+        //    / \
+        //   1   2
+        let mut tree = Tree::from_level_order(vec![Some("a"), Some("b"), Some("c")]);
+        let root = tree.root_id().unwrap();
+        let left = tree[root].left().unwrap();
+        let right = tree[root].right().unwrap();
+        tree.set_meta(left, 7);
+
+        let map: HashMap<FloatId, Number> = HashMap::from([
+            (FloatId::from(root), 2.0),
+            (FloatId::from(left), 0.0),
+            (FloatId::from(right), 1.0),
+        ]);
+        tree.apply_id_map(&map).unwrap();
+
+        assert_eq!(tree.root_id(), Some(2.0));
+        assert_eq!(tree[2.0].value, "a");
+        assert_eq!(tree[2.0].left(), Some(0.0));
+        assert_eq!(tree[2.0].right(), Some(1.0));
+        assert_eq!(tree[0.0].parent(), Some(2.0));
+        assert_eq!(tree[1.0].value, "c");
+        assert_eq!(tree.meta::<i32>(0.0), Some(&7));
+        assert!(tree.is_tree());
+
+        // Missing, extra and colliding entries are all rejected untouched
+        let before = format!("{:?}", tree);
+        let missing = HashMap::from([(FloatId::from(2.0), 5.0), (FloatId::from(0.0), 6.0)]);
+        let mut extra = map.clone();
+        extra.insert(FloatId::from(99.0), 99.0);
+        let mut colliding: HashMap<FloatId, Number> = [0.0, 1.0, 2.0]
+            .map(|id| (FloatId::from(id), id + 3.0))
+            .into();
+        colliding.insert(FloatId::from(1.0), 3.0);
+        for bad in [missing, extra, colliding] {
+            assert_eq!(tree.apply_id_map(&bad), Err(TreeError::InvalidIdMap));
+        }
+        assert_eq!(format!("{:?}", tree), before);
+
+        // The identity map is a no-op and an empty tree takes an empty map
+        let identity: HashMap<FloatId, Number> =
+            [0.0, 1.0, 2.0].map(|id| (FloatId::from(id), id)).into();
+        tree.apply_id_map(&identity).unwrap();
+        assert_eq!(format!("{:?}", tree), before);
+        assert!(Tree::<i32>::new().apply_id_map(&HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_count_subtrees_where() {
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1), Some(4)]).unwrap();
+
+        let mut visited = Vec::new();
+        let with_two_levels = tree.count_subtrees_where(|tree, id| {
+            visited.push(id);
+            tree.height(id) >= 1
+        });
+        assert_eq!(with_two_levels, 3);
+        assert_eq!(visited, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        // Every node counts, whether or not it hangs off the root
+        let mut forest = tree.clone();
+        forest.add_node(Node::with_id(6, 6.0));
+        assert_eq!(
+            forest.count_subtrees_where(|tree, id| tree.num_nodes(id) == 1),
+            4
+        );
+        assert_eq!(Tree::<i32>::new().count_subtrees_where(|_, _| true), 0);
+    }
+
+    #[test]
+    fn test_children_sorted() {
+        let mut tree = Tree::new();
+        let root_id = tree.add_node(Node::with_id(0, 0.0)).unwrap();
+        for (id, value) in [(5.0, 30), (1.0, 10), (3.0, 20), (2.0, 30), (4.0, -20)] {
+            tree.add_node(Node::with_id(value, id));
+            tree.get_node_mut(root_id).unwrap().add_child(id);
+            tree.get_node_mut(id).unwrap().set_parent(root_id);
+        }
+        tree.get_node_mut(root_id).unwrap().add_child(99.0);
+
+        assert_eq!(tree.children_sorted(root_id), vec![4.0, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!(
+            tree.children_sorted_by_key(root_id, |value: &i32| value.abs()),
+            vec![1.0, 3.0, 4.0, 2.0, 5.0]
+        );
+        assert_eq!(
+            tree.children_sorted_by_key(root_id, |value: &i32| std::cmp::Reverse(*value)),
+            vec![2.0, 5.0, 3.0, 1.0, 4.0]
+        );
+        assert!(tree.children_sorted(1.0).is_empty());
+        assert!(tree.children_sorted(42.0).is_empty());
+    }
+
+    #[test]
+    fn test_flatten() {
+        //   0     3
+        //   |     |
+        //   1     4
+        //   |     |
+        //   2     5
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(1)]).unwrap();
+        for (id, parent) in [(3.0, None), (4.0, Some(3.0)), (5.0, Some(4.0))] {
+            tree.add_node(Node::with_id(id as usize, id));
+            if let Some(parent) = parent {
+                tree.get_node_mut(id).unwrap().set_parent(parent);
+                tree.get_node_mut(parent).unwrap().add_child(id);
+            }
+        }
+        assert_eq!(
+            tree.clone().flatten(TraversalOrder::Bfs),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            tree.clone().flatten(TraversalOrder::Postorder),
+            vec![2, 1, 0, 5, 4, 3]
+        );
+
+        // Nodes on a parent cycle are not reachable from any root but are
+        // still returned, after the forest
+        tree.add_node(Node::with_id(6, 6.0));
+        tree.add_node(Node::with_id(7, 7.0));
+        for (id, parent) in [(6.0, 7.0), (7.0, 6.0)] {
+            tree.get_node_mut(id).unwrap().set_parent(parent);
+            tree.get_node_mut(parent).unwrap().add_child(id);
+        }
+        assert_eq!(
+            tree.flatten(TraversalOrder::Preorder),
+            vec![0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        assert!(Tree::<i32>::new().flatten(TraversalOrder::Dfs).is_empty());
+    }
+
+    #[test]
+    fn test_leaf_rules() {
+        let tree = Tree::from_parent_array(&[None]).unwrap();
+        assert_eq!(tree.leaf_rules(0.0), vec![(vec![0.0], &0)]);
+        assert!(tree.leaf_rules(99.0).is_empty());
+
+        // A long chain with a fork at the bottom
+        let depth: usize = 50_000;
+        let mut parents: Vec<Option<usize>> = (0..=depth).map(|i| i.checked_sub(1)).collect();
+        parents.push(Some(depth - 1));
+        let tree = Tree::from_parent_array(&parents).unwrap();
+
+        let rules = tree.leaf_rules(0.0);
+        assert_eq!(rules.len(), 2);
+        let (path, value) = &rules[1];
+        assert_eq!(**value, depth + 1);
+        assert_eq!(path.len(), depth + 1);
+        assert_eq!(path[depth - 1], (depth - 1) as Number);
+        assert_eq!(path.last(), Some(&((depth + 1) as Number)));
+        assert_eq!(rules[0].0.len(), depth + 1);
+        for (path, value) in &rules {
+            assert_eq!(path[0], 0.0);
+            assert!(tree[*path.last().unwrap()].is_leaf());
+            assert_eq!(
+                tree.path_between(0.0, path[path.len() - 1]).as_ref(),
+                Some(path)
+            );
+            assert_eq!(**value as Number, *path.last().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_is_isomorphic() {
+        //        0                 0
+        //      / | \             / | \
+        //     1  2  3           1  2  3
+        //    /|     |           |     |\
+        //   4 5     6           4     5 6
+        //   |                         |
+        //   7                         7
+        let a = Tree::from_parent_array(&[
+            None,
+            Some(0),
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(3),
+            Some(4),
+        ])
+        .unwrap();
+        let b = Tree::from_parent_array(&[
+            None,
+            Some(0),
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(3),
+            Some(3),
+            Some(5),
+        ])
+        .unwrap();
+        assert!(a.is_isomorphic(&b));
+        assert!(b.is_isomorphic(&a));
+        assert_ne!(a, b);
+
+        // Values and ID schemes do not matter, nor does the value type
+        let (renamed, _) = b.clone_with_new_ids();
+        let strings = Tree::from_level_order(vec![Some("x"), Some("y")]);
+        let pair = Tree::from_parent_array(&[None, Some(0)]).unwrap();
+        assert!(a.is_isomorphic(&renamed));
+        assert!(strings.is_isomorphic(&pair));
+
+        // Same size and degree sequence, different shape
+        let c = Tree::from_parent_array(&[
+            None,
+            Some(0),
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(3),
+            Some(6),
+        ])
+        .unwrap();
+        assert!(!a.is_isomorphic(&c));
+
+        let empty: Tree<i32> = Tree::new();
+        assert!(empty.is_isomorphic(&Tree::<String>::new()));
+        assert!(!empty.is_isomorphic(&a));
+    }
+
+    #[test]
+    fn test_subtree_fingerprints() {
+        //          0:x
+        //        /     \
+        //     1:y       2:y
+        //    /   \     /   \
+        //  3:z   4:w  5:w  6:z
+        //  |                |
+        //  7:v              8:u
+        let mut tree = Tree::new();
+        tree.add_node(Node::with_id("x", 0.0));
+        let links = [
+            (1.0, "y", 0.0),
+            (2.0, "y", 0.0),
+            (3.0, "z", 1.0),
+            (4.0, "w", 1.0),
+            (5.0, "w", 2.0),
+            (6.0, "z", 2.0),
+            (7.0, "v", 3.0),
+            (8.0, "u", 6.0),
+        ];
+        for (id, value, parent) in links {
+            tree.add_node(Node::with_id(value, id));
+            tree.get_node_mut(parent).unwrap().add_child(id);
+            tree.get_node_mut(id).unwrap().set_parent(parent);
+        }
+        // A detached copy of the `z -> v` subtree
+        tree.add_node(Node::with_id("z", 9.0));
+        tree.add_node(Node::with_id("v", 10.0));
+        tree.get_node_mut(9.0).unwrap().add_child(10.0);
+        tree.get_node_mut(10.0).unwrap().set_parent(9.0);
+
+        let prints = tree.subtree_fingerprints();
+        let print = |id: Number| prints[&FloatId::from(id)];
+        assert_eq!(prints.len(), tree.size());
+        assert_eq!(print(4.0), print(5.0));
+        assert_eq!(print(3.0), print(9.0));
+        assert_ne!(print(3.0), print(6.0));
+        assert_ne!(print(1.0), print(2.0));
+
+        // The root fingerprint is what the structural `Hash` feeds in
+        let hash_of = |tree: &Tree<&str>| {
+            let mut hasher = DefaultHasher::new();
+            tree.hash(&mut hasher);
+            hasher.finish()
+        };
+        let mut expected = DefaultHasher::new();
+        Some(print(0.0)).hash(&mut expected);
+        assert_eq!(hash_of(&tree), expected.finish());
+
+        // Changing a leaf changes the fingerprint of every ancestor only
+        let before = prints.clone();
+        tree.get_node_mut(8.0).unwrap().value = "v";
+        let after = tree.subtree_fingerprints();
+        for id in [8.0, 6.0, 2.0, 0.0] {
+            assert_ne!(before[&FloatId::from(id)], after[&FloatId::from(id)]);
+        }
+        for id in [1.0, 3.0, 4.0, 5.0, 7.0, 9.0, 10.0] {
+            assert_eq!(before[&FloatId::from(id)], after[&FloatId::from(id)]);
+        }
+        assert!(Tree::<i32>::new().subtree_fingerprints().is_empty());
+    }
+
+    #[test]
+    fn test_explicit_ids_do_not_collide_with_generated_ones() {
+        // Explicit whole-number IDs are reserved, so generated ones skip them
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        let child = tree.add_child_value(0.0, 100).unwrap();
+        assert!(child > 3.0);
+        assert_eq!(tree.size(), 5);
+        assert_eq!(tree[1.0].value, 1);
+        assert!(tree.is_tree());
+
+        let far = Node::with_id(0, 1_000_000.0);
+        assert!(Node::new(0).id > far.id);
+
+        // A node whose ID was set after construction is not overwritten
+        let mut tree = Tree::new();
+        let root_id = tree.add_node(Node::new(0)).unwrap();
+        let next = Node::new(1).id + 1.0;
+        let mut squatter = Node::new(2);
+        squatter.id = next;
+        tree.nodes.insert(FloatId::from(next), squatter);
+        let child = tree.add_child_value(root_id, 3).unwrap();
+        assert_ne!(child, next);
+        assert_eq!(tree[next].value, 2);
+        assert_eq!(tree.size(), 3);
+    }
+
+    #[test]
+    fn test_huge_explicit_ids_do_not_exhaust_generated_ones() {
+        // Reserving near 2^53 would make generated IDs round to one value
+        let last_exact = 2f64.powi(53) - 1.0;
+        let mut tree = Tree::new();
+        let root_id = tree.add_node(Node::with_id(0, last_exact)).unwrap();
+        assert!(tree.add_node(Node::with_id(0, 2f64.powi(52))).is_some());
+        let first = Node::new(1).id;
+        let second = Node::new(2).id;
+        assert_ne!(first, second);
+        assert!(first < 2f64.powi(52) && second < 2f64.powi(52));
+        tree.add_child_value(root_id, 3).unwrap();
+        tree.add_child_value(root_id, 4).unwrap();
+        assert_eq!(tree.size(), 4);
+    }
+
+    #[test]
+    fn test_binary_traversals() {
+        let values =
+            |nodes: Vec<&Node<i32>>| -> Vec<i32> { nodes.iter().map(|node| node.value).collect() };
+
+        // A BST's binary inorder is sorted, whatever the insertion order
+        let mut bst = BST::new();
+        for x in [50, 30, 70, 20, 40, 60, 80, 35, 45, 65] {
+            bst.insert(x);
+        }
+        let tree = bst.as_tree();
+        let root_id = tree.root_id().unwrap();
+        assert_eq!(
+            values(tree.binary_inorder(root_id)),
+            vec![20, 30, 35, 40, 45, 50, 60, 65, 70, 80]
+        );
+        assert_eq!(
+            values(tree.binary_preorder(root_id)),
+            vec![50, 30, 20, 40, 35, 45, 70, 60, 65, 80]
+        );
+        assert_eq!(
+            values(tree.binary_postorder(root_id)),
+            vec![20, 35, 45, 40, 30, 65, 60, 80, 70, 50]
+        );
+
+        // The n-ary children set is ignored
+        let mut tree = Tree::from_level_order(vec![Some(2), Some(1), Some(3)]);
+        let root_id = tree.root_id().unwrap();
+        let extra = tree.add_child_value(root_id, 9).unwrap();
+        assert_eq!(values(tree.binary_inorder(root_id)), vec![1, 2, 3]);
+        assert!(tree.binary_preorder(extra).len() == 1);
+        assert!(tree.binary_postorder(-1.0).is_empty());
+
+        // Degenerate chains do not overflow the stack
+        let mut tree = Tree::new();
+        for id in 0..20_000 {
+            let mut node = Node::with_id(-id, id as Number);
+            if id > 0 {
+                node.set_parent(id as Number - 1.0);
+            }
+            if id + 1 < 20_000 {
+                node.set_left(id as Number + 1.0);
+            }
+            tree.add_node(node);
+        }
+        let inorder = values(tree.binary_inorder(0.0));
+        assert_eq!(inorder.len(), 20_000);
+        assert!(inorder.is_sorted());
+        assert_eq!(tree.binary_preorder(0.0)[0].value, 0);
+        assert_eq!(tree.binary_postorder(0.0)[0].value, -19_999);
+
+        // A link back up ends the walk instead of looping
+        let mut looped = Tree::from_level_order(vec![Some(1), Some(2)]);
+        let root_id = looped.root_id().unwrap();
+        let left = looped[root_id].left().unwrap();
+        looped.get_node_mut(left).unwrap().set_right(root_id);
+        assert_eq!(values(looped.binary_inorder(root_id)), vec![2, 1]);
+        assert_eq!(values(looped.binary_preorder(root_id)), vec![1, 2]);
+        assert_eq!(values(looped.binary_postorder(root_id)), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_sorted_traversals() {
+        let mut tree = Tree::new();
+        let root_id = tree.add_node(Node::with_id(0, 10.0)).unwrap();
+        let edges = [
+            (10.0, 7.0),
+            (10.0, 3.0),
+            (10.0, 5.0),
+            (3.0, 9.0),
+            (3.0, 1.0),
+            (7.0, 2.0),
+        ];
+        for (parent_id, child_id) in edges {
+            tree.add_node(Node::with_id(0, child_id));
+            tree.get_node_mut(parent_id).unwrap().add_child(child_id);
+            tree.get_node_mut(child_id).unwrap().set_parent(parent_id);
+        }
+
+        let dfs: Vec<Number> = tree.dfs_sorted(root_id).iter().map(|n| n.id).collect();
+        let bfs: Vec<Number> = tree.bfs_sorted(root_id).iter().map(|n| n.id).collect();
+        assert_eq!(dfs, vec![10.0, 3.0, 1.0, 9.0, 5.0, 7.0, 2.0]);
+        assert_eq!(bfs, vec![10.0, 3.0, 5.0, 7.0, 1.0, 9.0, 2.0]);
+
+        // Same nodes as the unordered traversals
+        assert_eq!(dfs.len(), tree.dfs(root_id).len());
+        assert_eq!(bfs.len(), tree.bfs(root_id).len());
+    }
+
+    #[test]
+    fn test_clone_with_new_ids() {
+        let tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4)]);
+        let (copy, id_map) = tree.clone_with_new_ids();
+
+        assert_eq!(copy.size(), tree.size());
+        assert_eq!(id_map.len(), tree.size());
+        for (old_id, new_id) in &id_map {
+            assert!(copy.get_node(old_id.value()).is_none());
+            let old_node = tree.get_node(old_id.value()).unwrap();
+            let new_node = copy.get_node(*new_id).unwrap();
+            assert_eq!(new_node.id, *new_id);
+            assert_eq!(new_node.value, old_node.value);
+            assert_eq!(
+                new_node.parent(),
+                old_node.parent().map(|id| id_map[&FloatId::from(id)])
+            );
+            assert_eq!(
+                new_node.left(),
+                old_node.left().map(|id| id_map[&FloatId::from(id)])
+            );
+        }
+
+        let root_id = tree.root_id().unwrap();
+        let new_root_id = copy.root_id().unwrap();
+        assert_eq!(new_root_id, id_map[&FloatId::from(root_id)]);
+        assert_eq!(
+            copy.to_level_order(new_root_id),
+            tree.to_level_order(root_id)
+        );
+        assert_eq!(copy.num_nodes(new_root_id), 4);
+    }
+
+    #[test]
+    fn test_from_parent_array() {
+        let parents = [None, Some(0), Some(0), Some(1), Some(1), Some(2)];
+        let tree = Tree::from_parent_array(&parents).unwrap();
+
+        assert_eq!(tree.size(), 6);
+        assert_eq!(tree.root_id(), Some(0.0));
+        for (index, parent) in parents.iter().enumerate() {
+            let node = tree.get_node(index as Number).unwrap();
+            assert_eq!(node.value, index);
+            assert_eq!(node.parent(), parent.map(|p| p as Number));
+        }
+        assert_eq!(tree.num_leaves(0.0), 3);
+        assert_eq!(tree.height(0.0), 2);
+
+        // Later generated IDs do not land on the array's IDs
+        let mut grown = tree.clone();
+        for parent in 0..6 {
+            let child = grown
+                .add_child_value(parent as Number, 10 + parent)
+                .unwrap();
+            assert!(child >= 6.0);
+        }
+        assert_eq!(grown.size(), 12);
+        assert!(grown.is_tree());
+
+        // A root anywhere in the array
+        let tree = Tree::from_parent_array(&[Some(1), None]).unwrap();
+        assert_eq!(tree.root_id(), Some(1.0));
+
+        assert!(Tree::from_parent_array(&[]).unwrap().is_empty());
+        assert_eq!(
+            Tree::from_parent_array(&[None, Some(0), None]).unwrap_err(),
+            TreeError::MultipleRoots
+        );
+        assert_eq!(
+            Tree::from_parent_array(&[Some(1), Some(0)]).unwrap_err(),
+            TreeError::MissingRoot
+        );
+        assert_eq!(
+            Tree::from_parent_array(&[None, Some(5)]).unwrap_err(),
+            TreeError::InvalidParent {
+                index: 1,
+                parent: 5
+            }
+        );
+        assert_eq!(
+            Tree::from_parent_array(&[None, Some(2), Some(1)]).unwrap_err(),
+            TreeError::Cycle
+        );
+    }
+
+    #[test]
+    fn test_balance_factor() {
+        //        1
+        //      /   \
+        //     2     3
+        //    / \
+        //   4   5
+        //  /
+        // 6
+        let tree = Tree::from_level_order(vec![
+            Some(1),
+            Some(2),
+            Some(3),
+            Some(4),
+            Some(5),
+            None,
+            None,
+            Some(6),
+        ]);
+        let root_id = tree.root_id().unwrap();
+        let root = tree.get_node(root_id).unwrap();
+        let (two, three) = (root.left().unwrap(), root.right().unwrap());
+        let four = tree.get_node(two).unwrap().left().unwrap();
+
+        assert_eq!(tree.balance_factor(root_id), 2);
+        assert_eq!(tree.balance_factor(two), 1);
+        assert_eq!(tree.balance_factor(three), 0);
+        assert_eq!(tree.balance_factor(four), 1);
+        assert_eq!(tree.balance_factor(-1.0), 0);
+    }
+
+    #[test]
+    fn test_forest_traversal() {
+        // Two trees plus an isolated node, with the auto-root on the second tree
+        let mut tree = Tree::new();
+        for id in [5.0, 6.0, 1.0, 2.0, 3.0, 9.0] {
+            tree.add_node(Node::with_id(id as i32, id));
+        }
+        for (parent_id, child_id) in [(1.0, 2.0), (1.0, 3.0), (5.0, 6.0)] {
+            tree.get_node_mut(parent_id).unwrap().add_child(child_id);
+            tree.get_node_mut(child_id).unwrap().set_parent(parent_id);
+        }
+        assert_eq!(tree.root_id(), Some(5.0));
+        assert_eq!(tree.bfs(5.0).len(), 2);
+
+        assert_eq!(tree.roots(), vec![1.0, 5.0, 9.0]);
+
+        for order in [
+            TraversalOrder::Dfs,
+            TraversalOrder::Bfs,
+            TraversalOrder::Preorder,
+            TraversalOrder::Postorder,
+        ] {
+            let forest = tree.traverse_forest(order);
+            let sizes: Vec<usize> = forest.iter().map(|nodes| nodes.len()).collect();
+            assert_eq!(sizes, vec![3, 2, 1]);
+            assert_eq!(
+                forest.iter().map(|nodes| nodes.len()).sum::<usize>(),
+                tree.size()
+            );
+        }
+
+        let postorder = tree.traverse_forest(TraversalOrder::Postorder);
+        assert_eq!(postorder[0].last().unwrap().id, 1.0);
+        let bfs = tree.traverse_forest(TraversalOrder::Bfs);
+        assert_eq!(bfs[0][0].id, 1.0);
+
+        assert!(Tree::<i32>::new()
+            .traverse_forest(TraversalOrder::Dfs)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_structural_eq_and_hash() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash_of<T: Hash>(tree: &Tree<T>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            tree.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let a = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4)]);
+        let b = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4)]);
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        // Values and left/right placement both matter
+        let mirrored = Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4)]);
+        let relabeled = Tree::from_level_order(vec![Some(1), Some(2), Some(5), None, Some(4)]);
+        assert_ne!(a, mirrored);
+        assert_ne!(a, relabeled);
+        assert_ne!(hash_of(&a), hash_of(&mirrored));
+
+        // n-ary children are unordered and ids are ignored
+        let x = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        let mut y = Tree::new();
+        for (id, value) in [(10.0, 0), (20.0, 2), (30.0, 1), (40.0, 3)] {
+            y.add_node(Node::with_id(value, id));
+        }
+        for (parent_id, child_id) in [(10.0, 20.0), (10.0, 30.0), (30.0, 40.0)] {
+            y.get_node_mut(parent_id).unwrap().add_child(child_id);
+            y.get_node_mut(child_id).unwrap().set_parent(parent_id);
+        }
+        assert_eq!(x, y);
+        assert_eq!(hash_of(&x), hash_of(&y));
+
+        // Duplicate sibling values must be matched one-to-one
+        let p = Tree::from_parent_array(&[None, Some(0), Some(0)]).unwrap();
+        let mut q = Tree::from_parent_array(&[None, Some(0), Some(0)]).unwrap();
+        q.get_node_mut(2.0).unwrap().value = 1;
+        assert_ne!(p, q);
+
+        assert_eq!(Tree::<i32>::new(), Tree::<i32>::new());
+        assert_ne!(Tree::<i32>::new(), a);
+
+        // Deep left chains compare without overflowing the stack
+        let chain = |len: i32| {
+            let mut tree = Tree::new();
+            let mut parent = tree.add_node(Node::new(0)).unwrap();
+            for value in 1..len {
+                let child = tree.add_node(Node::new(value)).unwrap();
+                tree.get_node_mut(parent).unwrap().set_left(child);
+                tree.get_node_mut(child).unwrap().set_parent(parent);
+                parent = child;
+            }
+            tree
+        };
+        assert_eq!(chain(50_000), chain(50_000));
+        assert_ne!(chain(50_000), chain(49_999));
+
+        // A link back to an ancestor ends the comparison instead of looping
+        let mut looped = Tree::from_level_order(vec![Some(1), Some(2)]);
+        let root_id = looped.root_id().unwrap();
+        let left = looped[root_id].left().unwrap();
+        looped.get_node_mut(left).unwrap().set_right(root_id);
+        assert_eq!(looped, looped.clone());
+        assert_eq!(hash_of(&looped), hash_of(&looped.clone()));
+    }
+
+    #[test]
+    fn test_iter_with_depth() {
+        let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(3)]).unwrap();
+
+        let pairs: Vec<(usize, usize)> = tree
+            .iter_with_depth(0.0)
+            .map(|(node, depth)| (node.value, depth))
+            .collect();
+        assert_eq!(pairs.len(), 5);
+        for &(value, depth) in &pairs {
+            assert_eq!(depth, tree.depth(value as Number));
+        }
+        // Preorder: every node comes after its parent
+        let position = |value: usize| pairs.iter().position(|&(v, _)| v == value).unwrap();
+        assert!(
+            position(0) < position(1) && position(1) < position(3) && position(3) < position(4)
+        );
+
+        // Depths are relative to the start node
+        let sub: Vec<(usize, usize)> = tree
+            .iter_with_depth(1.0)
+            .map(|(node, depth)| (node.value, depth))
+            .collect();
+        assert_eq!(sub, vec![(1, 0), (3, 1), (4, 2)]);
+
+        assert_eq!(tree.iter_with_depth(-1.0).count(), 0);
+    }
 
-        // Test display
-        let display_str = format!("{}", node);
-        assert_eq!(display_str, "Node(value=42)");
+    #[test]
+    fn test_paths_of_length() {
+        //        0
+        //      / | \
+        //     1  2  3
+        //    / \    \
+        //   4   5    6
+        //   |
+        //   7
+        let parents = [
+            None,
+            Some(0),
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(3),
+            Some(4),
+        ];
+        let tree = Tree::from_parent_array(&parents).unwrap();
+
+        assert_eq!(tree.paths_of_length(0.0, 0).len(), 8);
+        assert_eq!(tree.paths_of_length(0.0, 1).len(), 7);
+        assert_eq!(
+            tree.paths_of_length(0.0, 2),
+            vec![
+                vec![0.0, 1.0, 4.0],
+                vec![0.0, 1.0, 5.0],
+                vec![0.0, 3.0, 6.0],
+                vec![1.0, 4.0, 7.0],
+            ]
+        );
+        assert_eq!(tree.paths_of_length(0.0, 3), vec![vec![0.0, 1.0, 4.0, 7.0]]);
+        assert!(tree.paths_of_length(0.0, 4).is_empty());
+
+        // Only paths inside the given subtree
+        assert_eq!(tree.paths_of_length(1.0, 2), vec![vec![1.0, 4.0, 7.0]]);
+        assert!(tree.paths_of_length(-1.0, 1).is_empty());
     }
 
     #[test]
-    fn test_node_relationships() {
-        let mut parent = Node::new("parent");
-        let mut child = Node::new("child");
+    fn test_insert_and_get_mut_respects_max_nodes() {
+        let mut tree = Tree::new();
+        tree.set_max_nodes(3);
+        let (root_id, _) = tree.insert_and_get_mut(0);
+        for value in 1..3 {
+            let (_, node) = tree.insert_and_get_mut(value);
+            node.set_parent(root_id);
+        }
+        let children: Vec<_> = tree
+            .nodes
+            .keys()
+            .copied()
+            .filter(|&id| id != FloatId::from(root_id))
+            .collect();
+        for id in children {
+            tree.get_node_mut(root_id).unwrap().add_child(id.value());
+        }
+        for value in 3..6 {
+            tree.add_child_value(root_id, value).unwrap();
+            assert!(tree.size() <= 3);
+        }
+        assert!(tree.contains_id(root_id));
+    }
 
-        parent.add_child(child.id);
-        child.set_parent(parent.id);
+    #[test]
+    fn test_lru_eviction() {
+        let mut tree = Tree::new();
+        let root = tree.add_node(Node::new(0)).unwrap();
+        let a = tree.add_child_value(root, 1).unwrap();
+        let b = tree.add_child_value(a, 2).unwrap();
+        let c = tree.add_child_value(root, 3).unwrap();
+        tree.get_node_mut(root).unwrap().set_left(c);
+        tree.set_max_nodes(4);
+        assert_eq!(tree.size(), 4);
+
+        // `c` was added after `b`, but touching `b` makes `c` the LRU leaf.
+        // `a` is never eligible while it has a child.
+        tree.touch(b);
+        let d = tree.add_child_value(b, 4).unwrap();
+        assert_eq!(tree.size(), 4);
+        assert!(tree.get_node(c).is_none());
+        assert!(!tree.root().unwrap().children().contains(&c));
+        assert_eq!(tree.root().unwrap().left(), None);
+
+        // Shrinking the cap evicts leaves bottom-up until the tree fits
+        tree.set_max_nodes(2);
+        assert_eq!(tree.size(), 2);
+        assert!(tree.get_node(d).is_none());
+        assert!(tree.get_node(b).is_none());
+        assert_eq!(tree.root().unwrap().children(), vec![a]);
+        assert!(tree.get_node(a).unwrap().is_leaf());
+
+        // Untouched nodes go first, in ascending ID order, and access order
+        // survives renumbering
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(0)]).unwrap();
+        tree.set_max_nodes(4);
+        tree.touch(1.0);
+        tree.renumber();
+        let e = tree.add_child_value(0.0, 4).unwrap();
+        assert!(tree.get_node(2.0).is_none());
+        tree.add_child_value(0.0, 5).unwrap();
+        assert!(tree.get_node(3.0).is_none());
+        tree.add_child_value(0.0, 6).unwrap();
+        assert!(tree.get_node(1.0).is_none());
+        assert!(tree.get_node(e).is_some());
+
+        // A long run of capped inserts keeps only the most recent leaves
+        let mut tree = Tree::new();
+        let root = tree.add_node(Node::new(0)).unwrap();
+        tree.set_max_nodes(100);
+        let added: Vec<Number> = (1..20_000)
+            .map(|value| tree.add_child_value(root, value).unwrap())
+            .collect();
+        assert_eq!(tree.size(), 100);
+        assert!(added[added.len() - 99..]
+            .iter()
+            .all(|&id| tree.contains_id(id)));
+    }
 
-        assert_eq!(parent.children(), vec![child.id]);
-        assert_eq!(child.parent(), Some(parent.id));
-        assert!(!parent.is_leaf());
-        assert!(child.is_leaf());
-        assert!(!child.is_root());
+    #[test]
+    fn test_max_children() {
+        let mut tree = Tree::with_max_children(3);
+        assert_eq!(tree.max_children(), Some(3));
+        let root = tree.add_node(Node::new(0)).unwrap();
+        assert_eq!(
+            tree.try_add_child_value(-1.0, 1),
+            Err(TreeError::MissingNode)
+        );
+
+        let children: Vec<Number> = (1..=3)
+            .map(|i| tree.try_add_child_value(root, i).unwrap())
+            .collect();
+        assert_eq!(
+            tree.try_add_child_value(root, 4),
+            Err(TreeError::TooManyChildren { max: 3 })
+        );
+        assert_eq!(tree.add_child_value(root, 4), None);
+        assert_eq!(tree.size(), 4);
+
+        // Other nodes still have room, and freeing a slot makes room again
+        assert!(tree.add_child_value(children[0], 5).is_some());
+        tree.remove_node(children[2]);
+        tree.get_node_mut(root).unwrap().remove_child(children[2]);
+        assert!(tree.add_child_value(root, 6).is_some());
+
+        // Lowering the cap leaves existing nodes alone but reports them
+        assert!(tree.overfull_nodes().is_empty());
+        tree.set_max_children(1);
+        assert_eq!(tree.overfull_nodes(), vec![root]);
+        assert_eq!(tree[root].num_children(), 3);
+        assert!(tree.add_child_value(root, 7).is_none());
+        assert!(tree.clone().add_child_value(children[1], 8).is_some());
+
+        // A cap of zero makes every node a leaf
+        let mut leaves = Tree::with_max_children(0);
+        let only = leaves.add_node(Node::new(0)).unwrap();
+        assert_eq!(
+            leaves.try_add_child_value(only, 1),
+            Err(TreeError::TooManyChildren { max: 0 })
+        );
+    }
 
-        // Test multiple children
-        let child2 = Node::new("child2");
-        parent.add_child(child2.id);
-        assert_eq!(parent.num_children(), 2);
+    #[test]
+    fn test_visitor_accept() {
+        struct Recorder(Vec<String>);
 
-        // Test removing child
-        parent.remove_child(child2.id);
-        assert_eq!(parent.num_children(), 1);
+        impl Visitor<usize> for Recorder {
+            fn on_enter(&mut self, node: &Node<usize>, depth: usize) {
+                self.0.push(format!("enter {} {}", node.value, depth));
+            }
 
-        // Test clearing relationships
-        parent.remove_child(child.id);
-        child.remove_parent();
-        assert!(parent.is_root() && parent.is_leaf());
-        assert!(child.is_root() && child.is_leaf());
+            fn on_leave(&mut self, node: &Node<usize>, depth: usize) {
+                self.0.push(format!("leave {} {}", node.value, depth));
+            }
+        }
+
+        let tree = Tree::from_parent_array(&[None, Some(0), Some(1), Some(0)]).unwrap();
+        let mut recorder = Recorder(Vec::new());
+        tree.accept(0.0, &mut recorder);
+        assert_eq!(
+            recorder.0,
+            vec![
+                "enter 0 0",
+                "enter 1 1",
+                "enter 2 2",
+                "leave 2 2",
+                "leave 1 1",
+                "enter 3 1",
+                "leave 3 1",
+                "leave 0 0",
+            ]
+        );
+
+        let mut recorder = Recorder(Vec::new());
+        tree.accept(99.0, &mut recorder);
+        assert!(recorder.0.is_empty());
     }
 
     #[test]
-    fn test_binary_tree_operations() {
-        let mut root = Node::new(10);
-        let left = Node::new(5);
-        let right = Node::new(15);
+    fn test_left_child_right_sibling() {
+        //       0
+        //     / | \
+        //    1  2  3
+        //   / \    \
+        //  4   5    6
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(0), Some(1), Some(1), Some(3)])
+                .unwrap();
+
+        let binary = tree.to_left_child_right_sibling();
+        assert_eq!(binary.size(), 7);
+        assert_eq!(binary.root().unwrap().left(), Some(1.0));
+        assert_eq!(binary.root().unwrap().right(), None);
+        assert_eq!(binary.get_node(1.0).unwrap().left(), Some(4.0));
+        assert_eq!(binary.get_node(1.0).unwrap().right(), Some(2.0));
+        assert_eq!(binary.get_node(2.0).unwrap().right(), Some(3.0));
+        assert_eq!(binary.get_node(3.0).unwrap().left(), Some(6.0));
+        assert_eq!(binary.get_node(4.0).unwrap().right(), Some(5.0));
+        assert_eq!(binary.get_node(5.0).unwrap().parent(), Some(4.0));
+        assert_eq!(binary.height(0.0), 4);
+
+        let nary = Tree::from_left_child_right_sibling(&binary);
+        assert_eq!(nary, tree);
+        assert_eq!(nary.get_node(5.0).unwrap().parent(), Some(1.0));
+        assert!(nary
+            .nodes
+            .values()
+            .all(|node| node.left().is_none() && node.right().is_none()));
+    }
 
-        root.set_left(left.id);
-        root.set_right(right.id);
+    #[test]
+    fn test_count_edges_where() {
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        assert_eq!(tree.count_edges_where(|_, _| true), 3);
+        assert_eq!(
+            tree.count_edges_where(|parent, child| child == &(parent + 2)),
+            2
+        );
+
+        // Detached components still count
+        let a = tree.add_node_no_root(Node::new(10)).unwrap();
+        let b = tree.add_node_no_root(Node::new(1)).unwrap();
+        tree.get_node_mut(a).unwrap().add_child(b);
+        tree.get_node_mut(b).unwrap().set_parent(a);
+        assert_eq!(tree.count_edges_where(|parent, child| child < parent), 1);
+        assert_eq!(tree.count_edges_where(|_, _| true), 4);
+    }
 
-        assert_eq!(root.left(), Some(left.id));
-        assert_eq!(root.right(), Some(right.id));
-        assert!(root.has_left());
-        assert!(root.has_right());
-        assert!(!root.is_binary_leaf());
+    #[test]
+    fn test_edge_list() {
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(2), Some(2)]).unwrap();
+        tree.get_node_mut(4.0).unwrap().add_child(99.0);
+        // A detached pair still contributes its link
+        tree.add_node(Node::with_id(5, -2.0));
+        tree.add_node(Node::with_id(6, 7.5));
+        tree.get_node_mut(-2.0).unwrap().add_child(7.5);
+
+        let expected = vec![(-2.0, 7.5), (0.0, 1.0), (0.0, 2.0), (2.0, 3.0), (2.0, 4.0)];
+        assert_eq!(tree.edge_list(), expected);
+        assert_eq!(tree.clone().edge_list(), expected);
+        assert!(Tree::<i32>::new().edge_list().is_empty());
+    }
 
-        root.clear_left();
-        root.clear_right();
-        assert_eq!(root.left(), None);
-        assert_eq!(root.right(), None);
+    #[test]
+    fn test_leaf_depths() {
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(3), Some(3), Some(2)])
+                .unwrap();
+        assert_eq!(tree.leaf_depths(0.0), vec![2, 3, 3]);
+        assert_eq!(tree.leaf_depths(3.0), vec![1, 1]);
+        assert_eq!(tree.leaf_depths(6.0), vec![0]);
+        assert!(tree.leaf_depths(99.0).is_empty());
+        assert_eq!(tree.leaf_depths(0.0).len(), tree.num_leaves(0.0));
     }
 
     #[test]
-    fn test_float_id_functionality() {
-        use std::collections::HashMap;
+    fn test_is_ancestor() {
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(1), Some(2)]).unwrap();
+        assert!(tree.is_ancestor(0.0, 3.0));
+        assert!(tree.is_ancestor(2.0, 3.0));
+        assert!(!tree.is_ancestor(3.0, 0.0));
+        assert!(!tree.is_ancestor(2.0, 2.0));
+        assert!(!tree.is_ancestor(0.0, 99.0));
+
+        // A parent cycle terminates instead of looping forever
+        tree.get_node_mut(0.0).unwrap().set_parent(3.0);
+        assert!(tree.is_ancestor(3.0, 1.0));
+        assert!(!tree.is_ancestor(99.0, 1.0));
+    }
 
-        let id1 = FloatId::new(1.5);
-        let id2 = FloatId::new(1.5);
-        let id3 = FloatId::new(2.5);
+    #[test]
+    fn test_children_iter_matches_children() {
+        let mut parent = Node::new(0);
+        assert_eq!(parent.children_iter().next(), None);
 
-        // Test equality and hashing
-        assert_eq!(id1, id2);
-        assert_ne!(id1, id3);
+        for id in [3.0, 1.0, 2.0] {
+            parent.add_child(id);
+        }
+        assert_eq!(
+            parent.children_iter().collect::<Vec<_>>(),
+            parent.children()
+        );
+    }
 
-        let mut map = HashMap::new();
-        map.insert(id1, "first");
-        map.insert(id2, "second");
-        map.insert(id3, "third");
+    #[test]
+    fn test_postorder_iter_matches_recursive() {
+        let tree = Tree::from_parent_array(&[
+            None,
+            Some(0),
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(3),
+            Some(6),
+        ])
+        .unwrap();
+        let ids = |nodes: Vec<&Node<usize>>| nodes.iter().map(|node| node.id).collect::<Vec<_>>();
+        assert_eq!(ids(tree.postorder_iter(0.0)), ids(tree.postorder(0.0)));
+        assert_eq!(ids(tree.postorder_iter(3.0)), vec![7.0, 6.0, 3.0]);
+        assert!(tree.postorder_iter(99.0).is_empty());
+    }
 
-        assert_eq!(map.get(&id1), Some(&"second"));
-        assert_eq!(map.len(), 2);
+    #[test]
+    fn test_try_root_and_empty_tree() {
+        let mut tree: Tree<i32> = Tree::new();
+        assert_eq!(tree.try_root().unwrap_err(), TreeError::Empty);
+        assert_eq!(tree.height(0.0), 0);
 
-        // Test NaN handling
-        let nan1 = FloatId::new(f64::NAN);
-        let nan2 = FloatId::new(f64::NAN);
-        let regular = FloatId::new(1.0);
+        assert!(!tree.set_root(1.0));
+        assert_eq!(tree.root_id(), None);
 
-        assert_eq!(nan1, nan2);
-        assert_ne!(nan1, regular);
+        let id = tree.add_node(Node::new(7)).unwrap();
+        assert_eq!(tree.try_root().unwrap().id, id);
+        assert_eq!(tree.height(id), 0);
 
-        // Test conversion
-        let value = 3.14159;
-        let float_id = FloatId::new(value);
-        assert_eq!(float_id.value(), value);
+        assert!(!tree.set_root(99.0));
+        assert_eq!(tree.root_id(), Some(id));
+        assert!(tree.set_root(id));
 
-        let converted_to_f64: f64 = float_id.into();
-        assert_eq!(converted_to_f64, value);
+        tree.set_root_id(None);
+        assert_eq!(tree.try_root().unwrap_err(), TreeError::MissingRoot);
     }
 
     #[test]
-    fn test_tree_core_operations_and_properties() {
-        let mut tree = Tree::<&str>::new();
-
-        let root = Node::new("root");
-        let child1 = Node::new("child1");
-        let child2 = Node::new("child2");
-        let grandchild1 = Node::new("grandchild1");
-        let grandchild2 = Node::new("grandchild2");
-
-        let root_id = tree.add_node(root).unwrap();
-        let child1_id = tree.add_node(child1).unwrap();
-        let child2_id = tree.add_node(child2).unwrap();
-        let grandchild1_id = tree.add_node(grandchild1).unwrap();
-        let grandchild2_id = tree.add_node(grandchild2).unwrap();
+    fn test_insert_and_get_mut() {
+        let mut tree = Tree::new();
+        let (root_id, root) = tree.insert_and_get_mut(0);
+        root.value = 10;
 
-        // Set up relationships
-        if let Some(root_node) = tree.get_node_mut(root_id) {
-            root_node.add_child(child1_id);
-            root_node.add_child(child2_id);
+        for value in 1..=3 {
+            let (child_id, child) = tree.insert_and_get_mut(value);
+            child.set_parent(root_id);
+            tree.get_node_mut(root_id).unwrap().add_child(child_id);
         }
+        assert_eq!(tree.size(), 4);
+        assert_eq!(tree.root().unwrap().value, 10);
+        assert_eq!(tree.num_leaves(root_id), 3);
+    }
 
-        if let Some(child1_node) = tree.get_node_mut(child1_id) {
-            child1_node.set_parent(root_id);
-            child1_node.add_child(grandchild1_id);
+    #[test]
+    fn test_renumber() {
+        let mut tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4)]);
+        let stray = tree.add_node_no_root(Node::new(9)).unwrap();
+        let left = tree.root().unwrap().left().unwrap();
+        tree.set_meta(left, "left");
+        let before = tree.to_level_order(tree.root_id().unwrap());
+
+        let id_map = tree.renumber();
+        assert_eq!(id_map.len(), 5);
+        assert_eq!(tree.root_id(), Some(0.0));
+        assert_eq!(tree.to_level_order(0.0), before);
+        assert_eq!(id_map[&FloatId::from(stray)], 4.0);
+        assert_eq!(tree[4.0].value, 9);
+
+        let new_left = id_map[&FloatId::from(left)];
+        assert_eq!(new_left, 1.0);
+        assert_eq!(tree.meta::<&str>(new_left), Some(&"left"));
+        assert_eq!(tree[3.0].parent(), Some(1.0));
+        assert_eq!(tree[1.0].children(), vec![3.0]);
+
+        // New nodes never land on renumbered IDs
+        for value in 10..15 {
+            let child = tree.add_child_value(0.0, value).unwrap();
+            assert!(child >= 5.0);
         }
+        assert_eq!(tree.size(), 10);
+        assert_eq!(tree[4.0].value, 9);
+    }
 
-        if let Some(child2_node) = tree.get_node_mut(child2_id) {
-            child2_node.set_parent(root_id);
-            child2_node.add_child(grandchild2_id);
-        }
+    #[test]
+    fn test_subtree_contains() {
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(3)]).unwrap();
+        tree.add_node_no_root(Node::new(7));
+
+        assert!(tree.subtree_contains(0.0, &4));
+        assert!(tree.subtree_contains(1.0, &4));
+        assert!(tree.subtree_contains(2.0, &2));
+        assert!(!tree.subtree_contains(2.0, &4));
+        // Values outside the rooted subtree are not found
+        assert!(tree.search_by_value(&7).is_some());
+        assert!(!tree.subtree_contains(0.0, &7));
+    }
 
-        if let Some(grandchild1_node) = tree.get_node_mut(grandchild1_id) {
-            grandchild1_node.set_parent(child1_id);
+    #[test]
+    fn test_leftmost_and_rightmost_leaf() {
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1), Some(2)]).unwrap();
+        assert_eq!(tree.leftmost_leaf(0.0), Some(3.0));
+        assert_eq!(tree.rightmost_leaf(0.0), Some(5.0));
+        assert_eq!(tree.rightmost_leaf(1.0), Some(4.0));
+        assert_eq!(tree.leftmost_leaf(5.0), Some(5.0));
+        assert_eq!(tree.leftmost_leaf(99.0), None);
+
+        let single = Tree::from_level_order(vec![Some(1)]);
+        let root = single.root_id().unwrap();
+        assert_eq!(single.leftmost_leaf(root), Some(root));
+        assert_eq!(single.rightmost_leaf(root), Some(root));
+    }
+
+    #[test]
+    fn test_recursion_depth_guard() {
+        let parents: Vec<Option<usize>> = (0..10)
+            .map(|i| if i == 0 { None } else { Some(i - 1) })
+            .collect();
+        let mut tree = Tree::from_parent_array(&parents).unwrap();
+        assert_eq!(tree.max_recursion_depth(), None);
+
+        tree.set_max_recursion_depth(4);
+        assert_eq!(tree.dfs(0.0).len(), 5);
+        assert_eq!(tree.preorder(0.0).len(), 5);
+        assert_eq!(tree.postorder(0.0).len(), 5);
+        assert_eq!(tree.inorder(0.0).len(), 5);
+        assert_eq!(tree.bfs(0.0).len(), 10);
+        for order in [
+            TraversalOrder::Dfs,
+            TraversalOrder::Preorder,
+            TraversalOrder::Postorder,
+        ] {
+            assert_eq!(tree.try_traverse(0.0, order), Err(TreeError::TooDeep));
+            assert_eq!(tree.try_traverse(5.0, order).unwrap().len(), 5);
         }
+        assert_eq!(
+            tree.try_traverse(0.0, TraversalOrder::Bfs).unwrap().len(),
+            10
+        );
+
+        tree.clear_max_recursion_depth();
+        assert_eq!(
+            tree.try_traverse(0.0, TraversalOrder::Postorder)
+                .unwrap()
+                .len(),
+            10
+        );
+    }
 
-        if let Some(grandchild2_node) = tree.get_node_mut(grandchild2_id) {
-            grandchild2_node.set_parent(child2_id);
+    #[test]
+    fn test_graph_vertex_builder() {
+        let vertex = Node::graph_vertex(0)
+            .with_outgoing([1.0, 2.0, 2.0])
+            .with_incoming(vec![3.0])
+            .with_undirected([4.0, 5.0])
+            .with_outgoing([6.0]);
+
+        let mut outgoing = vertex.outgoing();
+        outgoing.sort_by(|a, b| a.total_cmp(b));
+        assert_eq!(outgoing, vec![1.0, 2.0, 6.0]);
+        assert_eq!(vertex.incoming(), vec![3.0]);
+        assert_eq!(vertex.edges().len(), 2);
+        assert!(vertex.is_root() && vertex.is_leaf());
+        assert!(!vertex.has_self_loop());
+    }
+
+    #[test]
+    fn test_validate_tree() {
+        let valid = || Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        assert!(valid().is_tree());
+        assert!(Tree::<i32>::new().is_tree());
+
+        // Shared child (DAG)
+        let mut tree = valid();
+        tree.get_node_mut(2.0).unwrap().add_child(3.0);
+        assert_eq!(tree.validate_tree(), Err(TreeError::MultipleParents));
+
+        // Parent field disagrees with the child lists
+        let mut tree = valid();
+        tree.get_node_mut(3.0).unwrap().set_parent(2.0);
+        assert_eq!(tree.validate_tree(), Err(TreeError::InconsistentLinks));
+
+        // Dangling child
+        let mut tree = valid();
+        tree.get_node_mut(3.0).unwrap().add_child(99.0);
+        assert_eq!(tree.validate_tree(), Err(TreeError::InconsistentLinks));
+
+        // Detached cycle 4 <-> 5 next to a valid tree
+        let mut tree = valid();
+        tree.add_node(Node::with_id(4, 4.0));
+        tree.add_node(Node::with_id(5, 5.0));
+        for (parent, child) in [(4.0, 5.0), (5.0, 4.0)] {
+            tree.get_node_mut(parent).unwrap().add_child(child);
+            tree.get_node_mut(child).unwrap().set_parent(parent);
         }
+        assert_eq!(tree.validate_tree(), Err(TreeError::Cycle));
+
+        // Every node has a parent
+        let mut tree = valid();
+        tree.get_node_mut(0.0).unwrap().set_parent(3.0);
+        tree.get_node_mut(3.0).unwrap().add_child(0.0);
+        assert_eq!(tree.validate_tree(), Err(TreeError::MissingRoot));
+
+        // Stored root is a leaf, or missing, while node 0 has no parent
+        let mut tree = valid();
+        tree.set_root(3.0);
+        assert_eq!(tree.validate_tree(), Err(TreeError::RootMismatch));
+        tree.set_root_id(None);
+        assert_eq!(tree.validate_tree(), Err(TreeError::RootMismatch));
+        tree.set_root(0.0);
+        assert!(tree.is_tree());
+    }
 
-        tree.set_root(root_id);
+    #[test]
+    fn test_rebuild_parent_links() {
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        for id in 0..4 {
+            tree.get_node_mut(id as Number).unwrap().remove_parent();
+        }
+        // A stale parent on a node no one lists as a child is cleared
+        tree.add_node(Node::with_id(4, 4.0));
+        tree.get_node_mut(4.0).unwrap().set_parent(1.0);
+        tree.get_node_mut(3.0).unwrap().add_child(99.0);
+
+        tree.rebuild_parent_links().unwrap();
+        assert_eq!(tree[0.0].parent(), None);
+        assert_eq!(tree[1.0].parent(), Some(0.0));
+        assert_eq!(tree[2.0].parent(), Some(0.0));
+        assert_eq!(tree[3.0].parent(), Some(1.0));
+        assert_eq!(tree[4.0].parent(), None);
+        assert_eq!(tree.roots(), vec![0.0, 4.0]);
+
+        // A shared child is rejected without touching any links
+        tree.get_node_mut(2.0).unwrap().add_child(3.0);
+        tree.get_node_mut(1.0).unwrap().remove_parent();
+        assert_eq!(tree.rebuild_parent_links(), Err(TreeError::MultipleParents));
+        assert_eq!(tree[1.0].parent(), None);
+        assert_eq!(tree[3.0].parent(), Some(1.0));
+    }
 
-        // Test core properties
-        assert_eq!(tree.size(), 5);
-        assert_eq!(tree.height(root_id), 2);
-        assert_eq!(tree.depth(child1_id), 1);
-        assert_eq!(tree.depth(grandchild1_id), 2);
-        assert_eq!(tree.num_leaves(root_id), 2);
-        assert_eq!(tree.num_nodes(root_id), 5);
-        assert!(tree.is_balanced(root_id));
+    #[test]
+    fn test_rebuild_children_links() {
+        let expected =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1)]).unwrap();
 
-        let leaves = tree.get_leaves(root_id);
-        assert_eq!(leaves.len(), 2);
-        assert!(leaves.iter().any(|node| node.value == "grandchild1"));
-        assert!(leaves.iter().any(|node| node.value == "grandchild2"));
+        // Only the parents are set
+        let mut tree = Tree::new();
+        for id in 0..5 {
+            tree.add_node(Node::with_id(id, id as Number));
+        }
+        for (child, parent) in [(1.0, 0.0), (2.0, 0.0), (3.0, 1.0), (4.0, 1.0)] {
+            tree.get_node_mut(child).unwrap().set_parent(parent);
+        }
+        // Stale children are dropped
+        tree.get_node_mut(2.0).unwrap().add_child(4.0);
+
+        tree.rebuild_children_links();
+        assert!(tree.is_tree());
+        assert_eq!(tree, expected);
+        let mut children = tree[1.0].children();
+        children.sort_by(f64::total_cmp);
+        assert_eq!(children, vec![3.0, 4.0]);
+        assert!(tree[2.0].is_leaf());
+
+        // A dangling parent contributes no child
+        tree.get_node_mut(4.0).unwrap().set_parent(99.0);
+        tree.rebuild_children_links();
+        assert_eq!(tree[1.0].children(), vec![3.0]);
+        assert_eq!(tree[4.0].parent(), Some(99.0));
+    }
 
-        // Test all traversal types
-        let dfs_result = tree.dfs(root_id);
-        let bfs_result = tree.bfs(root_id);
-        let preorder_result = tree.preorder(root_id);
-        let postorder_result = tree.postorder(root_id);
+    #[test]
+    fn test_num_levels() {
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(1), Some(0), Some(3), Some(4)]).unwrap();
+        for id in 0..6 {
+            let id = id as Number;
+            assert_eq!(tree.num_levels(id), tree.height(id) + 1);
+        }
+        assert_eq!(tree.num_levels(0.0), 4);
+        assert_eq!(tree.num_levels(-1.0), 0);
+        assert_eq!(Tree::<i32>::new().num_levels(0.0), 0);
+    }
 
-        assert_eq!(dfs_result.len(), 5);
-        assert_eq!(bfs_result.len(), 5);
-        assert_eq!(preorder_result.len(), 5);
-        assert_eq!(postorder_result.len(), 5);
+    #[test]
+    fn test_deepest_node() {
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(1), Some(0), Some(3), Some(4)]).unwrap();
+        assert_eq!(tree.deepest_node(0.0), Some((5.0, 3)));
+        assert_eq!(tree.deepest_node(1.0), Some((2.0, 1)));
+        assert_eq!(tree.deepest_node(5.0), Some((5.0, 0)));
+        assert_eq!(tree.deepest_node(99.0), None);
+        assert_eq!(tree.height(0.0), 3);
+
+        let (id, depth) = Tree::from_parent_array(&[None, Some(0), Some(0)])
+            .unwrap()
+            .deepest_node(0.0)
+            .unwrap();
+        assert!(id == 1.0 || id == 2.0);
+        assert_eq!(depth, 1);
+    }
 
-        // Verify traversal order
-        assert_eq!(preorder_result[0].id, root_id);
-        assert_eq!(postorder_result[4].id, root_id);
+    #[test]
+    fn test_swap_subtrees() {
+        //       0
+        //     / | \
+        //    1  2  3
+        //   /      |
+        //  4       5
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(0), Some(1), Some(3)]).unwrap();
+        let values = |tree: &Tree<usize>| {
+            tree.dfs_sorted(0.0)
+                .iter()
+                .map(|node| node.value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(&tree), vec![0, 1, 4, 2, 3, 5]);
+
+        // Swap a sibling with a nested node in another branch
+        assert!(tree.swap_subtrees(1.0, 5.0));
+        assert_eq!(tree[1.0].parent(), Some(3.0));
+        assert_eq!(tree[5.0].parent(), Some(0.0));
+        assert_eq!(tree[3.0].children(), vec![1.0]);
+        assert_eq!(values(&tree), vec![0, 2, 3, 1, 4, 5]);
+        assert!(tree.is_tree());
+
+        // Swap two siblings under the root
+        let mut binary =
+            Tree::from_level_order(vec![Some(1), Some(2), Some(3), Some(4), None, Some(5)]);
+        let root = binary.root_id().unwrap();
+        let (left, right) = (binary[root].left().unwrap(), binary[root].right().unwrap());
+        assert!(binary.swap_subtrees(left, right));
+        assert_eq!(binary[root].left(), Some(right));
+        assert_eq!(binary[root].right(), Some(left));
+        assert_eq!(
+            binary.to_level_order(root),
+            vec![Some(1), Some(3), Some(2), Some(5), None, Some(4)]
+        );
+
+        assert!(!tree.swap_subtrees(3.0, 4.0));
+        assert!(!tree.swap_subtrees(4.0, 0.0));
+        assert!(!tree.swap_subtrees(2.0, 2.0));
+        assert!(!tree.swap_subtrees(2.0, 99.0));
+        assert_eq!(values(&tree), vec![0, 2, 3, 1, 4, 5]);
+    }
 
-        // Test trait methods work correctly
-        assert!(!tree.is_empty());
+    #[test]
+    fn test_core_api_without_clone_or_ord() {
+        // Neither Clone, Ord, PartialEq nor Debug
+        struct Opaque(u8);
 
-        // Test search by value through trait
-        assert_eq!(tree.search_by_value(&"root"), Some(root_id));
-        assert_eq!(tree.search_by_value(&"child1"), Some(child1_id));
-        assert_eq!(tree.search_by_value(&"grandchild1"), Some(grandchild1_id));
-        assert_eq!(tree.search_by_value(&"nonexistent"), None);
+        let mut tree = Tree::new();
+        let root = tree.add_node(Node::new(Opaque(0))).unwrap();
+        let a = tree.add_child_value(root, Opaque(1)).unwrap();
+        let (b, node) = tree.insert_and_get_mut(Opaque(2));
+        node.set_parent(a);
+        tree.get_node_mut(a).unwrap().add_child(b);
+        tree.get_node_mut(b).unwrap().value.0 += 10;
+
+        assert_eq!(tree.dfs(root).len(), 3);
+        assert_eq!(tree.bfs(root).len(), 3);
+        assert_eq!(tree.preorder(root).len(), 3);
+        assert_eq!(tree.postorder_iter(root).len(), 3);
+        assert_eq!(tree.height(root), 2);
+        assert!(tree.is_tree());
+        assert!(!tree.swap_subtrees(a, b));
+
+        let removed = tree.remove_node(b).unwrap();
+        assert_eq!(removed.value.0, 12);
+        tree.get_node_mut(a).unwrap().remove_child(b);
+        assert_eq!(tree.size(), 2);
+        assert_eq!(tree[root].value.0, 0);
+    }
 
-        // Test node count through trait
-        assert_eq!(tree.num_nodes(root_id), 5); // root + child1 + child2 + grandchild1 + grandchild2
-        assert_eq!(tree.num_nodes(child1_id), 2); // child1 + grandchild1
-        assert_eq!(tree.num_nodes(grandchild1_id), 1); // grandchild1 only
+    #[test]
+    fn test_nodes_at_distance() {
+        //         0
+        //       /   \
+        //      1     2
+        //     / \     \
+        //    3   4     5
+        //    |
+        //    6
+        let tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1), Some(2), Some(3)])
+                .unwrap();
+
+        assert_eq!(tree.nodes_at_distance(4.0, 0), vec![4.0]);
+        assert_eq!(tree.nodes_at_distance(4.0, 1), vec![1.0]);
+        assert_eq!(tree.nodes_at_distance(4.0, 2), vec![0.0, 3.0]);
+        assert_eq!(tree.nodes_at_distance(4.0, 3), vec![2.0, 6.0]);
+        assert_eq!(tree.nodes_at_distance(4.0, 4), vec![5.0]);
+        assert!(tree.nodes_at_distance(4.0, 5).is_empty());
+        assert_eq!(tree.nodes_at_distance(0.0, 2), vec![3.0, 4.0, 5.0]);
+
+        // Agrees with the length of the path between every pair
+        for a in 0..7 {
+            for b in 0..7 {
+                let (a, b) = (a as Number, b as Number);
+                let k = tree.path_between(a, b).unwrap().len() - 1;
+                assert!(tree.nodes_at_distance(a, k).contains(&b));
+            }
+        }
 
-        // Test balance check through trait
-        assert!(tree.is_balanced(root_id)); // balanced tree
-        assert!(tree.is_balanced(child1_id)); // balanced subtree
-        assert!(tree.is_balanced(grandchild1_id)); // leaf node is always balanced
+        assert!(tree.nodes_at_distance(99.0, 0).is_empty());
     }
 
     #[test]
-    fn test_infinite_recursion() {
-        let mut tree = Tree::new();
-        let node1 = Node::new("root");
-        let node2 = Node::new("child");
+    fn test_paths_and_lowest_common_ancestor() {
+        //       0
+        //      / \
+        //     1   2
+        //    / \   \
+        //   3   4   5
+        //   |
+        //   6
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1), Some(2), Some(3)])
+                .unwrap();
+        tree.add_node_no_root(Node::with_id(7, 7.0));
+
+        assert_eq!(tree.lowest_common_ancestor(6.0, 4.0), Some(1.0));
+        assert_eq!(tree.lowest_common_ancestor(6.0, 5.0), Some(0.0));
+        assert_eq!(tree.lowest_common_ancestor(6.0, 6.0), Some(6.0));
+        assert_eq!(tree.lowest_common_ancestor(6.0, 7.0), None);
+
+        assert_eq!(
+            tree.path_between(6.0, 5.0),
+            Some(vec![6.0, 3.0, 1.0, 0.0, 2.0, 5.0])
+        );
+        assert_eq!(tree.path_between(4.0, 4.0), Some(vec![4.0]));
+        assert_eq!(tree.path_between(5.0, 7.0), None);
+
+        assert_eq!(tree.count_on_path(6.0, 5.0, &1), Some(1));
+        assert_eq!(tree.count_on_path(6.0, 4.0, &2), Some(0));
+        for id in [1.0, 3.0] {
+            tree.get_node_mut(id).unwrap().value = 9;
+        }
+        assert_eq!(tree.count_on_path(6.0, 4.0, &9), Some(2));
+        assert_eq!(tree.count_on_path(6.0, 7.0, &9), None);
+    }
 
-        let id1 = tree.add_node(node1).unwrap();
-        let id2 = tree.add_node(node2).unwrap();
+    #[test]
+    fn test_for_each_value_mut() {
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        tree.add_node_no_root(Node::with_id(10, 10.0));
+        let before = tree.clone();
+
+        tree.for_each_value_mut(|value| *value *= 2);
+        assert_eq!(tree[3.0].value, 6);
+        assert_eq!(tree[10.0].value, 20);
+        for (id, node) in &before.nodes {
+            assert_eq!(tree.nodes[id].parent, node.parent);
+            assert_eq!(tree.nodes[id].children, node.children);
+        }
+        assert_eq!(tree.root_id(), before.root_id());
+    }
 
-        // Set up parent-child relationship
-        if let Some(parent) = tree.get_node_mut(id1) {
-            parent.add_child(id2);
+    #[test]
+    fn test_iter_mut() {
+        let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        // A node outside the root's tree is still visited
+        tree.add_node(Node::with_id(4, 4.0));
+
+        let mut seen = 0;
+        for node in tree.iter_mut() {
+            node.value += 100;
+            seen += 1;
         }
-        if let Some(child) = tree.get_node_mut(id2) {
-            child.set_parent(id1);
+        assert_eq!(seen, tree.size());
+        for id in 0..5 {
+            assert_eq!(tree[id as Number].value, id + 100);
         }
+        assert!(Tree::<i32>::new().iter_mut().next().is_none());
+    }
 
-        // Test that trait methods don't cause infinite recursion
-        assert_eq!(tree.size(), 2);
-        assert!(!tree.is_empty());
+    #[test]
+    fn test_for_each_bfs_mut() {
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(3), Some(2)]).unwrap();
+        let expected_order: Vec<Number> = tree.bfs(0.0).iter().map(|node| node.id).collect();
+
+        // Parents are always visited first, so each depth can be derived
+        // from the depth already written for the parent
+        let mut depths: HashMap<FloatId, usize> = HashMap::new();
+        let mut order = Vec::new();
+        tree.for_each_bfs_mut(0.0, |node| {
+            let depth = node
+                .parent()
+                .map_or(0, |parent_id| depths[&FloatId::from(parent_id)] + 1);
+            depths.insert(FloatId::from(node.id), depth);
+            order.push(node.id);
+            node.value = depth;
+        });
+
+        assert_eq!(order, expected_order);
+        for id in 0..6 {
+            let id = id as Number;
+            assert_eq!(tree[id].value, tree.depth(id));
+        }
 
-        // Test search by value through trait (should not crash)
-        if let Some(found_id) = tree.search_by_value(&"root") {
-            assert_eq!(found_id, id1);
-        } else {
-            panic!("Should have found 'root' node");
+        // Only the subtree under `start` is visited
+        tree.for_each_bfs_mut(3.0, |node| node.value = 0);
+        assert_eq!(tree[4.0].value, 0);
+        assert_eq!(tree[5.0].value, 2);
+        tree.for_each_bfs_mut(99.0, |_| panic!("missing start visited"));
+    }
+
+    #[test]
+    fn test_values_in_traversal_order() {
+        let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+        let values =
+            |nodes: Vec<&Node<usize>>| nodes.iter().map(|node| node.value).collect::<Vec<_>>();
+
+        assert_eq!(tree.values_bfs_cloned(0.0), values(tree.bfs(0.0)));
+        assert_eq!(tree.values_dfs_cloned(0.0), values(tree.dfs(0.0)));
+        assert_eq!(tree.values_preorder_cloned(0.0), values(tree.preorder(0.0)));
+        assert_eq!(
+            tree.values_postorder_cloned(0.0),
+            values(tree.postorder(0.0))
+        );
+        assert_eq!(tree.values_preorder(1.0).len(), 2);
+        assert!(tree.values_bfs(99.0).is_empty());
+    }
+
+    #[test]
+    fn test_annotate_subtree() {
+        // A directory tree where each node holds its own file size
+        //        0 (dir)
+        //      /   \
+        //   1 (dir)  2: 30
+        //   /   \
+        // 3: 10  4: 5
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1)]).unwrap();
+        let sizes = [0, 0, 30, 10, 5];
+        tree.for_each_value_mut(|value| *value = sizes[*value]);
+        tree.add_node(Node::with_id(7, 9.0));
+
+        let totals = tree.annotate_subtree(|size| *size, |total, child| total + child);
+        assert_eq!(totals.len(), 6);
+        assert_eq!(totals[&FloatId::from(0.0)], 45);
+        assert_eq!(totals[&FloatId::from(1.0)], 15);
+        assert_eq!(totals[&FloatId::from(2.0)], 30);
+        assert_eq!(totals[&FloatId::from(9.0)], 7);
+        assert_eq!(tree.meta::<usize>(1.0), Some(&15));
+
+        // Other aggregates: subtree node counts and maxima
+        let counts = tree.annotate_subtree(|_| 1usize, |total, child| total + child);
+        for (id, count) in &counts {
+            assert_eq!(*count, tree.num_nodes(id.value()));
         }
+        let maxima = tree.annotate_subtree(|size| *size, |max, child| max.max(*child));
+        assert_eq!(maxima[&FloatId::from(1.0)], 10);
+        assert_eq!(maxima[&FloatId::from(0.0)], 30);
+    }
 
-        // Test node count through trait (should not crash)
-        let node_count = tree.num_nodes(id1);
-        assert_eq!(node_count, 2);
+    #[test]
+    fn test_all_depths() {
+        let tree = Tree::from_level_order(vec![Some(1), Some(2), Some(3), None, Some(4), Some(5)]);
+        let root_id = tree.root_id().unwrap();
+
+        let depths = tree.all_depths(root_id);
+        assert_eq!(depths.len(), tree.size());
+        for (id, depth) in &depths {
+            assert_eq!(*depth, tree.depth(id.value()));
+        }
 
-        // Test balance check through trait (should not crash)
-        let is_balanced = tree.is_balanced(id1);
-        assert!(is_balanced);
+        // Depths are relative to the start node
+        let left_id = tree.get_node(root_id).unwrap().left().unwrap();
+        let sub_depths = tree.all_depths(left_id);
+        assert_eq!(sub_depths.len(), 2);
+        assert_eq!(sub_depths[&FloatId::from(left_id)], 0);
+
+        assert!(tree.all_depths(-1.0).is_empty());
     }
 }
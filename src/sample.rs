@@ -0,0 +1,207 @@
+use crate::{FloatId, Number, Tree};
+use rand::Rng;
+
+impl<T> Tree<T> {
+    /// Pick a node uniformly at random
+    ///
+    /// Candidates are taken in ascending ID order, so the same tree and an
+    /// identically seeded `rng` always give the same node. Returns `None`
+    /// for an empty tree. Available with the `rand` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    /// use rand::{rngs::SmallRng, SeedableRng};
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0)]).unwrap();
+    ///
+    /// let mut rng = SmallRng::seed_from_u64(7);
+    /// let picked = tree.sample_node(&mut rng).unwrap();
+    /// assert!(tree.contains_id(picked));
+    ///
+    /// let mut again = SmallRng::seed_from_u64(7);
+    /// assert_eq!(tree.sample_node(&mut again), Some(picked));
+    /// ```
+    pub fn sample_node<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Number> {
+        let ids = self.sorted_ids(|_| true);
+        Self::pick_uniform(&ids, rng)
+    }
+
+    /// Pick a leaf uniformly at random
+    ///
+    /// A leaf is a node with no children, as in [`Tree::get_leaves`]. Like
+    /// [`Tree::sample_node`], this is deterministic for a seeded `rng`.
+    /// Returns `None` for an empty tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    /// use rand::{rngs::SmallRng, SeedableRng};
+    ///
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    ///
+    /// let mut rng = SmallRng::seed_from_u64(1);
+    /// for _ in 0..20 {
+    ///     let leaf = tree.sample_leaf(&mut rng).unwrap();
+    ///     assert!(leaf == 2.0 || leaf == 3.0);
+    /// }
+    /// ```
+    pub fn sample_leaf<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Number> {
+        let ids = self.sorted_ids(|id| self.nodes[id].is_leaf());
+        Self::pick_uniform(&ids, rng)
+    }
+
+    /// Pick a node at random with probability proportional to its weight
+    ///
+    /// `weight` is called once per node, in ascending ID order, and a node
+    /// of weight 0 is never picked. Weighting each node by
+    /// [`Tree::num_nodes`] samples in proportion to subtree size, which
+    /// favours nodes near the root. Deterministic for a seeded `rng`.
+    /// Returns `None` if the tree is empty or every weight is 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weights add up to more than `u64::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::Tree;
+    /// use rand::{rngs::SmallRng, SeedableRng};
+    ///
+    /// //     0
+    /// //    / \
+    /// //   1   2
+    /// //   |
+    /// //   3
+    /// let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+    ///
+    /// let mut rng = SmallRng::seed_from_u64(3);
+    /// let mut hits = [0; 4];
+    /// for _ in 0..8000 {
+    ///     let id = tree
+    ///         .sample_node_weighted(&mut rng, |id| tree.num_nodes(id) as u64)
+    ///         .unwrap();
+    ///     hits[id as usize] += 1;
+    /// }
+    ///
+    /// // Weights 4, 2, 1, 1 out of 8
+    /// assert!((3700..4300).contains(&hits[0]));
+    /// assert!((1700..2300).contains(&hits[1]));
+    /// ```
+    pub fn sample_node_weighted<R, F>(&self, rng: &mut R, mut weight: F) -> Option<Number>
+    where
+        R: Rng + ?Sized,
+        F: FnMut(Number) -> u64,
+    {
+        let weighted: Vec<(FloatId, u64)> = self
+            .sorted_ids(|_| true)
+            .into_iter()
+            .map(|id| (id, weight(id.value())))
+            .filter(|&(_, w)| w > 0)
+            .collect();
+        let total = weighted
+            .iter()
+            .try_fold(0u64, |sum, &(_, w)| sum.checked_add(w))
+            .expect("total weight overflows u64");
+        if total == 0 {
+            return None;
+        }
+
+        let mut target = rng.random_range(0..total);
+        for (id, w) in weighted {
+            if target < w {
+                return Some(id.value());
+            }
+            target -= w;
+        }
+        None
+    }
+
+    /// IDs of the nodes matching `keep`, in ascending order
+    fn sorted_ids(&self, keep: impl Fn(&FloatId) -> bool) -> Vec<FloatId> {
+        let mut ids: Vec<FloatId> = self.nodes.keys().copied().filter(keep).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    fn pick_uniform<R: Rng + ?Sized>(ids: &[FloatId], rng: &mut R) -> Option<Number> {
+        if ids.is_empty() {
+            return None;
+        }
+        Some(ids[rng.random_range(0..ids.len())].value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Node;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_sampling_is_deterministic_and_covers_nodes() {
+        let tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1), Some(1)]).unwrap();
+
+        let draw = |seed| {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            (0..200)
+                .map(|_| tree.sample_node(&mut rng).unwrap())
+                .collect::<Vec<Number>>()
+        };
+        let first = draw(42);
+        assert_eq!(first, draw(42));
+        for id in [0.0, 1.0, 2.0, 3.0, 4.0] {
+            assert!(first.contains(&id));
+        }
+
+        let mut rng = SmallRng::seed_from_u64(9);
+        let mut leaves: Vec<Number> = (0..200)
+            .map(|_| tree.sample_leaf(&mut rng).unwrap())
+            .collect();
+        leaves.sort_by(f64::total_cmp);
+        leaves.dedup();
+        assert_eq!(leaves, vec![2.0, 3.0, 4.0]);
+
+        let empty: Tree<i32> = Tree::new();
+        assert_eq!(empty.sample_node(&mut rng), None);
+        assert_eq!(empty.sample_leaf(&mut rng), None);
+    }
+
+    #[test]
+    fn test_weighted_sampling() {
+        let mut tree = Tree::new();
+        for id in [1.0, 2.0, 3.0] {
+            tree.add_node(Node::with_id(id as u64, id));
+        }
+        let mut rng = SmallRng::seed_from_u64(5);
+
+        // Zero-weight nodes are never picked
+        for _ in 0..100 {
+            let id = tree
+                .sample_node_weighted(&mut rng, |id| if id == 2.0 { 0 } else { 1 })
+                .unwrap();
+            assert_ne!(id, 2.0);
+        }
+        assert_eq!(tree.sample_node_weighted(&mut rng, |_| 0), None);
+        assert_eq!(
+            tree.sample_node_weighted(&mut rng, |id| (id == 3.0) as u64),
+            Some(3.0)
+        );
+
+        // Frequencies follow the weights
+        let mut hits = [0u32; 4];
+        for _ in 0..6000 {
+            let id = tree
+                .sample_node_weighted(&mut rng, |id| tree[id].value)
+                .unwrap();
+            hits[id as usize] += 1;
+        }
+        assert!((800..1200).contains(&hits[1]), "{:?}", hits);
+        assert!((1800..2200).contains(&hits[2]), "{:?}", hits);
+        assert!((2800..3200).contains(&hits[3]), "{:?}", hits);
+    }
+}
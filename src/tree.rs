@@ -1,5 +1,7 @@
 use crate::Tree;
-use crate::{Node, Number};
+use crate::{FloatId, Node, Number, TreeError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Sub;
 
 /// A Binary Search Tree implementation
 ///
@@ -28,9 +30,22 @@ use crate::{Node, Number};
 /// // For advanced tree operations, access the underlying tree
 /// let tree_ref = bst.as_tree();
 /// ```
+///
+/// # Height
+///
+/// The BST keeps the height of every subtree alongside the tree, updating
+/// it along the path walked by each insert and delete, so [`BST::height`]
+/// is O(1) instead of a full traversal. This costs one `usize` map entry
+/// per node and O(h) extra work per insert or delete. Handing out mutable
+/// access with [`BST::as_tree_mut`] or [`BST::get_node_mut`] marks the
+/// cached heights stale, since links may change behind the BST's back;
+/// `height` then recounts on every call until the next insert, delete or
+/// bulk load rebuilds the cache in O(n).
 #[derive(Debug)]
 pub struct BST<T: Ord + Clone> {
     tree: Tree<T>,
+    heights: HashMap<FloatId, usize>,
+    heights_stale: bool,
 }
 
 impl<T: Ord + Clone> BST<T> {
@@ -47,7 +62,11 @@ impl<T: Ord + Clone> BST<T> {
     /// assert_eq!(bst.size(), 0);
     /// ```
     pub fn new() -> Self {
-        Self { tree: Tree::new() }
+        Self {
+            tree: Tree::new(),
+            heights: HashMap::new(),
+            heights_stale: false,
+        }
     }
 
     /// Get a reference to the underlying tree structure
@@ -80,7 +99,8 @@ impl<T: Ord + Clone> BST<T> {
     /// This provides controlled access to the tree for advanced operations
     /// while maintaining encapsulation. Use this method when you need
     /// direct mutable access to tree-specific functionality not exposed through
-    /// the BST interface.
+    /// the BST interface. The cached subtree heights are marked stale, as
+    /// described on [`BST`].
     ///
     /// # Examples
     ///
@@ -96,6 +116,7 @@ impl<T: Ord + Clone> BST<T> {
     /// // Perform advanced tree operations...
     /// ```
     pub fn as_tree_mut(&mut self) -> &mut Tree<T> {
+        self.heights_stale = true;
         &mut self.tree
     }
 
@@ -120,26 +141,32 @@ impl<T: Ord + Clone> BST<T> {
     /// assert!(bst.search(&7).is_some());
     /// ```
     pub fn insert(&mut self, element: T) {
+        self.insert_or_find(element);
+    }
+
+    /// Insert an element unless it is present, returning its node's ID
+    fn insert_or_find(&mut self, element: T) -> Option<Number> {
+        self.refresh_heights();
         if self.tree.is_empty() {
             let node = Node::new(element);
-            if let Some(id) = self.tree.add_node(node) {
-                self.tree.set_root(id);
-            }
-            return;
+            let id = self.tree.add_node(node)?;
+            self.tree.set_root(id);
+            self.heights.insert(FloatId::from(id), 1);
+            return Some(id);
         }
 
         let root_id = self.tree.root_id().unwrap();
-        self.insert_recursive(root_id, element);
+        self.insert_recursive(root_id, element)
     }
 
-    fn insert_recursive(&mut self, node_id: Number, element: T) {
+    fn insert_recursive(&mut self, node_id: Number, element: T) -> Option<Number> {
         if let Some(node) = self.tree.get_node(node_id) {
             let current_value = &node.value;
 
             match element.cmp(current_value) {
                 std::cmp::Ordering::Less => {
                     if let Some(left_id) = node.left() {
-                        self.insert_recursive(left_id, element);
+                        self.insert_recursive(left_id, element)
                     } else {
                         let new_node = Node::new(element);
                         if let Some(new_id) = self.tree.add_node(new_node) {
@@ -150,12 +177,17 @@ impl<T: Ord + Clone> BST<T> {
                             if let Some(child) = self.tree.get_node_mut(new_id) {
                                 child.set_parent(node_id);
                             }
+                            self.heights.insert(FloatId::from(new_id), 1);
+                            self.update_heights_from(Some(node_id));
+                            Some(new_id)
+                        } else {
+                            None
                         }
                     }
                 }
                 std::cmp::Ordering::Greater => {
                     if let Some(right_id) = node.right() {
-                        self.insert_recursive(right_id, element);
+                        self.insert_recursive(right_id, element)
                     } else {
                         let new_node = Node::new(element);
                         if let Some(new_id) = self.tree.add_node(new_node) {
@@ -166,14 +198,228 @@ impl<T: Ord + Clone> BST<T> {
                             if let Some(child) = self.tree.get_node_mut(new_id) {
                                 child.set_parent(node_id);
                             }
+                            self.heights.insert(FloatId::from(new_id), 1);
+                            self.update_heights_from(Some(node_id));
+                            Some(new_id)
+                        } else {
+                            None
                         }
                     }
                 }
                 std::cmp::Ordering::Equal => {
                     // Element already exists, do nothing
+                    Some(node_id)
+                }
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Build a balanced BST from sorted elements
+    ///
+    /// Shorthand for [`BST::bulk_load_sorted`] on a new BST.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let bst = BST::from_sorted(1..=7);
+    /// assert_eq!(bst.size(), 7);
+    /// assert_eq!(bst.height(), 3);
+    /// assert_eq!(bst.root().and_then(|id| bst.get_node(id)).unwrap().value, 4);
+    /// ```
+    pub fn from_sorted(values: impl IntoIterator<Item = T>) -> Self {
+        let mut bst = Self::new();
+        bst.bulk_load_sorted(values);
+        bst
+    }
+
+    /// Load many elements at once, leaving the BST perfectly balanced
+    ///
+    /// The elements should be in ascending order. They are merged with the
+    /// elements already in the BST, duplicates are dropped, and the whole
+    /// tree is relinked with every subtree split at its median, so the
+    /// height is the minimum possible. Existing nodes are kept and only
+    /// their links change, so node IDs returned earlier by [`BST::search`]
+    /// stay valid.
+    ///
+    /// Each call costs O(existing + new): one comparison per element for
+    /// the merge and none for placement. Loading n sorted keys in a single
+    /// call is therefore O(n), where inserting them one at a time with
+    /// [`BST::insert`] costs O(n²), because each insert walks the
+    /// degenerate right spine that sorted input produces. Since every call
+    /// relinks the whole tree, k small batches cost O(k·n); collect them
+    /// into one call where possible. Unsorted input is accepted but sorted
+    /// first, at O(n log n).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// bst.insert(10);
+    /// let ten = bst.search(&10).unwrap();
+    /// bst.bulk_load_sorted([1, 2, 3, 10, 20, 30]);
+    ///
+    /// assert_eq!(bst.size(), 6);
+    /// assert_eq!(bst.height(), 3);
+    /// assert_eq!(bst.search(&10), Some(ten));
+    /// assert_eq!(bst.into_sorted_vec(), vec![1, 2, 3, 10, 20, 30]);
+    /// ```
+    pub fn bulk_load_sorted(&mut self, values: impl IntoIterator<Item = T>) {
+        let mut incoming: Vec<T> = values.into_iter().collect();
+        if !incoming.is_sorted() {
+            incoming.sort();
+        }
+
+        let existing: Vec<Number> = self.inorder().iter().map(|node| node.id).collect();
+        for &id in &existing {
+            if let Some(node) = self.tree.get_node_mut(id) {
+                for child_id in node.children() {
+                    node.remove_child(child_id);
+                }
+                node.clear_left();
+                node.clear_right();
+                node.remove_parent();
+            }
+        }
+        self.tree.set_root_id(None);
+        self.heights.clear();
+
+        // Merge by node ID, giving each new element a node as it is taken
+        let mut merged: Vec<Number> = Vec::with_capacity(existing.len() + incoming.len());
+        let mut existing = existing.into_iter().peekable();
+        let mut incoming = incoming.into_iter().peekable();
+        loop {
+            let take_incoming = match (existing.peek(), incoming.peek()) {
+                (Some(&id), Some(value)) => *value < self.tree[id].value,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (None, None) => break,
+            };
+            if !take_incoming {
+                merged.extend(existing.next());
+                continue;
+            }
+            let Some(value) = incoming.next() else {
+                break;
+            };
+            if merged.last().map(|&id| &self.tree[id].value) != Some(&value) {
+                merged.extend(self.tree.add_node_no_root(Node::new(value)));
+            }
+        }
+
+        let len = merged.len();
+        let root_id = self.build_balanced(&mut merged.into_iter(), len);
+        self.tree.set_root_id(root_id.map(FloatId::from));
+        self.heights_stale = false;
+    }
+
+    /// Link the next `len` unlinked nodes of `ids` into a balanced subtree
+    ///
+    /// Nodes are consumed in order: the left half, then the subtree's root,
+    /// then the right half. Returns the root's ID.
+    fn build_balanced(
+        &mut self,
+        ids: &mut impl Iterator<Item = Number>,
+        len: usize,
+    ) -> Option<Number> {
+        if len == 0 {
+            return None;
+        }
+        let left_len = len / 2;
+        let left_id = self.build_balanced(ids, left_len);
+        let node_id = ids.next()?;
+        let right_id = self.build_balanced(ids, len - left_len - 1);
+
+        for (child_id, is_left) in [(left_id, true), (right_id, false)] {
+            let Some(child_id) = child_id else {
+                continue;
+            };
+            if let Some(node) = self.tree.get_node_mut(node_id) {
+                if is_left {
+                    node.set_left(child_id);
+                } else {
+                    node.set_right(child_id);
                 }
+                node.add_child(child_id);
+            }
+            if let Some(child) = self.tree.get_node_mut(child_id) {
+                child.set_parent(node_id);
             }
         }
+        let height = 1 + self
+            .cached_height(left_id)
+            .max(self.cached_height(right_id));
+        self.heights.insert(FloatId::from(node_id), height);
+        Some(node_id)
+    }
+
+    /// Serialize the BST so that its exact shape can be rebuilt
+    ///
+    /// The elements are listed in level order with `None` for each missing
+    /// left or right child, in the format of [`Tree::to_level_order`].
+    /// Unlike [`BST::inorder`], this records where every element sits, so
+    /// [`BST::deserialize_shape`] restores the same left/right structure and
+    /// height instead of whatever shape reinserting the elements would give.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let bst = BST::from_sorted([1, 2, 3, 4]);
+    /// assert_eq!(bst.serialize_shape(), vec![Some(3), Some(2), Some(4), Some(1)]);
+    /// ```
+    pub fn serialize_shape(&self) -> Vec<Option<T>> {
+        self.tree
+            .root_id()
+            .map_or_else(Vec::new, |root_id| self.tree.to_level_order(root_id))
+    }
+
+    /// Rebuild a BST from the output of [`BST::serialize_shape`]
+    ///
+    /// Elements are placed exactly where the level-order slots say, without
+    /// rebalancing, so the result has the same shape as the serialized BST.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TreeError::NotSearchOrder`] if the placed elements are not
+    /// in strictly increasing order from left to right, which includes
+    /// duplicate elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::{BST, TreeError};
+    ///
+    /// let mut bst = BST::new();
+    /// for x in [1, 2, 3] {
+    ///     bst.insert(x);
+    /// }
+    /// let copy = BST::deserialize_shape(bst.serialize_shape()).unwrap();
+    /// assert_eq!(copy.height(), 3);
+    /// assert_eq!(BST::from_sorted([1, 2, 3]).height(), 2);
+    ///
+    /// assert_eq!(
+    ///     BST::deserialize_shape(vec![Some(1), Some(2)]).unwrap_err(),
+    ///     TreeError::NotSearchOrder
+    /// );
+    /// ```
+    pub fn deserialize_shape(values: Vec<Option<T>>) -> Result<Self, TreeError> {
+        let mut bst = Self {
+            tree: Tree::from_level_order(values),
+            heights: HashMap::new(),
+            heights_stale: true,
+        };
+        if !bst.inorder().is_sorted_by(|a, b| a.value < b.value) {
+            return Err(TreeError::NotSearchOrder);
+        }
+        bst.refresh_heights();
+        Ok(bst)
     }
 
     /// Search for an element in the BST
@@ -229,6 +475,12 @@ impl<T: Ord + Clone> BST<T> {
 
     /// Delete an element from the BST
     ///
+    /// Returns the removed element, or `None` if it was not in the BST, like
+    /// `BTreeMap::remove`. When the element's node has two children, the
+    /// in-order successor's value is moved into that node and the
+    /// successor's node is removed instead; the element itself is still
+    /// returned.
+    ///
     /// # Examples
     ///
     /// ```
@@ -241,25 +493,27 @@ impl<T: Ord + Clone> BST<T> {
     /// bst.insert(7);
     ///
     /// assert_eq!(bst.size(), 3);
-    /// bst.delete(&3);
+    /// assert_eq!(bst.delete(&3), Some(3));
     /// assert_eq!(bst.size(), 2);
     /// assert!(!bst.contains(&3));
+    /// assert_eq!(bst.delete(&3), None);
     /// ```
-    pub fn delete(&mut self, element: &T) {
-        if let Some(node_id) = self.search(element) {
-            self.delete_node(node_id);
-        }
+    pub fn delete(&mut self, element: &T) -> Option<T> {
+        let node_id = self.search(element)?;
+        self.delete_node(node_id)
     }
 
-    fn delete_node(&mut self, node_id: Number) {
+    fn delete_node(&mut self, node_id: Number) -> Option<T> {
+        self.refresh_heights();
+
         // First, get all the information we need from the node
         let node_info = if let Some(node) = self.tree.get_node(node_id) {
-            (node.left(), node.right(), node.parent(), node.value.clone())
+            (node.left(), node.right(), node.parent())
         } else {
-            return;
+            return None;
         };
 
-        let (has_left, has_right, parent_id, _node_value) = node_info;
+        let (has_left, has_right, parent_id) = node_info;
         let has_left = has_left.is_some();
         let has_right = has_right.is_some();
 
@@ -279,7 +533,7 @@ impl<T: Ord + Clone> BST<T> {
                     // This is the root node, clear the root
                     self.tree.set_root_id(None);
                 }
-                self.tree.remove_node(node_id);
+                self.remove_spliced(node_id, parent_id)
             }
             (true, false) => {
                 // Node with only left child
@@ -291,6 +545,8 @@ impl<T: Ord + Clone> BST<T> {
                         } else if parent.right() == Some(node_id) {
                             parent.set_right(left_id);
                         }
+                        parent.remove_child(node_id);
+                        parent.add_child(left_id);
                     }
                 } else {
                     // This is the root node
@@ -303,7 +559,7 @@ impl<T: Ord + Clone> BST<T> {
                         left.remove_parent();
                     }
                 }
-                self.tree.remove_node(node_id);
+                self.remove_spliced(node_id, parent_id)
             }
             (false, true) => {
                 // Node with only right child
@@ -315,6 +571,8 @@ impl<T: Ord + Clone> BST<T> {
                         } else if parent.right() == Some(node_id) {
                             parent.set_right(right_id);
                         }
+                        parent.remove_child(node_id);
+                        parent.add_child(right_id);
                     }
                 } else {
                     // This is the root node
@@ -327,23 +585,132 @@ impl<T: Ord + Clone> BST<T> {
                         right.remove_parent();
                     }
                 }
-                self.tree.remove_node(node_id);
+                self.remove_spliced(node_id, parent_id)
             }
             (true, true) => {
                 // Node with two children
                 let right_id = node_info.1.unwrap();
                 let successor_id = self.find_min(right_id);
-                if let Some(successor) = self.tree.get_node(successor_id) {
-                    let successor_value = successor.value.clone();
-                    self.delete_node(successor_id);
-                    if let Some(node) = self.tree.get_node_mut(node_id) {
-                        node.value = successor_value;
-                    }
+                let successor_value = self.delete_node(successor_id)?;
+                let node = self.tree.get_node_mut(node_id)?;
+                Some(std::mem::replace(&mut node.value, successor_value))
+            }
+        }
+    }
+
+    /// Remove a node already unlinked from the BST and fix heights above it
+    fn remove_spliced(&mut self, node_id: Number, parent_id: Option<Number>) -> Option<T> {
+        self.heights.remove(&FloatId::from(node_id));
+        self.update_heights_from(parent_id);
+        self.tree.remove_node(node_id).map(|node| node.value)
+    }
+
+    /// Get the cached height of a subtree, with no subtree having height 0
+    fn cached_height(&self, node_id: Option<Number>) -> usize {
+        node_id
+            .and_then(|id| self.heights.get(&FloatId::from(id)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Recompute cached heights from `node_id` up towards the root
+    ///
+    /// Stops early once a node's height is unchanged, since nothing above
+    /// it can change either.
+    fn update_heights_from(&mut self, mut node_id: Option<Number>) {
+        while let Some(id) = node_id {
+            let Some(node) = self.tree.get_node(id) else {
+                return;
+            };
+            let height = 1 + self
+                .cached_height(node.left())
+                .max(self.cached_height(node.right()));
+            if self.heights.insert(FloatId::from(id), height) == Some(height) {
+                return;
+            }
+            node_id = node.parent();
+        }
+    }
+
+    /// Rebuild every cached height if mutable access may have changed links
+    fn refresh_heights(&mut self) {
+        if !self.heights_stale {
+            return;
+        }
+        self.heights.clear();
+        if let Some(root_id) = self.tree.root_id() {
+            self.fill_heights(root_id);
+        }
+        self.heights_stale = false;
+    }
+
+    /// Cache the height of every node under `node_id`, children first
+    fn fill_heights(&mut self, node_id: Number) {
+        let mut expanded = HashSet::new();
+        let mut stack = vec![(node_id, false)];
+        while let Some((id, children_done)) = stack.pop() {
+            let Some(node) = self.tree.get_node(id) else {
+                continue;
+            };
+            let (left_id, right_id) = (node.left(), node.right());
+            if !children_done {
+                // A node seen before was reached again through a cycle
+                if !expanded.insert(FloatId::from(id)) {
+                    continue;
                 }
+                stack.push((id, true));
+                stack.extend(left_id.into_iter().chain(right_id).map(|id| (id, false)));
+                continue;
             }
+            let height = 1 + self
+                .cached_height(left_id)
+                .max(self.cached_height(right_id));
+            self.heights.insert(FloatId::from(id), height);
         }
     }
 
+    /// Remove and return the smallest element
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// for x in [5, 3, 8] {
+    ///     bst.insert(x);
+    /// }
+    ///
+    /// assert_eq!(bst.pop_min(), Some(3));
+    /// assert_eq!(bst.pop_min(), Some(5));
+    /// assert_eq!(bst.pop_min(), Some(8));
+    /// assert_eq!(bst.pop_min(), None);
+    /// ```
+    pub fn pop_min(&mut self) -> Option<T> {
+        let min_id = self.find_min(self.tree.root_id()?);
+        self.delete_node(min_id)
+    }
+
+    /// Remove and return the largest element
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// for x in [5, 3, 8] {
+    ///     bst.insert(x);
+    /// }
+    ///
+    /// assert_eq!(bst.pop_max(), Some(8));
+    /// assert_eq!(bst.max(), Some(&5));
+    /// ```
+    pub fn pop_max(&mut self) -> Option<T> {
+        let max_id = self.find_max(self.tree.root_id()?);
+        self.delete_node(max_id)
+    }
+
     fn find_min(&self, node_id: Number) -> Number {
         if let Some(node) = self.tree.get_node(node_id) {
             if let Some(left_id) = node.left() {
@@ -391,6 +758,159 @@ impl<T: Ord + Clone> BST<T> {
         }
     }
 
+    /// Get the boundary of the BST, counter-clockwise from the root
+    ///
+    /// The boundary is the root, then the left boundary top-down, then
+    /// every leaf from left to right, then the right boundary bottom-up.
+    /// The left boundary is the path that starts at the root's left child
+    /// and steps to the left child where there is one and to the right
+    /// child otherwise; the right boundary mirrors it. Leaves are left off
+    /// both boundaries so each node appears once. A root with no left
+    /// child has no left boundary (likewise on the right), and a
+    /// single-node BST's boundary is just the root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// //         8
+    /// //       /   \
+    /// //      4     12
+    /// //     / \      \
+    /// //    2   6      14
+    /// //       /      /
+    /// //      5      13
+    /// let mut bst = BST::new();
+    /// for x in [8, 4, 12, 2, 6, 14, 5, 13] {
+    ///     bst.insert(x);
+    /// }
+    ///
+    /// assert_eq!(bst.boundary(), vec![&8, &4, &2, &5, &13, &14, &12]);
+    /// ```
+    pub fn boundary(&self) -> Vec<&T> {
+        let Some(root) = self.tree.root_id().and_then(|id| self.tree.get_node(id)) else {
+            return Vec::new();
+        };
+        let mut boundary = vec![&root.value];
+        if root.left().is_none() && root.right().is_none() {
+            return boundary;
+        }
+
+        let mut current = root.left().and_then(|id| self.tree.get_node(id));
+        while let Some(node) = current.filter(|node| !Self::is_binary_leaf(node)) {
+            boundary.push(&node.value);
+            current = node
+                .left()
+                .or(node.right())
+                .and_then(|id| self.tree.get_node(id));
+        }
+
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if Self::is_binary_leaf(node) {
+                boundary.push(&node.value);
+            }
+            for child_id in [node.right(), node.left()].into_iter().flatten() {
+                stack.extend(self.tree.get_node(child_id));
+            }
+        }
+
+        let mut right_boundary = Vec::new();
+        let mut current = root.right().and_then(|id| self.tree.get_node(id));
+        while let Some(node) = current.filter(|node| !Self::is_binary_leaf(node)) {
+            right_boundary.push(&node.value);
+            current = node
+                .right()
+                .or(node.left())
+                .and_then(|id| self.tree.get_node(id));
+        }
+        boundary.extend(right_boundary.into_iter().rev());
+        boundary
+    }
+
+    /// Group the elements into columns by horizontal distance from the root
+    ///
+    /// The root is in column 0, and each step to a left child moves one
+    /// column left while each step to a right child moves one column right.
+    /// Columns are returned from leftmost to rightmost, and each column
+    /// lists its elements from top to bottom. Elements in the same row and
+    /// column are ordered by value, smallest first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// //        8
+    /// //      /   \
+    /// //     4     12
+    /// //    / \   /  \
+    /// //   2   6 10   14
+    /// let bst = BST::from_sorted([2, 4, 6, 8, 10, 12, 14]);
+    ///
+    /// assert_eq!(
+    ///     bst.vertical_order(),
+    ///     vec![vec![&2], vec![&4], vec![&8, &6, &10], vec![&12], vec![&14]]
+    /// );
+    /// ```
+    pub fn vertical_order(&self) -> Vec<Vec<&T>> {
+        let mut columns: BTreeMap<i64, Vec<(usize, &T)>> = BTreeMap::new();
+        let mut stack: Vec<(Number, i64, usize)> = self
+            .tree
+            .root_id()
+            .map(|id| (id, 0, 0))
+            .into_iter()
+            .collect();
+
+        while let Some((node_id, column, row)) = stack.pop() {
+            let Some(node) = self.tree.get_node(node_id) else {
+                continue;
+            };
+            columns.entry(column).or_default().push((row, &node.value));
+            if let Some(left_id) = node.left() {
+                stack.push((left_id, column - 1, row + 1));
+            }
+            if let Some(right_id) = node.right() {
+                stack.push((right_id, column + 1, row + 1));
+            }
+        }
+
+        columns
+            .into_values()
+            .map(|mut column| {
+                column.sort_unstable();
+                column.into_iter().map(|(_, value)| value).collect()
+            })
+            .collect()
+    }
+
+    fn is_binary_leaf(node: &Node<T>) -> bool {
+        node.left().is_none() && node.right().is_none()
+    }
+
+    /// Consume the BST and return its elements in sorted order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// bst.insert(5);
+    /// bst.insert(3);
+    /// bst.insert(7);
+    ///
+    /// assert_eq!(bst.into_sorted_vec(), vec![3, 5, 7]);
+    /// ```
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let ids: Vec<Number> = self.inorder().iter().map(|node| node.id).collect();
+        ids.into_iter()
+            .filter_map(|id| self.tree.remove_node(id))
+            .map(|node| node.value)
+            .collect()
+    }
+
     /// Get the minimum element in the BST
     ///
     /// # Examples
@@ -449,6 +969,63 @@ impl<T: Ord + Clone> BST<T> {
         }
     }
 
+    /// Get the second smallest element in the BST
+    ///
+    /// This is the in-order successor of the minimum: the smallest element
+    /// of the minimum's right subtree if it has one, and otherwise its
+    /// parent. The BST holds no duplicates, so the result is always strictly
+    /// greater than [`BST::min`]. Returns `None` with fewer than two
+    /// elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// bst.insert(5);
+    /// assert_eq!(bst.second_min(), None);
+    ///
+    /// for x in [5, 3, 8, 4] {
+    ///     bst.insert(x);
+    /// }
+    /// assert_eq!(bst.second_min(), Some(&4));
+    /// ```
+    pub fn second_min(&self) -> Option<&T> {
+        let min = self.tree.get_node(self.find_min(self.tree.root_id()?))?;
+        let second_id = match min.right() {
+            Some(right_id) => self.find_min(right_id),
+            None => min.parent()?,
+        };
+        self.tree.get_node(second_id).map(|n| &n.value)
+    }
+
+    /// Get the second largest element in the BST
+    ///
+    /// The mirror image of [`BST::second_min`]: the in-order predecessor of
+    /// the maximum, strictly less than [`BST::max`]. Returns `None` with
+    /// fewer than two elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// for x in [5, 3, 8, 7, 8] {
+    ///     bst.insert(x);
+    /// }
+    /// assert_eq!(bst.second_max(), Some(&7));
+    /// ```
+    pub fn second_max(&self) -> Option<&T> {
+        let max = self.tree.get_node(self.find_max(self.tree.root_id()?))?;
+        let second_id = match max.left() {
+            Some(left_id) => self.find_max(left_id),
+            None => max.parent()?,
+        };
+        self.tree.get_node(second_id).map(|n| &n.value)
+    }
+
     /// Check if the BST contains a given element
     ///
     /// # Examples
@@ -488,6 +1065,10 @@ impl<T: Ord + Clone> BST<T> {
 
     /// Get the height of the BST
     ///
+    /// The height is the number of nodes on the longest root-to-leaf path.
+    /// It is read from the cached subtree heights in O(1), or recounted in
+    /// O(n) while the cache is stale (see [`BST`]).
+    ///
     /// # Examples
     ///
     /// ```
@@ -504,10 +1085,9 @@ impl<T: Ord + Clone> BST<T> {
     /// assert_eq!(bst.height(), 2);
     /// ```
     pub fn height(&self) -> usize {
-        if let Some(root_id) = self.tree.root_id() {
-            self.bst_height_recursive(root_id)
-        } else {
-            0
+        match self.tree.root_id() {
+            Some(root_id) if self.heights_stale => self.bst_height_recursive(root_id),
+            root_id => self.cached_height(root_id),
         }
     }
 
@@ -659,6 +1239,8 @@ impl<T: Ord + Clone> BST<T> {
 
     /// Get a mutable reference to a node by its ID
     ///
+    /// The cached subtree heights are marked stale, as described on [`BST`].
+    ///
     /// # Examples
     ///
     /// ```
@@ -673,6 +1255,7 @@ impl<T: Ord + Clone> BST<T> {
     /// }
     /// ```
     pub fn get_node_mut(&mut self, id: Number) -> Option<&mut Node<T>> {
+        self.heights_stale = true;
         self.tree.get_node_mut(id)
     }
 
@@ -715,39 +1298,584 @@ impl<T: Ord + Clone> BST<T> {
     }
 }
 
-// BST provides its own focused API for binary search tree operations
-// Generic tree functionality is available through as_tree() when needed
-impl<T: Ord + Clone> Default for BST<T> {
-    /// Create a new empty BST using the default implementation
+impl<T: Ord + Clone + Sub<Output = T>> BST<T> {
+    /// Get the element closest to `target`
+    ///
+    /// The distance between two elements is the larger minus the smaller,
+    /// so unsigned types never underflow. Ties go to the smaller element.
+    /// Descends a single root-to-leaf path, like a combined floor/ceiling.
     ///
     /// # Examples
     ///
     /// ```
     /// use jangal::BST;
     ///
-    /// let bst: BST<i32> = BST::default();
-    /// assert!(bst.is_empty());
-    /// assert_eq!(bst.size(), 0);
-    /// ```
-    fn default() -> Self {
-        Self::new()
+    /// let mut bst = BST::new();
+    /// for x in [10, 4, 17, 1] {
+    ///     bst.insert(x);
+    /// }
+    ///
+    /// assert_eq!(bst.nearest(&15), Some(&17));
+    /// assert_eq!(bst.nearest(&7), Some(&4));
+    /// assert_eq!(bst.nearest(&100), Some(&17));
+    /// ```
+    pub fn nearest(&self, target: &T) -> Option<&T> {
+        let mut best: Option<(&T, T)> = None;
+        let mut current = self.tree.root_id();
+        while let Some(node_id) = current {
+            let node = self.tree.get_node(node_id)?;
+            let distance = Self::distance(&node.value, target);
+            let closer = match &best {
+                Some((best_value, best_distance)) => {
+                    distance < *best_distance
+                        || (distance == *best_distance && node.value < **best_value)
+                }
+                None => true,
+            };
+            if closer {
+                best = Some((&node.value, distance));
+            }
+            current = match target.cmp(&node.value) {
+                std::cmp::Ordering::Less => node.left(),
+                std::cmp::Ordering::Greater => node.right(),
+                std::cmp::Ordering::Equal => None,
+            };
+        }
+        best.map(|(value, _)| value)
     }
-}
 
-/// A van Emde Boas tree implementation
-///
-/// This vEB tree provides efficient operations on integers from 0 to u-1
-/// where u is a power of 2. It inherits all tree functionality from the core Tree type.
-///
-/// # Examples
-///
-/// ```
-/// use jangal::vEB;
-/// use jangal::TreeLike;
-///
-/// let mut veb = vEB::new(8);
-/// veb.insert(3);
-/// veb.insert(5);
+    /// Get the `k` elements closest to `target`, nearest first
+    ///
+    /// Uses the same distance and tie-breaking as [`BST::nearest`]. Returns
+    /// fewer than `k` elements if the BST is smaller than that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// for x in [10, 4, 17, 1, 12] {
+    ///     bst.insert(x);
+    /// }
+    ///
+    /// assert_eq!(bst.k_nearest(&11, 3), vec![&10, &12, &17]);
+    /// assert_eq!(bst.k_nearest(&0, 10).len(), 5);
+    /// ```
+    pub fn k_nearest(&self, target: &T, k: usize) -> Vec<&T> {
+        let sorted: Vec<&T> = self.inorder().into_iter().map(|n| &n.value).collect();
+        let split = sorted.partition_point(|value| *value < target);
+        let (mut below, mut above) = (split, split);
+        let mut result = Vec::with_capacity(k.min(sorted.len()));
+        while result.len() < k {
+            let take_below = match (below.checked_sub(1), sorted.get(above)) {
+                (Some(lo), Some(hi)) => {
+                    Self::distance(sorted[lo], target) <= Self::distance(hi, target)
+                }
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            if take_below {
+                below -= 1;
+                result.push(sorted[below]);
+            } else {
+                result.push(sorted[above]);
+                above += 1;
+            }
+        }
+        result
+    }
+
+    fn distance(a: &T, b: &T) -> T {
+        if a < b {
+            b.clone() - a.clone()
+        } else {
+            a.clone() - b.clone()
+        }
+    }
+}
+
+impl BST<usize> {
+    /// Build a vEB tree holding the same elements as this BST
+    ///
+    /// The universe size is the smallest power of two greater than the
+    /// largest element (and at least 2), so every element fits. Returns
+    /// `None` if that power of two does not fit in a `usize`, which happens
+    /// once the largest element is above `usize::MAX / 2`.
+    ///
+    /// [`vEB::new`] allocates its whole cluster structure up front, so
+    /// memory use grows with the universe size, not with the number of
+    /// elements. A BST holding just a few values near a billion produces a
+    /// universe of 2^30 and allocates accordingly, so check [`BST::max`]
+    /// before converting BSTs with large values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let mut bst = BST::new();
+    /// bst.insert(5);
+    /// bst.insert(3);
+    /// bst.insert(8);
+    ///
+    /// let veb = bst.to_veb().unwrap();
+    /// assert_eq!(veb.universe_size(), 16);
+    /// assert_eq!(veb.size(), 3);
+    /// assert!(veb.contains(&5));
+    ///
+    /// bst.insert(usize::MAX);
+    /// assert!(bst.to_veb().is_none());
+    /// ```
+    pub fn to_veb(&self) -> Option<vEB> {
+        let universe_size = match self.max() {
+            Some(&max) => max.checked_add(1)?.checked_next_power_of_two()?.max(2),
+            None => 2,
+        };
+        let mut veb = vEB::new(universe_size);
+        for node in self.inorder() {
+            veb.insert(node.value);
+        }
+        Some(veb)
+    }
+}
+
+// BST provides its own focused API for binary search tree operations
+// Generic tree functionality is available through as_tree() when needed
+impl<T: Ord + Clone> Default for BST<T> {
+    /// Create a new empty BST using the default implementation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::BST;
+    ///
+    /// let bst: BST<i32> = BST::default();
+    /// assert!(bst.is_empty());
+    /// assert_eq!(bst.size(), 0);
+    /// ```
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A Binary Search Tree that keeps duplicates as per-node counts
+///
+/// Each distinct value is stored once in an underlying [`BST`], and each of
+/// its nodes carries a count of how many times that value has been
+/// inserted. `insert` increments the count, `delete` decrements it and
+/// removes the node once it reaches zero, and `size` reports the total
+/// number of elements including duplicates. All lookups go through the
+/// tree.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::MultiBST;
+///
+/// let mut bst = MultiBST::new();
+/// bst.insert(5);
+/// bst.insert(5);
+/// bst.insert(3);
+///
+/// assert_eq!(bst.count(&5), 2);
+/// assert_eq!(bst.size(), 3);
+/// assert_eq!(bst.distinct_size(), 2);
+///
+/// bst.delete(&5);
+/// assert_eq!(bst.count(&5), 1);
+/// ```
+#[derive(Debug)]
+pub struct MultiBST<T: Ord + Clone> {
+    bst: BST<T>,
+    counts: HashMap<FloatId, usize>,
+    total: usize,
+}
+
+impl<T: Ord + Clone> MultiBST<T> {
+    /// Create a new empty multiset BST
+    pub fn new() -> Self {
+        Self {
+            bst: BST::new(),
+            counts: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    /// Get a reference to the underlying BST of distinct values
+    pub fn as_bst(&self) -> &BST<T> {
+        &self.bst
+    }
+
+    /// Insert an element, incrementing its count if it is already present
+    pub fn insert(&mut self, element: T) {
+        if let Some(node_id) = self.bst.insert_or_find(element) {
+            *self.counts.entry(FloatId::from(node_id)).or_insert(0) += 1;
+            self.total += 1;
+        }
+    }
+
+    /// Remove one occurrence of an element
+    ///
+    /// The element's node is removed once its count reaches zero. Deleting
+    /// an absent element does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::MultiBST;
+    ///
+    /// let mut bst = MultiBST::new();
+    /// bst.insert(7);
+    /// bst.insert(7);
+    ///
+    /// bst.delete(&7);
+    /// assert!(bst.contains(&7));
+    /// bst.delete(&7);
+    /// assert!(!bst.contains(&7));
+    /// assert!(bst.as_bst().is_empty());
+    /// ```
+    pub fn delete(&mut self, element: &T) {
+        let Some(node_id) = self.bst.search(element) else {
+            return;
+        };
+        let id = FloatId::from(node_id);
+        let Some(count) = self.counts.get_mut(&id) else {
+            return;
+        };
+        *count -= 1;
+        self.total -= 1;
+        if *count > 0 {
+            return;
+        }
+
+        // A node with two children takes over its in-order successor's
+        // value, so it must take over the successor's count too
+        self.counts.remove(&id);
+        let successor_id = self
+            .bst
+            .get_node(node_id)
+            .filter(|node| node.left().is_some())
+            .and_then(|node| node.right())
+            .map(|right_id| self.bst.find_min(right_id));
+        self.bst.delete_node(node_id);
+        if let Some(successor_id) = successor_id {
+            if let Some(count) = self.counts.remove(&FloatId::from(successor_id)) {
+                self.counts.insert(id, count);
+            }
+        }
+    }
+
+    /// Get the number of times an element has been inserted
+    pub fn count(&self, element: &T) -> usize {
+        self.bst
+            .search(element)
+            .and_then(|node_id| self.counts.get(&FloatId::from(node_id)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Get the count stored on the node with the given ID
+    ///
+    /// Returns 0 if the underlying BST has no such node.
+    pub fn node_count(&self, node_id: Number) -> usize {
+        self.counts
+            .get(&FloatId::from(node_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Check if the BST contains at least one occurrence of an element
+    pub fn contains(&self, element: &T) -> bool {
+        self.bst.contains(element)
+    }
+
+    /// Get the total number of elements, counting duplicates
+    pub fn size(&self) -> usize {
+        self.total
+    }
+
+    /// Get the number of distinct elements
+    pub fn distinct_size(&self) -> usize {
+        self.bst.size()
+    }
+
+    /// Check if the BST is empty
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Consume the BST and return every element in sorted order
+    ///
+    /// Each element appears as many times as its count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::MultiBST;
+    ///
+    /// let mut bst = MultiBST::new();
+    /// for x in [4, 1, 4, 2, 1, 4] {
+    ///     bst.insert(x);
+    /// }
+    /// assert_eq!(bst.into_sorted_vec(), vec![1, 1, 2, 4, 4, 4]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        let counts: Vec<usize> = self
+            .bst
+            .inorder()
+            .iter()
+            .map(|node| self.node_count(node.id))
+            .collect();
+        self.bst
+            .into_sorted_vec()
+            .into_iter()
+            .zip(counts)
+            .flat_map(|(value, count)| std::iter::repeat_n(value, count))
+            .collect()
+    }
+}
+
+impl<T: Ord + Clone> Default for MultiBST<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A Binary Search Tree ordered by a key extracted from each element
+///
+/// Elements are stored whole, but ordered and looked up by the key returned
+/// from the extractor passed to [`KeyedBST::new_by_key`], so the element
+/// type itself does not need to implement `Ord`. As with [`BST`], inserting
+/// an element whose key is already present does nothing.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::KeyedBST;
+///
+/// struct Task {
+///     priority: u32,
+///     name: &'static str,
+/// }
+///
+/// let mut tasks = KeyedBST::new_by_key(|task: &Task| task.priority);
+/// tasks.insert(Task { priority: 2, name: "write" });
+/// tasks.insert(Task { priority: 1, name: "plan" });
+/// tasks.insert(Task { priority: 3, name: "ship" });
+///
+/// assert_eq!(tasks.get_by_key(&1).unwrap().name, "plan");
+/// assert!(tasks.search_by_key(&4).is_none());
+///
+/// let names: Vec<&str> = tasks.inorder().iter().map(|node| node.value.name).collect();
+/// assert_eq!(names, vec!["plan", "write", "ship"]);
+/// ```
+pub struct KeyedBST<T, K: Ord> {
+    tree: Tree<T>,
+    key: Box<dyn Fn(&T) -> K>,
+}
+
+impl<T, K: Ord> KeyedBST<T, K> {
+    /// Create a new empty BST ordered by the key `key` extracts
+    pub fn new_by_key<F>(key: F) -> Self
+    where
+        F: Fn(&T) -> K + 'static,
+    {
+        Self {
+            tree: Tree::new(),
+            key: Box::new(key),
+        }
+    }
+
+    /// Get a reference to the underlying tree structure
+    pub fn as_tree(&self) -> &Tree<T> {
+        &self.tree
+    }
+
+    /// Insert an element, ordered by its key
+    ///
+    /// Returns `false` (and drops the element) if an element with the same
+    /// key is already present.
+    pub fn insert(&mut self, element: T) -> bool {
+        let element_key = (self.key)(&element);
+        let mut parent = None;
+        let mut current = self.tree.root_id();
+        while let Some(id) = current {
+            let node = &self.tree[id];
+            let ordering = element_key.cmp(&(self.key)(&node.value));
+            parent = Some((id, ordering == std::cmp::Ordering::Less));
+            current = match ordering {
+                std::cmp::Ordering::Less => node.left(),
+                std::cmp::Ordering::Greater => node.right(),
+                std::cmp::Ordering::Equal => return false,
+            };
+        }
+
+        let Some(new_id) = self.tree.add_node(Node::new(element)) else {
+            return false;
+        };
+        match parent {
+            Some((parent_id, is_left)) => {
+                if let Some(parent) = self.tree.get_node_mut(parent_id) {
+                    if is_left {
+                        parent.set_left(new_id);
+                    } else {
+                        parent.set_right(new_id);
+                    }
+                    parent.add_child(new_id);
+                }
+                if let Some(child) = self.tree.get_node_mut(new_id) {
+                    child.set_parent(parent_id);
+                }
+            }
+            None => {
+                self.tree.set_root(new_id);
+            }
+        }
+        true
+    }
+
+    /// Find the ID of the node whose element has the given key
+    pub fn search_by_key(&self, key: &K) -> Option<Number> {
+        let mut current = self.tree.root_id();
+        while let Some(id) = current {
+            let node = self.tree.get_node(id)?;
+            current = match key.cmp(&(self.key)(&node.value)) {
+                std::cmp::Ordering::Less => node.left(),
+                std::cmp::Ordering::Greater => node.right(),
+                std::cmp::Ordering::Equal => return Some(id),
+            };
+        }
+        None
+    }
+
+    /// Get the element with the given key
+    pub fn get_by_key(&self, key: &K) -> Option<&T> {
+        self.search_by_key(key)
+            .and_then(|id| self.tree.get_node(id))
+            .map(|node| &node.value)
+    }
+
+    /// Remove and return the element with the given key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::KeyedBST;
+    ///
+    /// let mut words = KeyedBST::new_by_key(|word: &String| word.len());
+    /// words.insert("tree".to_string());
+    /// words.insert("forest".to_string());
+    ///
+    /// assert_eq!(words.delete_by_key(&4), Some("tree".to_string()));
+    /// assert_eq!(words.delete_by_key(&4), None);
+    /// assert_eq!(words.size(), 1);
+    /// ```
+    pub fn delete_by_key(&mut self, key: &K) -> Option<T> {
+        let id = self.search_by_key(key)?;
+        let node = self.tree.get_node(id)?;
+        match (node.left(), node.right()) {
+            (Some(_), Some(right_id)) => {
+                let mut successor_id = right_id;
+                while let Some(left_id) = self.tree.get_node(successor_id)?.left() {
+                    successor_id = left_id;
+                }
+                let successor = self.splice_out(successor_id)?;
+                let node = self.tree.get_node_mut(id)?;
+                Some(std::mem::replace(&mut node.value, successor.value))
+            }
+            _ => self.splice_out(id).map(|node| node.value),
+        }
+    }
+
+    /// Remove a node with at most one child, linking that child to its parent
+    fn splice_out(&mut self, id: Number) -> Option<Node<T>> {
+        let node = self.tree.get_node(id)?;
+        let child_id = node.left().or(node.right());
+        let parent_id = node.parent();
+
+        match parent_id {
+            Some(parent_id) => {
+                if let Some(parent) = self.tree.get_node_mut(parent_id) {
+                    let was_left = parent.left() == Some(id);
+                    parent.remove_child(id);
+                    match (child_id, was_left) {
+                        (Some(child_id), true) => parent.set_left(child_id),
+                        (Some(child_id), false) => parent.set_right(child_id),
+                        (None, true) => parent.clear_left(),
+                        (None, false) => parent.clear_right(),
+                    }
+                    if let Some(child_id) = child_id {
+                        parent.add_child(child_id);
+                    }
+                }
+            }
+            None => self.tree.set_root_id(child_id.map(Into::into)),
+        }
+        if let Some(child) = child_id.and_then(|child_id| self.tree.get_node_mut(child_id)) {
+            match parent_id {
+                Some(parent_id) => child.set_parent(parent_id),
+                None => child.remove_parent(),
+            }
+        }
+        self.tree.remove_node(id)
+    }
+
+    /// Perform an inorder traversal, visiting elements in key order
+    pub fn inorder(&self) -> Vec<&Node<T>> {
+        let mut result = Vec::new();
+        if let Some(root_id) = self.tree.root_id() {
+            self.inorder_recursive(root_id, &mut result);
+        }
+        result
+    }
+
+    fn inorder_recursive<'a>(&'a self, node_id: Number, result: &mut Vec<&'a Node<T>>) {
+        if let Some(node) = self.tree.get_node(node_id) {
+            if let Some(left_id) = node.left() {
+                self.inorder_recursive(left_id, result);
+            }
+            result.push(node);
+            if let Some(right_id) = node.right() {
+                self.inorder_recursive(right_id, result);
+            }
+        }
+    }
+
+    /// Get the number of elements
+    pub fn size(&self) -> usize {
+        self.tree.size()
+    }
+
+    /// Check if the BST is empty
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
+
+impl<T: std::fmt::Debug, K: Ord> std::fmt::Debug for KeyedBST<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyedBST")
+            .field("tree", &self.tree)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A van Emde Boas tree implementation
+///
+/// This vEB tree provides efficient operations on integers from 0 to u-1
+/// where u is a power of 2. It inherits all tree functionality from the core Tree type.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::vEB;
+/// use jangal::TreeLike;
+///
+/// let mut veb = vEB::new(8);
+/// veb.insert(3);
+/// veb.insert(5);
 /// veb.insert(7);
 ///
 /// assert_eq!(veb.size(), 3);
@@ -896,24 +2024,27 @@ impl vEB {
             );
         }
 
+        if self.contains(&x) {
+            return;
+        }
+
         if self.min.is_none() {
             self.min = Some(x);
             self.max = Some(x);
             self.element_count = 1;
         } else {
+            // The min is kept out of the clusters, so a new min displaces the
+            // old one, which is then stored in the clusters in its place
+            let mut x = x;
             if x < self.min.unwrap() {
-                let old_min = self.min.unwrap();
-                self.min = Some(x);
-                if self.universe_size > 2 {
-                    self.insert_recursive(old_min);
-                }
-            }
-            if x > self.max.unwrap() {
-                self.max = Some(x);
+                x = self.min.replace(x).unwrap();
             }
             if self.universe_size > 2 {
                 self.insert_recursive(x);
             }
+            if x > self.max.unwrap() {
+                self.max = Some(x);
+            }
             self.element_count += 1;
         }
     }
@@ -1006,7 +2137,7 @@ impl vEB {
     /// assert!(!veb.contains(&3));
     /// ```
     pub fn delete(&mut self, x: &usize) {
-        if *x >= self.universe_size {
+        if !self.contains(x) {
             return;
         }
 
@@ -1202,6 +2333,11 @@ impl vEB {
 
     /// Find the successor of an element
     ///
+    /// The successor is the smallest element strictly greater than `x`, so
+    /// `x` itself is never returned even if it is present; use
+    /// [`vEB::first_at_least`] for the inclusive query. Returns `None` if
+    /// there is no such element or if `x` is outside the universe.
+    ///
     /// # Arguments
     ///
     /// * `x` - The element to find the successor of
@@ -1259,6 +2395,10 @@ impl vEB {
 
     /// Find the predecessor of an element
     ///
+    /// The predecessor is the largest element strictly smaller than `x`.
+    /// Returns `None` if there is no such element or if `x` is outside the
+    /// universe.
+    ///
     /// # Arguments
     ///
     /// * `x` - The element to find the predecessor of
@@ -1303,17 +2443,251 @@ impl vEB {
                 }
             }
 
-            let pred_cluster = self.summary.as_ref().unwrap().predecessor(&high_x);
-            if let Some(pred_cluster_val) = pred_cluster {
-                let offset = self.clusters[pred_cluster_val].as_ref().unwrap().max;
-                if let Some(offset_val) = offset {
-                    return Some(self.index(pred_cluster_val, offset_val));
+            let pred_cluster = self.summary.as_ref().unwrap().predecessor(&high_x);
+            if let Some(pred_cluster_val) = pred_cluster {
+                let offset = self.clusters[pred_cluster_val].as_ref().unwrap().max;
+                if let Some(offset_val) = offset {
+                    return Some(self.index(pred_cluster_val, offset_val));
+                }
+            } else if self.min.is_some() && *x > self.min.unwrap() {
+                return self.min;
+            }
+        }
+        None
+    }
+
+    /// Find the successor of each query point
+    ///
+    /// Equivalent to calling [`vEB::successor`] on every element of `xs`, so
+    /// queries outside the universe yield `None`. When consecutive queries
+    /// are non-decreasing, a query that falls below the previous answer
+    /// reuses it without another lookup, making sorted batches cheaper.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(16);
+    /// veb.insert(3);
+    /// veb.insert(9);
+    ///
+    /// assert_eq!(
+    ///     veb.successors(&[0, 2, 3, 8, 9, 100]),
+    ///     vec![Some(3), Some(3), Some(9), Some(9), None, None]
+    /// );
+    /// ```
+    pub fn successors(&self, xs: &[usize]) -> Vec<Option<usize>> {
+        let mut previous: Option<(usize, Option<usize>)> = None;
+        xs.iter()
+            .map(|&x| {
+                if x >= self.universe_size {
+                    return None;
+                }
+                let answer = match previous {
+                    Some((prev_x, prev)) if x >= prev_x && prev.is_none_or(|s| x < s) => prev,
+                    _ => self.successor(&x),
+                };
+                previous = Some((x, answer));
+                answer
+            })
+            .collect()
+    }
+
+    /// Find the predecessor of each query point
+    ///
+    /// Equivalent to calling [`vEB::predecessor`] on every element of `xs`,
+    /// so queries outside the universe yield `None`. When consecutive
+    /// queries are non-increasing, a query that stays above the previous
+    /// answer reuses it without another lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(16);
+    /// veb.insert(3);
+    /// veb.insert(9);
+    ///
+    /// assert_eq!(
+    ///     veb.predecessors(&[15, 10, 9, 4, 3, 100]),
+    ///     vec![Some(9), Some(9), Some(3), Some(3), None, None]
+    /// );
+    /// ```
+    pub fn predecessors(&self, xs: &[usize]) -> Vec<Option<usize>> {
+        let mut previous: Option<(usize, Option<usize>)> = None;
+        xs.iter()
+            .map(|&x| {
+                if x >= self.universe_size {
+                    return None;
+                }
+                let answer = match previous {
+                    Some((prev_x, prev)) if x <= prev_x && prev.is_none_or(|p| x > p) => prev,
+                    _ => self.predecessor(&x),
+                };
+                previous = Some((x, answer));
+                answer
+            })
+            .collect()
+    }
+
+    /// Find the smallest element greater than or equal to `x`
+    ///
+    /// This is the inclusive counterpart of [`vEB::successor`]: it returns
+    /// `x` itself if present and the successor of `x` otherwise. Queries at
+    /// or beyond the universe size return `None`, since no element can be
+    /// that large.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The lower bound of the query
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(8);
+    /// veb.insert(3);
+    /// veb.insert(5);
+    ///
+    /// assert_eq!(veb.first_at_least(&3), Some(3));
+    /// assert_eq!(veb.first_at_least(&4), Some(5));
+    /// assert_eq!(veb.first_at_least(&6), None);
+    /// assert_eq!(veb.first_at_least(&100), None);
+    /// ```
+    pub fn first_at_least(&self, x: &usize) -> Option<usize> {
+        if self.contains(x) {
+            Some(*x)
+        } else {
+            self.successor(x)
+        }
+    }
+
+    /// Find the smallest value greater than or equal to `x` that is absent
+    ///
+    /// This is the complement of [`vEB::first_at_least`], suited to handing
+    /// out free IDs: it returns `x` itself if `x` is absent, and otherwise
+    /// the first gap after the run of consecutive elements starting at `x`.
+    /// Returns `None` if every value from `x` to the end of the universe is
+    /// present, including when `x` is at or beyond the universe size.
+    ///
+    /// The run is stepped over one [`vEB::successor`] call at a time, so
+    /// the cost is O(k log log u) for a run of `k` consecutive elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The lower bound of the query
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(8);
+    /// for x in [0, 1, 2, 4, 6, 7] {
+    ///     veb.insert(x);
+    /// }
+    ///
+    /// assert_eq!(veb.first_absent(0), Some(3));
+    /// assert_eq!(veb.first_absent(3), Some(3));
+    /// assert_eq!(veb.first_absent(4), Some(5));
+    /// assert_eq!(veb.first_absent(6), None);
+    /// assert_eq!(veb.first_absent(8), None);
+    /// ```
+    pub fn first_absent(&self, x: usize) -> Option<usize> {
+        if x >= self.universe_size {
+            return None;
+        }
+        if !self.contains(&x) {
+            return Some(x);
+        }
+
+        let mut last = x;
+        while let Some(next) = self.successor(&last) {
+            if next != last + 1 {
+                break;
+            }
+            last = next;
+        }
+        Some(last + 1).filter(|&gap| gap < self.universe_size)
+    }
+
+    /// Collect the elements in the inclusive range `[low, high]`
+    ///
+    /// Elements are returned in ascending order. Rather than re-descending
+    /// from the top for every element as repeated `successor` calls would,
+    /// this walks the summary to visit only the non-empty clusters that
+    /// overlap the range. Bounds beyond the universe are clamped, and an
+    /// empty range (`low > high`) yields no elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `low` - The smallest value to include
+    /// * `high` - The largest value to include
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(16);
+    /// for x in [1, 4, 6, 9, 15] {
+    ///     veb.insert(x);
+    /// }
+    ///
+    /// assert_eq!(veb.range(4, 9), vec![4, 6, 9]);
+    /// assert_eq!(veb.range(10, 100), vec![15]);
+    /// assert!(veb.range(7, 8).is_empty());
+    /// ```
+    pub fn range(&self, low: usize, high: usize) -> Vec<usize> {
+        let mut result = Vec::new();
+        if low < self.universe_size {
+            let high = high.min(self.universe_size - 1);
+            self.collect_range(low, high, &mut result);
+        }
+        result
+    }
+
+    fn collect_range(&self, low: usize, high: usize, result: &mut Vec<usize>) {
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) if low <= high => (min, max),
+            _ => return,
+        };
+
+        if (low..=high).contains(&min) {
+            result.push(min);
+        }
+
+        if self.universe_size == 2 {
+            if max != min && (low..=high).contains(&max) {
+                result.push(max);
+            }
+            return;
+        }
+
+        let (first_cluster, last_cluster) = (self.high(low), self.high(high));
+        let summary = self.summary.as_ref().unwrap();
+        let mut next_cluster = summary.first_at_least(&first_cluster);
+
+        while let Some(i) = next_cluster.filter(|&i| i <= last_cluster) {
+            if let Some(cluster) = &self.clusters[i] {
+                let cluster_low = if i == first_cluster { self.low(low) } else { 0 };
+                let cluster_high = if i == last_cluster {
+                    self.low(high)
+                } else {
+                    cluster.universe_size - 1
+                };
+
+                let start = result.len();
+                cluster.collect_range(cluster_low, cluster_high, result);
+                for x in &mut result[start..] {
+                    *x = self.index(i, *x);
                 }
-            } else if self.min.is_some() && *x > self.min.unwrap() {
-                return self.min;
             }
+            next_cluster = summary.successor(&i);
         }
-        None
     }
 
     /// Get the universe size of the vEB tree
@@ -1346,6 +2720,144 @@ impl vEB {
         self.element_count
     }
 
+    /// Recompute the number of elements from the tree's contents
+    ///
+    /// Unlike [`vEB::size`], which returns a counter maintained by `insert`
+    /// and `delete`, this walks the min/max fields and every cluster to count
+    /// the elements actually stored. It is O(u) and intended for tests and
+    /// debugging: any disagreement with `size()` indicates a bug.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(16);
+    /// veb.insert(3);
+    /// veb.insert(9);
+    /// veb.delete(&3);
+    ///
+    /// assert_eq!(veb.recount(), 1);
+    /// assert_eq!(veb.recount(), veb.size());
+    /// ```
+    pub fn recount(&self) -> usize {
+        match (self.min, self.max) {
+            (None, _) => 0,
+            (Some(min), Some(max)) if self.universe_size == 2 => {
+                if min == max {
+                    1
+                } else {
+                    2
+                }
+            }
+            // The min is stored only in this node; everything else lives in
+            // the clusters
+            _ => {
+                1 + self
+                    .clusters
+                    .iter()
+                    .flatten()
+                    .map(|cluster| cluster.recount())
+                    .sum::<usize>()
+            }
+        }
+    }
+
+    /// Verify the structural invariants of the tree, recursively
+    ///
+    /// Checks that `min` and `max` are set together and lie in the
+    /// universe, that the min is kept out of the clusters (every clustered
+    /// element is greater than it), that the summary holds exactly the
+    /// indices of the non-empty clusters, that `max` is the largest
+    /// clustered element (or the min when the clusters are empty), and that
+    /// [`vEB::size`] matches [`vEB::recount`]. The same checks are applied
+    /// to the summary and every cluster. Like `recount` this is O(u) and
+    /// meant for tests and debugging; the error message names the first
+    /// violated invariant and where in the structure it was found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jangal::vEB;
+    ///
+    /// let mut veb = vEB::new(16);
+    /// for x in [7, 2, 12, 3] {
+    ///     veb.insert(x);
+    /// }
+    /// veb.delete(&2);
+    ///
+    /// assert_eq!(veb.check_invariants(), Ok(()));
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), String> {
+        self.check_invariants_at("root")
+    }
+
+    fn check_invariants_at(&self, path: &str) -> Result<(), String> {
+        let fail = |message: String| {
+            Err(format!(
+                "{} (u = {}): {}",
+                path, self.universe_size, message
+            ))
+        };
+
+        let (min, max) = match (self.min, self.max) {
+            (None, None) => (None, None),
+            (Some(min), Some(max)) if min <= max && max < self.universe_size => {
+                (Some(min), Some(max))
+            }
+            (min, max) => return fail(format!("invalid min {:?} / max {:?}", min, max)),
+        };
+        if self.element_count != self.recount() {
+            return fail(format!(
+                "size {} but {} elements stored",
+                self.element_count,
+                self.recount()
+            ));
+        }
+        if self.universe_size == 2 {
+            return Ok(());
+        }
+
+        let summary = match &self.summary {
+            Some(summary) => summary,
+            None => return fail("missing summary".to_string()),
+        };
+        let mut largest = None;
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            let cluster_min = cluster.as_ref().and_then(|cluster| cluster.min);
+            if summary.contains(&i) != cluster_min.is_some() {
+                return fail(format!(
+                    "summary disagrees with cluster {} (empty: {})",
+                    i,
+                    cluster_min.is_none()
+                ));
+            }
+            let Some(cluster) = cluster else {
+                continue;
+            };
+            if let Some(cluster_min) = cluster_min {
+                let smallest = self.index(i, cluster_min);
+                if min.is_none_or(|min| smallest <= min) {
+                    return fail(format!(
+                        "cluster {} holds {}, which is not above min {:?}",
+                        i, smallest, min
+                    ));
+                }
+                largest = cluster.max.map(|cluster_max| self.index(i, cluster_max));
+            }
+            cluster.check_invariants_at(&format!("{} / cluster {}", path, i))?;
+        }
+        if max != largest.or(min) {
+            return fail(format!(
+                "max is {:?} but the largest element is {:?}",
+                max,
+                largest.or(min)
+            ));
+        }
+
+        summary.check_invariants_at(&format!("{} / summary", path))
+    }
+
     /// Check if the vEB tree is empty
     ///
     /// # Examples
@@ -1537,6 +3049,475 @@ mod tests {
         assert!(bst.search(&9).is_some());
     }
 
+    #[test]
+    fn test_multi_bst_counts() {
+        let mut bst = MultiBST::new();
+        for x in [5, 3, 5, 8, 5, 3] {
+            bst.insert(x);
+        }
+        assert_eq!(bst.size(), 6);
+        assert_eq!(bst.distinct_size(), 3);
+        assert_eq!(bst.as_bst().size(), 3);
+        assert_eq!(bst.count(&5), 3);
+        assert_eq!(bst.count(&3), 2);
+        assert_eq!(bst.count(&4), 0);
+
+        bst.delete(&5);
+        bst.delete(&4);
+        assert_eq!(bst.count(&5), 2);
+        assert_eq!(bst.size(), 5);
+
+        bst.delete(&8);
+        assert!(!bst.contains(&8));
+        assert!(bst.as_bst().search(&8).is_none());
+        assert_eq!(bst.distinct_size(), 2);
+
+        for _ in 0..4 {
+            bst.delete(&3);
+            bst.delete(&5);
+        }
+        assert!(bst.is_empty());
+        assert!(bst.as_bst().is_empty());
+
+        // Counts live on nodes and follow values moved by two-child deletes
+        let mut bst = MultiBST::new();
+        for (x, times) in [(50, 1), (30, 2), (70, 3), (60, 4), (80, 5), (65, 6)] {
+            for _ in 0..times {
+                bst.insert(x);
+            }
+        }
+        let node_id = bst.as_bst().search(&60).unwrap();
+        assert_eq!(bst.node_count(node_id), 4);
+        bst.delete(&50);
+        assert_eq!(bst.count(&50), 0);
+        for (x, times) in [(30, 2), (70, 3), (60, 4), (80, 5), (65, 6)] {
+            assert_eq!(bst.count(&x), times);
+        }
+        for _ in 0..3 {
+            bst.delete(&70);
+        }
+        assert_eq!(bst.count(&80), 5);
+        assert_eq!(bst.count(&65), 6);
+        assert_eq!(bst.size(), 17);
+        assert!(bst.as_bst().as_tree().is_tree());
+        let sorted = bst.into_sorted_vec();
+        assert_eq!(sorted.len(), 17);
+        assert!(sorted.is_sorted());
+        assert_eq!(sorted.iter().filter(|&&x| x == 65).count(), 6);
+    }
+
+    #[test]
+    fn test_keyed_bst() {
+        #[derive(Debug, PartialEq)]
+        struct Item {
+            priority: i32,
+            label: char,
+        }
+
+        let mut bst = KeyedBST::new_by_key(|item: &Item| item.priority);
+        for (priority, label) in [(5, 'e'), (2, 'b'), (8, 'h'), (1, 'a'), (3, 'c'), (7, 'g')] {
+            assert!(bst.insert(Item { priority, label }));
+        }
+        assert!(!bst.insert(Item {
+            priority: 5,
+            label: 'x'
+        }));
+        assert_eq!(bst.size(), 6);
+        assert_eq!(bst.get_by_key(&5).unwrap().label, 'e');
+
+        // Two children: replaced by the in-order successor
+        let removed = bst.delete_by_key(&2).unwrap();
+        assert_eq!(removed.label, 'b');
+        // Root with two children
+        assert_eq!(bst.delete_by_key(&5).unwrap().label, 'e');
+        assert_eq!(bst.delete_by_key(&5), None);
+
+        let labels: String = bst.inorder().iter().map(|node| node.value.label).collect();
+        assert_eq!(labels, "acgh");
+        for key in [1, 3, 7, 8] {
+            assert!(bst.search_by_key(&key).is_some());
+            assert!(bst.delete_by_key(&key).is_some());
+        }
+        assert!(bst.is_empty());
+        assert_eq!(bst.as_tree().root_id(), None);
+    }
+
+    #[test]
+    fn test_veb_batch_lookups() {
+        let mut rng = Lcg(0xbeef);
+        let universe = 64;
+        let mut veb = vEB::new(universe);
+        for _ in 0..20 {
+            veb.insert(rng.below(universe));
+        }
+
+        let unsorted: Vec<usize> = (0..40).map(|_| rng.below(universe + 8)).collect();
+        let mut ascending = unsorted.clone();
+        ascending.sort_unstable();
+        let descending: Vec<usize> = ascending.iter().rev().copied().collect();
+
+        for queries in [unsorted, ascending, descending] {
+            let successors: Vec<_> = queries.iter().map(|x| veb.successor(x)).collect();
+            let predecessors: Vec<_> = queries.iter().map(|x| veb.predecessor(x)).collect();
+            assert_eq!(veb.successors(&queries), successors);
+            assert_eq!(veb.predecessors(&queries), predecessors);
+        }
+        assert!(veb.successors(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_bst_delete_returns_value() {
+        let mut bst = BST::new();
+        for x in [50, 30, 70, 20, 40, 60, 80, 35] {
+            bst.insert(x);
+        }
+
+        // Two children, one child, leaf, root and absent element
+        assert_eq!(bst.delete(&30), Some(30));
+        assert_eq!(bst.delete(&40), Some(40));
+        assert_eq!(bst.delete(&80), Some(80));
+        assert_eq!(bst.delete(&50), Some(50));
+        assert_eq!(bst.delete(&50), None);
+        assert_eq!(bst.delete(&99), None);
+
+        assert_eq!(bst.size(), 4);
+        let values: Vec<i32> = bst.inorder().iter().map(|node| node.value).collect();
+        assert_eq!(values, vec![20, 35, 60, 70]);
+    }
+
+    #[test]
+    fn test_bst_delete_keeps_children_sets_in_sync() {
+        // Deleting a one-child node must move the child into the parent's
+        // `children` set, not just its left/right link
+        let mut bst = BST::new();
+        for x in [50, 30, 70, 20, 60, 10, 65] {
+            bst.insert(x);
+        }
+        assert_eq!(bst.delete(&30), Some(30));
+        assert!(bst.as_tree().is_tree());
+        assert_eq!(bst.delete(&60), Some(60));
+        assert!(bst.as_tree().is_tree());
+
+        let root = bst.as_tree().root().unwrap();
+        let mut children: Vec<i32> = root
+            .children()
+            .into_iter()
+            .map(|id| bst.as_tree()[id].value)
+            .collect();
+        children.sort_unstable();
+        assert_eq!(children, vec![20, 70]);
+        let seventy = bst.search(&70).unwrap();
+        let sixty_five = bst.search(&65).unwrap();
+        assert_eq!(bst.as_tree()[seventy].children(), vec![sixty_five]);
+
+        // Deleting a one-child root hands the root over cleanly
+        let mut bst = BST::new();
+        for x in [10, 20, 30] {
+            bst.insert(x);
+        }
+        assert_eq!(bst.delete(&10), Some(10));
+        assert!(bst.as_tree().is_tree());
+        assert_eq!(bst.as_tree().root().unwrap().value, 20);
+    }
+
+    #[test]
+    fn test_bst_pop_min_and_max() {
+        let mut rng = Lcg(7);
+        let mut values: Vec<i32> = (0..200).collect();
+        rng.shuffle(&mut values);
+
+        let mut bst = BST::new();
+        for &x in &values {
+            bst.insert(x);
+        }
+
+        assert_eq!(bst.pop_max(), Some(199));
+        assert_eq!(bst.pop_max(), Some(198));
+        let mut drained = Vec::new();
+        while let Some(x) = bst.pop_min() {
+            drained.push(x);
+            assert!(bst.is_empty() || bst.as_tree().is_tree());
+        }
+        assert_eq!(drained, (0..198).collect::<Vec<_>>());
+        assert_eq!(bst.as_tree().root_id(), None);
+        assert_eq!(bst.pop_max(), None);
+    }
+
+    #[test]
+    fn test_bst_cached_height() {
+        let recount = |bst: &BST<i32>| bst.root().map_or(0, |id| bst.bst_height_recursive(id));
+
+        let mut rng = Lcg(0x5eed);
+        let mut bst = BST::new();
+        for _ in 0..400 {
+            let x = (rng.next() % 100) as i32;
+            if rng.next().is_multiple_of(3) {
+                bst.delete(&x);
+            } else {
+                bst.insert(x);
+            }
+            assert_eq!(bst.height(), recount(&bst));
+        }
+        while bst.pop_min().is_some() {
+            assert_eq!(bst.height(), recount(&bst));
+        }
+        assert_eq!(bst.height(), 0);
+
+        // Editing links directly falls back to recounting until the next
+        // insert rebuilds the cache
+        let mut bst = BST::from_sorted(1..=7);
+        assert_eq!(bst.height(), 3);
+        let root = bst.root().unwrap();
+        let left = bst.get_node(root).unwrap().left().unwrap();
+        bst.get_node_mut(root).unwrap().clear_left();
+        bst.as_tree_mut()
+            .get_node_mut(root)
+            .unwrap()
+            .remove_child(left);
+        assert_eq!(bst.height(), 3);
+        bst.get_node_mut(root).unwrap().clear_right();
+        assert_eq!(bst.height(), 1);
+        bst.insert(10);
+        assert!(!bst.heights_stale);
+        assert_eq!(bst.height(), 2);
+    }
+
+    #[test]
+    fn test_bst_boundary() {
+        let empty: BST<i32> = BST::new();
+        assert!(empty.boundary().is_empty());
+
+        let single = BST::from_sorted([1]);
+        assert_eq!(single.boundary(), vec![&1]);
+
+        // Only a right spine: no left boundary, and the root is not
+        // repeated on the right boundary
+        let mut spine = BST::new();
+        for x in [1, 2, 3, 4] {
+            spine.insert(x);
+        }
+        assert_eq!(spine.boundary(), vec![&1, &4, &3, &2]);
+
+        //            20
+        //          /    \
+        //         8      22
+        //        / \       \
+        //       4   12     25
+        //          /  \
+        //         10   14
+        let mut bst = BST::new();
+        for x in [20, 8, 22, 4, 12, 25, 10, 14] {
+            bst.insert(x);
+        }
+        assert_eq!(bst.boundary(), vec![&20, &8, &4, &10, &14, &25, &22]);
+
+        // The left boundary turns right where there is no left child
+        bst.delete(&4);
+        assert_eq!(bst.boundary(), vec![&20, &8, &12, &10, &14, &25, &22]);
+    }
+
+    #[test]
+    fn test_bst_vertical_order() {
+        let empty: BST<i32> = BST::new();
+        assert!(empty.vertical_order().is_empty());
+
+        //          50
+        //        /    \
+        //      30      70
+        //     /  \    /  \
+        //   20   40  60   80
+        //          \ /
+        //         45 55
+        let mut bst = BST::new();
+        for x in [50, 30, 70, 20, 40, 60, 80, 45, 55] {
+            bst.insert(x);
+        }
+        // 40 and 60 share row 2 of column 0 and are ordered by value
+        assert_eq!(
+            bst.vertical_order(),
+            vec![
+                vec![&20],
+                vec![&30, &55],
+                vec![&50, &40, &60],
+                vec![&70, &45],
+                vec![&80],
+            ]
+        );
+
+        // A left spine puts each element in its own column
+        let mut spine = BST::new();
+        for x in [3, 2, 1] {
+            spine.insert(x);
+        }
+        assert_eq!(spine.vertical_order(), vec![vec![&1], vec![&2], vec![&3]]);
+    }
+
+    #[test]
+    fn test_bst_shape_round_trip() {
+        let mut rng = Lcg(0xface);
+        let mut values: Vec<i32> = (0..300).collect();
+        rng.shuffle(&mut values);
+        let mut random = BST::new();
+        for &x in &values {
+            random.insert(x);
+        }
+
+        let mut spine = BST::new();
+        for x in 0..50 {
+            spine.insert(x);
+        }
+
+        for bst in [random, spine, BST::from_sorted(0..100), BST::new()] {
+            let shape = bst.serialize_shape();
+            let copy = BST::deserialize_shape(shape.clone()).unwrap();
+            assert_eq!(copy.height(), bst.height());
+            assert_eq!(copy.serialize_shape(), shape);
+            assert!(copy.is_empty() || copy.as_tree().is_tree());
+
+            // The copy keeps working as a BST with its own height cache
+            let mut copy = copy;
+            copy.insert(-1);
+            let recount = copy.root().map_or(0, |id| copy.bst_height_recursive(id));
+            assert_eq!(copy.height(), recount);
+        }
+
+        for bad in [
+            vec![Some(5), Some(7)],
+            vec![Some(5), None, Some(3)],
+            vec![Some(5), Some(5)],
+            vec![Some(5), Some(3), Some(8), None, Some(6)],
+        ] {
+            assert_eq!(
+                BST::deserialize_shape(bad).unwrap_err(),
+                TreeError::NotSearchOrder
+            );
+        }
+    }
+
+    #[test]
+    fn test_bst_second_min_and_max() {
+        let mut bst = BST::new();
+        assert_eq!(bst.second_min(), None);
+        assert_eq!(bst.second_max(), None);
+        bst.insert(10);
+        bst.insert(10);
+        assert_eq!(bst.second_min(), None);
+        assert_eq!(bst.second_max(), None);
+
+        // The extremes at the root, with and without a subtree to descend
+        bst.insert(20);
+        assert_eq!(bst.second_min(), Some(&20));
+        assert_eq!(bst.second_max(), Some(&10));
+
+        let mut rng = Lcg(0xdec0de);
+        let mut values: Vec<i32> = (0..100).map(|x| x * 3).collect();
+        rng.shuffle(&mut values);
+        let mut bst = BST::new();
+        let mut sorted: Vec<i32> = Vec::new();
+        for &x in &values {
+            bst.insert(x);
+            sorted.push(x);
+            sorted.sort_unstable();
+            assert_eq!(bst.second_min(), sorted.get(1));
+            let second_max = sorted.len().checked_sub(2).map(|i| &sorted[i]);
+            assert_eq!(bst.second_max(), second_max);
+        }
+    }
+
+    #[test]
+    fn test_bst_nearest() {
+        let mut bst: BST<u32> = BST::new();
+        assert_eq!(bst.nearest(&5), None);
+        assert!(bst.k_nearest(&5, 3).is_empty());
+
+        for x in [50, 20, 80, 10, 30, 70, 90] {
+            bst.insert(x);
+        }
+
+        assert_eq!(bst.nearest(&30), Some(&30));
+        assert_eq!(bst.nearest(&26), Some(&30));
+        assert_eq!(bst.nearest(&25), Some(&20));
+        assert_eq!(bst.nearest(&0), Some(&10));
+        assert_eq!(bst.nearest(&1000), Some(&90));
+
+        assert_eq!(bst.k_nearest(&60, 0), Vec::<&u32>::new());
+        assert_eq!(bst.k_nearest(&60, 2), vec![&50, &70]);
+        assert_eq!(bst.k_nearest(&75, 4), vec![&70, &80, &90, &50]);
+        assert_eq!(bst.k_nearest(&0, 3), vec![&10, &20, &30]);
+        assert_eq!(
+            bst.k_nearest(&100, 10),
+            vec![&90, &80, &70, &50, &30, &20, &10]
+        );
+    }
+
+    #[test]
+    fn test_bst_bulk_load_sorted() {
+        let bst = BST::from_sorted(0..1000);
+        assert_eq!(bst.size(), 1000);
+        assert_eq!(bst.height(), 10);
+        assert!(bst.as_tree().is_tree());
+        assert_eq!(bst.min(), Some(&0));
+        assert!(bst.contains(&500));
+
+        // Merges with existing elements, drops duplicates and sorts
+        // unsorted input
+        let mut bst = BST::new();
+        for x in [50, 10, 90] {
+            bst.insert(x);
+        }
+        let ids: Vec<Number> = [50, 10, 90]
+            .iter()
+            .map(|x| bst.search(x).unwrap())
+            .collect();
+        bst.bulk_load_sorted([90, 20, 10, 60, 60, 95]);
+        assert_eq!(bst.size(), 6);
+        assert_eq!(bst.height(), 3);
+        assert!(bst.as_tree().is_tree());
+        // Existing nodes keep their IDs
+        for (x, id) in [50, 10, 90].iter().zip(ids) {
+            assert_eq!(bst.search(x), Some(id));
+        }
+        assert_eq!(bst.delete(&60), Some(60));
+        bst.insert(55);
+        assert_eq!(bst.into_sorted_vec(), vec![10, 20, 50, 55, 90, 95]);
+
+        let mut empty: BST<i32> = BST::from_sorted([]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.root(), None);
+        empty.bulk_load_sorted([]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_bst_conversions() {
+        let mut bst = BST::new();
+        for x in [8, 3, 10, 1, 6, 14, 4, 7, 13] {
+            bst.insert(x);
+        }
+
+        let veb = bst.to_veb().unwrap();
+        assert_eq!(veb.universe_size(), 16);
+        assert_eq!(veb.size(), bst.size());
+        assert_eq!(veb.range(0, 15), vec![1, 3, 4, 6, 7, 8, 10, 13, 14]);
+
+        assert_eq!(bst.into_sorted_vec(), vec![1, 3, 4, 6, 7, 8, 10, 13, 14]);
+
+        // Universe boundaries
+        let mut bst = BST::new();
+        assert_eq!(bst.to_veb().unwrap().universe_size(), 2);
+        bst.insert(7);
+        assert_eq!(bst.to_veb().unwrap().universe_size(), 8);
+        bst.insert(8);
+        assert_eq!(bst.to_veb().unwrap().universe_size(), 16);
+
+        // Universes that do not fit in a usize
+        let mut bst = BST::new();
+        bst.insert(usize::MAX / 2 + 1);
+        assert!(bst.to_veb().is_none());
+        bst.insert(usize::MAX);
+        assert!(bst.to_veb().is_none());
+    }
+
     #[test]
     fn test_bst_root_deletion_with_one_child() {
         let mut bst = BST::new();
@@ -1584,6 +3565,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_bst_generic_types() {
         // Test with strings
         let mut bst_str = BST::new();
@@ -1606,12 +3588,12 @@ mod tests {
         impl Eq for FloatWrapper {}
         impl PartialOrd for FloatWrapper {
             fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-                self.0.partial_cmp(&other.0)
+                Some(self.cmp(other))
             }
         }
         impl Ord for FloatWrapper {
             fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-                self.0.partial_cmp(&other.0).unwrap()
+                crate::total_cmp_f64(self.0, other.0)
             }
         }
 
@@ -1729,6 +3711,60 @@ mod tests {
         assert_eq!(veb.predecessor(&current), None);
     }
 
+    #[test]
+    fn test_veb_first_at_least() {
+        let mut veb = vEB::new(16);
+        assert_eq!(veb.first_at_least(&0), None);
+
+        for x in [2, 7, 8, 15] {
+            veb.insert(x);
+        }
+
+        // Inclusive, unlike the strict successor
+        assert_eq!(veb.first_at_least(&7), Some(7));
+        assert_eq!(veb.successor(&7), Some(8));
+
+        let expected = [2, 2, 2, 7, 7, 7, 7, 7, 8, 15, 15, 15, 15, 15, 15, 15];
+        for (x, want) in expected.iter().enumerate() {
+            assert_eq!(veb.first_at_least(&x), Some(*want));
+        }
+
+        // Out-of-range queries
+        assert_eq!(veb.first_at_least(&16), None);
+        assert_eq!(veb.first_at_least(&usize::MAX), None);
+    }
+
+    #[test]
+    fn test_veb_first_absent() {
+        let mut veb = vEB::new(256);
+        assert_eq!(veb.first_absent(0), Some(0));
+        assert_eq!(veb.first_absent(255), Some(255));
+        assert_eq!(veb.first_absent(256), None);
+        assert_eq!(veb.first_absent(usize::MAX), None);
+
+        // Densely populated: every value except a few holes
+        let holes = [0, 17, 18, 64, 200, 255];
+        for x in (0..256).filter(|x| !holes.contains(x)) {
+            veb.insert(x);
+        }
+        for x in 0..256 {
+            let expected = (x..256).find(|y| holes.contains(y));
+            assert_eq!(veb.first_absent(x), expected, "from {}", x);
+        }
+
+        // Acting as an ID allocator until the universe is full
+        let mut allocated = Vec::new();
+        while let Some(id) = veb.first_absent(0) {
+            veb.insert(id);
+            allocated.push(id);
+        }
+        assert_eq!(allocated, holes);
+        assert_eq!(veb.size(), 256);
+        for x in [0, 100, 255] {
+            assert_eq!(veb.first_absent(x), None);
+        }
+    }
+
     #[test]
     fn test_veb_deletion_and_recovery() {
         let mut veb = vEB::new(16);
@@ -1753,6 +3789,181 @@ mod tests {
         assert_eq!(veb.predecessor(&7), Some(5));
     }
 
+    /// Minimal deterministic PRNG (64-bit LCG) for property-style tests
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            self.0 >> 33
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn shuffle<T>(&mut self, items: &mut [T]) {
+            for i in (1..items.len()).rev() {
+                items.swap(i, self.below(i + 1));
+            }
+        }
+    }
+
+    fn assert_veb_matches(veb: &vEB, oracle: &std::collections::BTreeSet<usize>) {
+        assert_eq!(veb.size(), oracle.len());
+        assert_eq!(veb.recount(), veb.size());
+        assert_eq!(veb.check_invariants(), Ok(()));
+        assert_eq!(veb.is_empty(), oracle.is_empty());
+        assert_eq!(veb.minimum(), oracle.iter().next().copied());
+        assert_eq!(veb.maximum(), oracle.iter().next_back().copied());
+        for x in 0..veb.universe_size() {
+            assert_eq!(veb.contains(&x), oracle.contains(&x), "contains({})", x);
+            assert_eq!(
+                veb.successor(&x),
+                oracle.range(x + 1..).next().copied(),
+                "successor({})",
+                x
+            );
+            assert_eq!(
+                veb.predecessor(&x),
+                oracle.range(..x).next_back().copied(),
+                "predecessor({})",
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn test_veb_against_btreeset_oracle() {
+        use std::collections::BTreeSet;
+
+        let mut rng = Lcg(0x5eed);
+        for &u in &[2, 4, 8, 16, 32, 64, 256] {
+            for _ in 0..25 {
+                let mut veb = vEB::new(u);
+                let mut oracle = BTreeSet::new();
+
+                let mut elements: Vec<usize> = (0..u).filter(|_| rng.below(2) == 0).collect();
+                rng.shuffle(&mut elements);
+                for &x in &elements {
+                    veb.insert(x);
+                    oracle.insert(x);
+                    assert_veb_matches(&veb, &oracle);
+                }
+
+                rng.shuffle(&mut elements);
+                for &x in &elements {
+                    veb.delete(&x);
+                    oracle.remove(&x);
+                    assert_veb_matches(&veb, &oracle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_veb_check_invariants_detects_corruption() {
+        let build = || {
+            let mut veb = vEB::new(16);
+            for x in [1, 6, 9, 14] {
+                veb.insert(x);
+            }
+            assert_eq!(veb.check_invariants(), Ok(()));
+            veb
+        };
+
+        // The min must not also be stored in a cluster
+        let mut veb = build();
+        veb.clusters[0].as_mut().unwrap().insert(1);
+        veb.summary.as_mut().unwrap().insert(0);
+        veb.element_count += 1;
+        assert!(veb
+            .check_invariants()
+            .unwrap_err()
+            .contains("not above min"));
+
+        // Every non-empty cluster must be in the summary
+        let mut veb = build();
+        veb.summary.as_mut().unwrap().delete(&2);
+        assert!(veb
+            .check_invariants()
+            .unwrap_err()
+            .contains("summary disagrees"));
+
+        // The max must match the cluster contents
+        let mut veb = build();
+        veb.max = Some(15);
+        assert!(veb.check_invariants().unwrap_err().contains("max is"));
+
+        // The size counter must match the stored elements
+        let mut veb = build();
+        veb.element_count = 2;
+        assert!(veb.check_invariants().unwrap_err().contains("size 2"));
+
+        // Nested violations report where they were found
+        let mut veb = build();
+        veb.clusters[3].as_mut().unwrap().element_count = 5;
+        let error = veb.check_invariants().unwrap_err();
+        assert!(error.starts_with("root / cluster 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_veb_range() {
+        use std::collections::BTreeSet;
+
+        let mut rng = Lcg(0xa11);
+        for &u in &[2, 4, 16, 64, 128] {
+            let mut veb = vEB::new(u);
+            let mut oracle = BTreeSet::new();
+            for x in (0..u).filter(|_| rng.below(3) == 0) {
+                veb.insert(x);
+                oracle.insert(x);
+            }
+
+            for low in 0..u {
+                for high in low..u {
+                    let expected: Vec<usize> = oracle.range(low..=high).copied().collect();
+                    assert_eq!(veb.range(low, high), expected, "range({}, {})", low, high);
+                }
+            }
+
+            // Degenerate and out-of-range bounds
+            assert!(veb.range(1, 0).is_empty());
+            assert!(veb.range(u, u + 10).is_empty());
+            assert_eq!(
+                veb.range(0, usize::MAX),
+                oracle.iter().copied().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_veb_duplicate_insert_and_absent_delete() {
+        let mut veb = vEB::new(16);
+        veb.insert(3);
+        veb.insert(9);
+        veb.insert(9);
+        veb.insert(3);
+        assert_eq!(veb.size(), 2);
+
+        veb.delete(&4);
+        veb.delete(&20);
+        assert_eq!(veb.size(), 2);
+        assert_eq!(veb.minimum(), Some(3));
+        assert_eq!(veb.maximum(), Some(9));
+
+        // Inserting below the min must not leave the old min duplicated
+        veb.insert(1);
+        veb.delete(&1);
+        veb.delete(&3);
+        assert_eq!(veb.size(), 1);
+        assert_eq!(veb.minimum(), Some(9));
+        assert_eq!(veb.maximum(), Some(9));
+    }
+
     #[test]
     fn test_veb_cluster_size() {
         let mut veb = vEB::new(4);
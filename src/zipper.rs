@@ -0,0 +1,220 @@
+use crate::{FloatId, Node, Number, Tree};
+
+/// A cursor for moving around a [`Tree`] and editing it in place
+///
+/// The zipper borrows the tree mutably and keeps track of a focused node.
+/// [`TreeZipper::up`], [`TreeZipper::down`], [`TreeZipper::left_sibling`]
+/// and [`TreeZipper::right_sibling`] move the focus along parent and child
+/// links. Siblings are ordered by ascending ID, as in [`Tree::dfs_sorted`].
+///
+/// The focus is always a node in the tree. A move that has nowhere to go
+/// (`up` at the root, `down` at a leaf or past the last child, a sibling
+/// move at either end or at the root) returns `false` and leaves the focus
+/// where it was.
+///
+/// # Examples
+///
+/// ```
+/// use jangal::Tree;
+///
+/// //     0
+/// //    / \
+/// //   1   2
+/// //   |
+/// //   3
+/// let mut tree = Tree::from_parent_array(&[None, Some(0), Some(0), Some(1)]).unwrap();
+///
+/// let mut zipper = tree.zipper().unwrap();
+/// assert!(zipper.down(0));
+/// assert!(zipper.down(0));
+/// assert_eq!(*zipper.value(), 3);
+/// assert!(!zipper.down(0));
+///
+/// assert!(zipper.up());
+/// assert!(zipper.right_sibling());
+/// *zipper.value_mut() = 20;
+/// assert!(!zipper.right_sibling());
+///
+/// assert_eq!(tree[2.0].value, 20);
+/// ```
+pub struct TreeZipper<'a, T> {
+    tree: &'a mut Tree<T>,
+    focus: FloatId,
+}
+
+impl<T> Tree<T> {
+    /// Open a [`TreeZipper`] focused on the root
+    ///
+    /// Returns `None` if the tree has no root.
+    pub fn zipper(&mut self) -> Option<TreeZipper<'_, T>> {
+        let root_id = self.root_id()?;
+        TreeZipper::new(self, root_id)
+    }
+}
+
+impl<'a, T> TreeZipper<'a, T> {
+    /// Open a zipper focused on the node with the given ID
+    ///
+    /// Returns `None` if no such node exists.
+    pub fn new(tree: &'a mut Tree<T>, focus: Number) -> Option<Self> {
+        let focus = FloatId::from(focus);
+        if !tree.nodes.contains_key(&focus) {
+            return None;
+        }
+        Some(Self { tree, focus })
+    }
+
+    /// Get the ID of the focused node
+    pub fn focus(&self) -> Number {
+        self.focus.value()
+    }
+
+    /// Get the focused node
+    pub fn node(&self) -> &Node<T> {
+        &self.tree.nodes[&self.focus]
+    }
+
+    /// Get the focused node's value
+    pub fn value(&self) -> &T {
+        &self.node().value
+    }
+
+    /// Get a mutable reference to the focused node's value
+    pub fn value_mut(&mut self) -> &mut T {
+        &mut self
+            .tree
+            .nodes
+            .get_mut(&self.focus)
+            .expect("focus is always a node in the tree")
+            .value
+    }
+
+    /// Move the focus to the parent
+    pub fn up(&mut self) -> bool {
+        let parent = self.node().parent;
+        self.move_to(parent)
+    }
+
+    /// Move the focus to the child at `child_index` in ascending ID order
+    pub fn down(&mut self, child_index: usize) -> bool {
+        let child = self.children_of(self.focus).get(child_index).copied();
+        self.move_to(child)
+    }
+
+    /// Move the focus to the previous sibling in ascending ID order
+    pub fn left_sibling(&mut self) -> bool {
+        let sibling = self
+            .siblings()
+            .and_then(|(siblings, index)| index.checked_sub(1).map(|i| siblings[i]));
+        self.move_to(sibling)
+    }
+
+    /// Move the focus to the next sibling in ascending ID order
+    pub fn right_sibling(&mut self) -> bool {
+        let sibling = self
+            .siblings()
+            .and_then(|(siblings, index)| siblings.get(index + 1).copied());
+        self.move_to(sibling)
+    }
+
+    /// Get the underlying tree
+    pub fn tree(&self) -> &Tree<T> {
+        self.tree
+    }
+
+    fn move_to(&mut self, target: Option<FloatId>) -> bool {
+        match target {
+            Some(id) if self.tree.nodes.contains_key(&id) => {
+                self.focus = id;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Children of `id` that exist in the tree, in ascending ID order
+    fn children_of(&self, id: FloatId) -> Vec<FloatId> {
+        let mut children: Vec<FloatId> = self.tree.nodes[&id]
+            .children
+            .iter()
+            .copied()
+            .filter(|child_id| self.tree.nodes.contains_key(child_id))
+            .collect();
+        children.sort_unstable();
+        children
+    }
+
+    /// The focus's siblings (including itself) and its position among them
+    fn siblings(&self) -> Option<(Vec<FloatId>, usize)> {
+        let parent = self
+            .node()
+            .parent
+            .filter(|id| self.tree.nodes.contains_key(id))?;
+        let siblings = self.children_of(parent);
+        let index = siblings.iter().position(|&id| id == self.focus)?;
+        Some((siblings, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zipper_navigation_and_edits() {
+        //        0
+        //      / | \
+        //     1  2  3
+        //    / \
+        //   4   5
+        let mut tree =
+            Tree::from_parent_array(&[None, Some(0), Some(0), Some(0), Some(1), Some(1)]).unwrap();
+
+        let mut zipper = tree.zipper().unwrap();
+        assert_eq!(zipper.focus(), 0.0);
+        assert!(!zipper.up());
+        assert!(!zipper.left_sibling());
+        assert!(!zipper.right_sibling());
+        assert!(!zipper.down(3));
+        assert_eq!(zipper.focus(), 0.0);
+
+        assert!(zipper.down(2));
+        assert_eq!(zipper.focus(), 3.0);
+        assert!(!zipper.right_sibling());
+        assert!(zipper.left_sibling());
+        assert!(zipper.left_sibling());
+        assert_eq!(zipper.focus(), 1.0);
+        assert!(!zipper.left_sibling());
+
+        assert!(zipper.down(1));
+        assert_eq!(zipper.focus(), 5.0);
+        assert!(zipper.node().is_leaf());
+        assert!(!zipper.down(0));
+        *zipper.value_mut() *= 10;
+        assert!(zipper.left_sibling());
+        *zipper.value_mut() = 40;
+
+        assert!(zipper.up());
+        assert!(zipper.up());
+        assert_eq!(zipper.focus(), 0.0);
+        assert_eq!(zipper.tree().size(), 6);
+
+        assert_eq!(tree[5.0].value, 50);
+        assert_eq!(tree[4.0].value, 40);
+    }
+
+    #[test]
+    fn test_zipper_requires_existing_focus() {
+        let mut tree: Tree<i32> = Tree::new();
+        assert!(tree.zipper().is_none());
+
+        let mut tree = Tree::from_parent_array(&[None, Some(0)]).unwrap();
+        assert!(TreeZipper::new(&mut tree, 99.0).is_none());
+
+        // Dangling links are not followed
+        tree.get_node_mut(1.0).unwrap().add_child(42.0);
+        let mut zipper = TreeZipper::new(&mut tree, 1.0).unwrap();
+        assert!(!zipper.down(0));
+        assert_eq!(*zipper.value(), 1);
+    }
+}